@@ -0,0 +1,264 @@
+//! Reading `--log-format internal-json` output of a Nix client, which knows about the builds that
+//! are queued up and not running yet. The daemon doesn't tell anyone about those.
+
+use crate::store;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Activity types, from Nix's `logging.hh`.
+const ACT_COPY_PATH: u64 = 100;
+const ACT_FILE_TRANSFER: u64 = 101;
+const ACT_BUILDS: u64 = 104;
+const ACT_BUILD: u64 = 105;
+const ACT_BUILD_WAITING: u64 = 111;
+/// Result type with the name of the phase a build just started.
+const RES_SET_PHASE: u64 = 104;
+/// Result type with `[done, expected, running, failed]`.
+const RES_PROGRESS: u64 = 105;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum Message {
+    Start {
+        id: u64,
+        #[serde(rename = "type")]
+        kind: u64,
+        #[serde(default)]
+        text: String,
+        #[serde(default)]
+        parent: u64,
+        #[serde(default)]
+        fields: Vec<serde_json::Value>,
+    },
+    Stop {
+        id: u64,
+    },
+    Result {
+        id: u64,
+        #[serde(rename = "type")]
+        kind: u64,
+        #[serde(default)]
+        fields: Vec<serde_json::Value>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Overall progress of the client's builds.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Progress {
+    pub done: u64,
+    pub expected: u64,
+    pub running: u64,
+    pub failed: u64,
+}
+
+impl Progress {
+    /// Builds that haven't started yet.
+    pub fn queued(&self) -> u64 {
+        self.expected
+            .saturating_sub(self.done + self.running + self.failed)
+    }
+}
+
+/// Something the client is doing that reports how far along it is: a build, a download, or a
+/// copy between stores.
+#[derive(Debug, Clone)]
+pub struct Activity {
+    /// The derivation for builds, the store path or URL for transfers.
+    pub target: String,
+    /// The activity this one is a part of, 0 if none.
+    pub parent: u64,
+    pub is_build: bool,
+    /// Bytes for transfers.
+    pub done: u64,
+    pub expected: u64,
+    /// Phase the build is in, only for builds.
+    pub phase: Option<String>,
+    /// The remote builder a build was handed to, if it was.
+    pub machine: Option<String>,
+    /// When we saw it start, the log doesn't say.
+    pub started: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+pub struct Queue {
+    /// Derivations waiting for a build slot or a lock, by activity ID.
+    pub waiting: BTreeMap<u64, String>,
+    builds_id: Option<u64>,
+    pub progress: Option<Progress>,
+    /// Running builds and transfers, by activity ID.
+    pub activities: BTreeMap<u64, Activity>,
+}
+
+impl Queue {
+    /// Takes in a line of the log. Things that aren't activity messages are ignored.
+    pub fn push(&mut self, line: &str) {
+        let Some(json) = line.strip_prefix("@nix ") else {
+            return;
+        };
+        let Ok(message) = serde_json::from_str::<Message>(json) else {
+            return;
+        };
+
+        match message {
+            Message::Start { id, kind, .. } if kind == ACT_BUILDS => self.builds_id = Some(id),
+            Message::Start { id, kind, text, .. } if kind == ACT_BUILD_WAITING => {
+                if let Some(derivation) = derivation_in(&text) {
+                    self.waiting.insert(id, derivation);
+                }
+            }
+            Message::Start {
+                id,
+                kind,
+                text,
+                parent,
+                fields,
+            } if matches!(kind, ACT_BUILD | ACT_COPY_PATH | ACT_FILE_TRANSFER) => {
+                // the first field is the derivation, store path or URL
+                let target = match fields.first().and_then(|f| f.as_str()) {
+                    Some(path) => store::strip_store_dir(path).unwrap_or(path).to_string(),
+                    None => text,
+                };
+                // builds have `[drv, machine, round, rounds]`, the machine is empty for local ones
+                let machine = fields
+                    .get(1)
+                    .and_then(|f| f.as_str())
+                    .filter(|m| kind == ACT_BUILD && !m.is_empty())
+                    .map(|m| m.strip_prefix("ssh://").unwrap_or(m))
+                    .map(|m| m.strip_prefix("ssh-ng://").unwrap_or(m))
+                    .map(|m| m.rsplit('@').next().unwrap_or(m).to_string());
+                self.activities.insert(
+                    id,
+                    Activity {
+                        target,
+                        parent,
+                        is_build: kind == ACT_BUILD,
+                        done: 0,
+                        expected: 0,
+                        phase: None,
+                        machine,
+                        started: Utc::now(),
+                    },
+                );
+            }
+            Message::Stop { id } => {
+                self.waiting.remove(&id);
+                self.activities.remove(&id);
+                if self.builds_id == Some(id) {
+                    self.builds_id = None;
+                    self.progress = None;
+                }
+            }
+            Message::Result { id, kind, fields }
+                if kind == RES_PROGRESS && self.builds_id == Some(id) =>
+            {
+                let field = |i: usize| fields.get(i).and_then(|f| f.as_u64()).unwrap_or(0);
+                self.progress = Some(Progress {
+                    done: field(0),
+                    expected: field(1),
+                    running: field(2),
+                    failed: field(3),
+                });
+            }
+            Message::Result { id, kind, fields } if kind == RES_PROGRESS => {
+                if let Some(activity) = self.activities.get_mut(&id) {
+                    let field = |i: usize| fields.get(i).and_then(|f| f.as_u64()).unwrap_or(0);
+                    activity.done = field(0);
+                    activity.expected = field(1);
+                }
+            }
+            Message::Result { id, kind, fields } if kind == RES_SET_PHASE => {
+                if let Some(activity) = self.activities.get_mut(&id) {
+                    activity.phase = fields.first().and_then(|f| f.as_str()).map(String::from);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty() && self.progress.is_none_or(|p| p.queued() == 0)
+    }
+
+    /// The build activity of a derivation, if the client is the one building it.
+    pub fn build(&self, derivation: &str) -> Option<(u64, &Activity)> {
+        self.activities
+            .iter()
+            .find(|(_, a)| a.is_build && a.target == derivation)
+            .map(|(id, a)| (*id, a))
+    }
+
+    /// Builds the client handed to remote builders, by activity ID.
+    pub fn delegated(&self) -> impl Iterator<Item = (u64, &Activity)> {
+        self.activities
+            .iter()
+            .filter(|(_, a)| a.is_build && a.machine.is_some())
+            .map(|(id, a)| (*id, a))
+    }
+
+    /// Done and expected of everything under an activity that knows how far along it is.
+    pub fn progress_under(&self, id: u64) -> Option<(u64, u64)> {
+        self.activities
+            .iter()
+            .filter(|(child, _)| self.is_under(**child, id))
+            .filter(|(_, a)| a.expected > 0)
+            .map(|(_, a)| (a.done, a.expected))
+            .reduce(|a, b| (a.0 + b.0, a.1 + b.1))
+    }
+
+    fn is_under(&self, mut id: u64, ancestor: u64) -> bool {
+        while let Some(activity) = self.activities.get(&id) {
+            if activity.parent == ancestor {
+                return true;
+            }
+            id = activity.parent;
+        }
+        false
+    }
+
+    /// Downloads and copies. Downloads that are part of a copy are left out, the copy already
+    /// says how far along it is.
+    pub fn transfers(&self) -> impl Iterator<Item = &Activity> {
+        self.activities
+            .values()
+            .filter(|a| !a.is_build && self.activities.get(&a.parent).is_none_or(|p| p.is_build))
+    }
+}
+
+/// Pulls the derivation name out of messages like `waiting for a machine to build
+/// '/nix/store/...-foo.drv'`.
+fn derivation_in(text: &str) -> Option<String> {
+    let start = text.find(store::store_dir())?;
+    let rest = store::strip_store_dir(&text[start..])?;
+    let end = rest.find(".drv")? + ".drv".len();
+    Some(rest[..end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derivation_in_message() {
+        let text = format!(
+            "waiting for a machine to build '{}/abc-foo-1.0.drv'",
+            store::store_dir()
+        );
+        assert_eq!(derivation_in(&text).as_deref(), Some("abc-foo-1.0.drv"));
+    }
+
+    #[test]
+    fn derivation_in_with_outputs() {
+        let text = format!("building '{}/abc-foo.drv^out,dev'", store::store_dir());
+        assert_eq!(derivation_in(&text).as_deref(), Some("abc-foo.drv"));
+    }
+
+    #[test]
+    fn derivation_in_without_one() {
+        assert_eq!(derivation_in("copying path"), None);
+        let text = format!("copying path '{}/abc-foo-1.0'", store::store_dir());
+        assert_eq!(derivation_in(&text), None);
+    }
+}
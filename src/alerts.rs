@@ -0,0 +1,86 @@
+//! Rules for how long builds are allowed to take before they look suspicious.
+
+use crate::ps;
+use chrono::{TimeDelta, Utc};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// One `[[alerts]]` entry of the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Part of the derivation name. Rules without one apply to every build.
+    #[serde(rename = "match")]
+    pub pattern: Option<String>,
+    /// Builds taking longer than this are flagged.
+    pub minutes: u32,
+    /// Whether to also send a desktop notification.
+    #[serde(default)]
+    pub notify: bool,
+}
+
+impl Rule {
+    fn matches(&self, build: &ps::Build) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| build.drv_name().name.contains(pattern.as_str()))
+    }
+
+    pub fn limit(&self) -> TimeDelta {
+        TimeDelta::minutes(self.minutes.into())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DurationAlerts {
+    /// The first one that matches a build wins, so specific ones go first.
+    rules: Vec<Rule>,
+    /// Host and Nix PID of builds that went over their limit.
+    overdue: HashSet<(Option<String>, usize)>,
+}
+
+impl DurationAlerts {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            overdue: HashSet::new(),
+        }
+    }
+
+    pub fn rule(&self, build: &ps::Build) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(build))
+    }
+
+    pub fn is_overdue(&self, build: &ps::Build) -> bool {
+        self.overdue.contains(&(build.host.clone(), build.nix_pid))
+    }
+
+    /// Checks the builds against the rules. Returns the ones that just went over their limit,
+    /// with the rule they broke.
+    pub fn update<'a>(&mut self, builds: &'a [ps::Build]) -> Vec<(&'a ps::Build, Rule)> {
+        let now = Utc::now();
+        let mut overdue = HashSet::new();
+        let mut new = vec![];
+
+        for build in builds {
+            let Some(rule) = self.rule(build) else {
+                continue;
+            };
+            if build
+                .elapsed(now)
+                .is_none_or(|elapsed| elapsed <= rule.limit())
+            {
+                continue;
+            }
+
+            let key = (build.host.clone(), build.nix_pid);
+            if !self.overdue.contains(&key) {
+                new.push((build, rule.clone()));
+            }
+            overdue.insert(key);
+        }
+
+        self.overdue = overdue;
+        new
+    }
+}
@@ -1,22 +1,151 @@
-use crate::ps;
+use crate::{
+    activity::Queue,
+    alerts::DurationAlerts,
+    archive::{self, Report},
+    category::Categories,
+    client::Client,
+    columns::Column,
+    config::{Config, LayoutDirection},
+    disk::{DiskUsage, Filesystem},
+    drv::{self, OutputState},
+    durations::{self, Durations},
+    events::EventLog,
+    exclude::Exclude,
+    export, glyphs,
+    history::{BuildCounts, History},
+    host::HostStats,
+    notify::{self, Notifications},
+    priority, proc, ps,
+    signal::{self, Signal},
+    source::{DataSource, Local, Sources},
+    stats::{
+        CpuHistory, CpuUsage, IoUsage, NetUsage, progress_bar, show_bytes, show_cpu, show_duration,
+        show_rate,
+    },
+    store,
+    stuck::{self, StuckBuilds},
+    theme::Theme,
+    watch, why,
+};
 use anyhow::Context;
-use chrono::{TimeDelta, Utc};
-use crossterm::event::{Event as TerminalEvent, KeyCode, KeyEvent, KeyModifiers};
+use chrono::{DateTime, TimeDelta, Utc};
+use crossterm::event::{Event as TerminalEvent, KeyCode, KeyEvent};
 use futures::{FutureExt, StreamExt};
-use std::time::Duration;
-use tokio::{sync::mpsc, time};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::{
+    process::Command,
+    signal::unix::{SignalKind, signal},
+    sync::mpsc,
+    task::JoinHandle,
+    time,
+};
+
+use confirm::Confirm;
+use derivation::DerivationView;
+use environ::EnvironView;
+use files::FilesView;
+use gc::GcRun;
+use groups::{RowKey, TableRow};
+use keys::Action;
+use layout::Areas;
+use log::BuildLog;
+use palette::Palette;
+use scan::{LocalScan, Scanner};
+use signals::SignalMenu;
+use sort::Sort;
+use tabs::Tab;
+use tree::{TreeLine, TreeState};
+use tree_view::TreeView;
 
 use ratatui::{
     DefaultTerminal, Frame,
-    layout::{Alignment, Direction, Layout, Rect},
-    macros::{constraint, constraints, line, row, text, vertical},
-    style::{Color, Style, Stylize},
-    widgets::{Block, BorderType, Cell, Padding, Paragraph, Row, Table, TableState},
+    layout::{Alignment, Direction, Rect},
+    macros::{constraint, constraints, line, row, text},
+    style::{Style, Stylize},
+    text::{Span, Text},
+    widgets::{Block, List, ListState, Padding, Paragraph, Row, Table, TableState},
 };
 
+mod columns;
+mod compact;
+mod confirm;
+mod delegated;
+mod derivation;
+mod disk;
+mod downloads;
+mod environ;
+mod files;
+mod gc;
+mod groups;
+mod help;
+mod hints;
+mod history;
+mod keys;
+mod layout;
+mod log;
+mod mouse;
+mod palette;
+mod queue;
+mod rpc;
+mod scan;
+mod signals;
+pub(crate) mod sort;
+mod summary;
+mod tabs;
+mod tree;
+mod tree_view;
+
+/// Limits for how much of the screen the builds table can take, in percent.
+const MIN_SPLIT: u16 = 10;
+const MAX_SPLIT: u16 = 90;
+/// How long to wait after a build started or finished before refreshing.
+const BUILDS_CHANGED_DELAY: Duration = Duration::from_millis(200);
+/// How many characters `h` and `l` scroll the process tree by.
+const TREE_SCROLL_STEP: isize = 8;
+/// Longest wait between tries while refreshing keeps failing.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
 #[derive(Debug)]
 pub enum AppEvent {
-    Refresh(anyhow::Result<ps::Output>),
+    /// New `nix ps` outputs, one per source, and what this machine says about them.
+    Refresh(Vec<anyhow::Result<ps::Output>>, Option<Box<LocalScan>>),
+    /// A line of build log for a derivation.
+    Log(String, String),
+    /// New numbers for the disk panel.
+    Filesystem(Filesystem),
+    StoreSize(u64),
+    /// A line of the Nix client log given with `--activity`.
+    Activity(String),
+    ActivityError(String),
+    /// A line of output of the garbage collection we started.
+    GcOutput(String),
+    /// The garbage collection we started finished, with how it went.
+    GcDone(String),
+    MaxJobs(usize),
+    /// A second went by, so times on screen should count up.
+    Tick,
+    /// A local build started or finished, so it's worth refreshing now.
+    BuildsChanged,
+    /// Watching for builds starting and finishing stopped working, refreshing on a timer still
+    /// works.
+    WatchError(String),
+    /// Metadata of a derivation was looked up.
+    Derivation(String, anyhow::Result<drv::Info>),
+    /// Everything in a derivation, for the derivation view.
+    DerivationLines(String, anyhow::Result<Vec<String>>),
+    /// What pulls a derivation in, from `nix why-depends`.
+    WhyDepends(String, anyhow::Result<Vec<String>>),
+    /// A request that came in on the `--socket`.
+    Rpc(rpc::Call),
+    /// SIGUSR1 came in, asking for the builds to be saved to a file.
+    Snapshot,
     Quit,
 }
 
@@ -26,6 +155,15 @@ pub enum Event {
     App(AppEvent),
 }
 
+/// Pane that receives navigation keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Focus {
+    #[default]
+    Builds,
+    Tree,
+    Log,
+}
+
 #[derive(Debug)]
 pub struct App {
     running: bool,
@@ -35,25 +173,248 @@ pub struct App {
     receiver: mpsc::UnboundedReceiver<Event>,
 
     // getting builds
+    pub sources: Sources,
     pub refresh_interval: Duration,
+    /// The next refresh, waiting for the interval to pass.
+    refresh_task: Option<JoinHandle<()>>,
+    /// Whether refreshing is paused, to read stuff without it changing.
+    pub frozen: bool,
+    /// Whether a refresh was asked for while paused, which should still show up.
+    refresh_requested: bool,
+    /// Whether a refresh is coming up because builds changed, so that more changes don't push it
+    /// back.
+    refresh_soon: bool,
+    /// Whether the terminal lost focus, which makes refreshing slower.
+    unfocused: bool,
+    unfocused_slowdown: u32,
     pub active_builds: Vec<ps::Build>,
+    /// Store paths being downloaded on this machine.
+    pub substitutions: Vec<proc::Substitution>,
+    /// Nice values of local builds by main PID, read when refreshing instead of when drawing.
+    pub nice: HashMap<usize, i32>,
+    /// Who each local build runs as, by main PID.
+    pub build_users: HashMap<usize, proc::BuildUser>,
+    /// Clients that started local builds, by Nix PID. Looked up once per build.
+    pub clients: HashMap<usize, Option<Client>>,
+    pub disk: DiskUsage,
+    /// Client log to get queued builds from.
+    pub activity: Option<PathBuf>,
+    /// Unix socket to answer JSON-RPC requests on.
+    pub socket: Option<PathBuf>,
+    pub queue: Queue,
+    /// PIDs of garbage collections running on this machine, ours or not.
+    pub collectors: Vec<usize>,
+    pub gc_max: u64,
+    pub gc: Option<GcRun>,
+    watching_disk: bool,
+    pub last_refresh: Option<DateTime<Utc>>,
+    pub refresh_error: Option<String>,
+    /// Refreshes in a row that failed, each one doubles the wait before the next.
+    failures: u32,
+    /// When the next try is, while refreshing fails.
+    retry_at: Option<DateTime<Utc>>,
+    /// One-off message shown until the next key press.
+    pub notice: Option<String>,
+    /// Where to export builds to, if not the default.
+    pub output: Option<PathBuf>,
+    pub cpu: CpuUsage,
+    pub io: IoUsage,
+    pub net: NetUsage,
+    pub stuck: StuckBuilds,
+    pub alerts: DurationAlerts,
+    /// Builds that are left out as soon as they're seen.
+    pub exclude: Exclude,
+    /// How many were left out on the last refresh.
+    pub excluded: usize,
+    /// What kind of program each process runs, for coloring the tree.
+    pub categories: Categories,
+    pub cpu_history: CpuHistory,
+    pub history: History,
+    pub build_counts: BuildCounts,
+    pub notifications: Notifications,
+    pub events: EventLog,
+    pub host: HostStats,
+    /// The `max-jobs` of the local Nix, once we know it.
+    pub max_jobs: Option<usize>,
 
     // stuff
-    pub direction: Direction,
+    pub theme: Theme,
+    pub tab: Tab,
+    /// Which way the builds and details are split, `None` to go by the shape of the terminal.
+    pub direction: Option<Direction>,
+    /// Percentage of the screen taken by the builds table.
+    pub split: u16,
+    pub focus: Focus,
+    /// Whether `g` was just pressed, waiting for another one.
+    pub pending_top: bool,
+    /// Size of the terminal as of the last render.
+    pub area: Rect,
+    /// Time the last render was for, what durations on screen count up to.
+    now: DateTime<Utc>,
     pub table_state: TableState,
+    pub sort: Sort,
+    pub columns: Vec<Column>,
+    pub column_widths: HashMap<Column, u16>,
+    /// Cursor in the column menu, if it's open.
+    pub column_menu: Option<usize>,
+    pub signal_menu: Option<SignalMenu>,
+    /// Signals last picked in the signal menu, newest first.
+    pub recent_signals: Vec<Signal>,
+    pub palette: Option<Palette>,
+    pub filter: String,
+    pub editing_filter: bool,
+    pub confirm: Option<Confirm>,
+    /// Main PIDs of builds we've sent SIGSTOP to.
+    pub paused: HashSet<usize>,
+    /// Whether builds of the same package are grouped together.
+    pub grouped: bool,
+    /// Package names of groups that show their builds.
+    pub expanded_groups: HashSet<String>,
+    /// Host and Nix PID of the build the tree and log belong to.
+    details_for: Option<(Option<String>, usize)>,
+    /// Rows of the properties and process tree scrolled off the top, by host and Nix PID, so
+    /// every build stays where it was left.
+    details_scroll: HashMap<(Option<String>, usize), usize>,
+    pub tree: TreeState,
+    /// Log of the selected build, if there's a way to get it.
+    pub log: Option<BuildLog>,
+    /// `nix derivation show` results, `None` while they're being looked up.
+    pub derivations: HashMap<String, Option<Result<drv::Info, String>>>,
+    /// Looks at this machine for the refreshes, shared with the task doing them.
+    scanner: Arc<Mutex<Scanner>>,
+    /// Derivations of builds on this machine, read from their files when the builds show up.
+    drv_files: HashMap<String, Option<drv::Local>>,
+    /// How long builds took in earlier runs.
+    pub durations: Durations,
+    /// How far along the outputs of the selected build are, by store path.
+    pub outputs: HashMap<String, drv::OutputState>,
+    /// Sizes of outputs and substitutions, so they aren't walked on every refresh.
+    disk_usage: proc::DiskUsage,
+    pub derivation_view: Option<DerivationView>,
+    pub environ_view: Option<EnvironView>,
+    pub files_view: Option<FilesView>,
+    pub tree_view: Option<TreeView>,
+    /// Chains from what a client asked for down to a derivation, `None` while looking.
+    pub why: HashMap<String, Option<Result<Vec<String>, String>>>,
+    pub show_help: bool,
+    /// Rows of the help scrolled off the top, when it doesn't fit.
+    help_scroll: usize,
+    /// Whether commands in the process tree are wrapped instead of cut off.
+    pub wrap_commands: bool,
+    /// Whether the bar with key hints is at the bottom.
+    pub show_hints: bool,
+    pub show_disk: bool,
+    /// Whether the details pane is there, or the builds table gets the whole screen.
+    pub show_details: bool,
+    /// What the history tab shows, `None` for the builds that finished during this run.
+    pub history_report: Option<Report>,
+    /// Builds saved by earlier runs, loaded when the history tab first needs them.
+    pub archive: Option<Vec<archive::Record>>,
+    /// Scroll positions of the history and logs tabs.
+    pub history_scroll: usize,
+    pub events_scroll: usize,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(config: Config, theme: Theme) -> Self {
         let (sender, receiver) = mpsc::unbounded_channel();
         Self {
             running: true,
             sender,
             receiver,
+            sources: Arc::new([Box::new(Local::default()) as Box<dyn DataSource>]),
             refresh_interval: Duration::from_secs(2),
+            refresh_task: None,
+            frozen: false,
+            refresh_requested: false,
+            refresh_soon: false,
+            unfocused: false,
+            unfocused_slowdown: config.unfocused_slowdown.max(1),
             active_builds: Vec::new(),
-            direction: Direction::Vertical,
+            substitutions: Vec::new(),
+            nice: HashMap::new(),
+            build_users: HashMap::new(),
+            clients: HashMap::new(),
+            disk: DiskUsage::default(),
+            activity: None,
+            socket: None,
+            queue: Queue::default(),
+            collectors: Vec::new(),
+            gc_max: config.gc_max,
+            gc: None,
+            watching_disk: false,
+            last_refresh: None,
+            refresh_error: None,
+            failures: 0,
+            retry_at: None,
+            notice: None,
+            output: None,
+            cpu: CpuUsage::default(),
+            io: IoUsage::default(),
+            net: NetUsage::default(),
+            stuck: StuckBuilds::new(config.stuck_after),
+            alerts: DurationAlerts::new(config.alerts),
+            exclude: Exclude::new(config.exclude, config.exclude_fetches),
+            excluded: 0,
+            categories: Categories::new(config.processes),
+            cpu_history: CpuHistory::default(),
+            history: History::default(),
+            build_counts: BuildCounts::new(TimeDelta::minutes(config.chart_minutes.into())),
+            notifications: config.notifications,
+            events: EventLog::default(),
+            host: HostStats::default(),
+            max_jobs: None,
+            theme,
+            tab: Tab::default(),
+            direction: match config.direction {
+                LayoutDirection::Auto => None,
+                LayoutDirection::Vertical => Some(Direction::Vertical),
+                LayoutDirection::Horizontal => Some(Direction::Horizontal),
+            },
+            split: config.split.clamp(MIN_SPLIT, MAX_SPLIT),
+            focus: Focus::default(),
+            pending_top: false,
+            area: Rect::default(),
+            now: Utc::now(),
             table_state: TableState::default(),
+            sort: config.sort,
+            columns: config.columns,
+            column_widths: config.column_widths,
+            column_menu: None,
+            signal_menu: None,
+            recent_signals: vec![],
+            palette: None,
+            filter: String::new(),
+            editing_filter: false,
+            confirm: None,
+            paused: HashSet::new(),
+            grouped: config.grouped,
+            expanded_groups: HashSet::new(),
+            details_for: None,
+            details_scroll: HashMap::new(),
+            tree: TreeState::default(),
+            log: None,
+            derivations: HashMap::new(),
+            scanner: Arc::default(),
+            outputs: HashMap::new(),
+            disk_usage: proc::DiskUsage::default(),
+            drv_files: HashMap::new(),
+            durations: Durations::default(),
+            derivation_view: None,
+            environ_view: None,
+            files_view: None,
+            tree_view: None,
+            why: HashMap::new(),
+            show_help: false,
+            help_scroll: 0,
+            wrap_commands: false,
+            show_hints: config.hints,
+            show_disk: config.disk,
+            show_details: config.details,
+            history_report: None,
+            archive: None,
+            history_scroll: 0,
+            events_scroll: 0,
         }
     }
 
@@ -73,173 +434,1250 @@ impl App {
             }
         });
 
+        // redraw every second, so that times count up between refreshes (and while paused)
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                if sender.send(Event::App(AppEvent::Tick)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // `kill -USR1` saves what ntop sees, for when the terminal is somewhere else
+        match signal(SignalKind::user_defined1()) {
+            Ok(mut usr1) => {
+                let sender = self.sender.clone();
+                tokio::spawn(async move {
+                    while usr1.recv().await.is_some() {
+                        if sender.send(Event::App(AppEvent::Snapshot)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            Err(err) => tracing::warn!("can't listen for SIGUSR1: {err}"),
+        }
+
+        if self.show_disk {
+            self.watch_disk();
+        }
+        if let Some(path) = self.activity.clone() {
+            self.follow_activity(path);
+        }
+        if let Some(path) = self.socket.clone() {
+            self.serve_rpc(path);
+        }
+        if self.watches_local() {
+            self.lookup_max_jobs();
+            self.watch_builds();
+        }
+
         // send initial data
-        _ = self
-            .sender
-            .send(Event::App(AppEvent::Refresh(ps::get().await)));
+        _ = self.sender.send(Event::App(self.fetch().await));
 
         while self.running {
-            terminal.draw(|frame| self.render(frame))?;
-            match self
+            let started = Instant::now();
+            terminal.draw(|frame| self.render(frame, Utc::now()))?;
+            tracing::trace!(elapsed = ?started.elapsed(), "drew a frame");
+            let event = self
                 .receiver
                 .recv()
                 .await
-                .context("while receiving event")?
-            {
-                Event::Terminal(event) => match event {
-                    crossterm::event::Event::Key(key_event)
-                        if key_event.kind == crossterm::event::KeyEventKind::Press =>
+                .context("while receiving event")?;
+            self.handle_event(event);
+        }
+        if let Some(path) = &self.socket {
+            _ = fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    /// Whether the app wants to keep going, until it's told to quit.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Does whatever an event calls for. [`App::run`] feeds it events from the terminal and the
+    /// background tasks, but anything can, like a test with made up events.
+    pub fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Terminal(event) => match event {
+                crossterm::event::Event::Key(key_event)
+                    if key_event.kind == crossterm::event::KeyEventKind::Press =>
+                {
+                    self.handle_key_events(key_event)
+                }
+                crossterm::event::Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event),
+                crossterm::event::Event::FocusLost => self.unfocused = true,
+                crossterm::event::Event::FocusGained => {
+                    self.unfocused = false;
+                    // the data might be pretty old by now
+                    if !self.frozen {
+                        self.schedule_refresh(Duration::ZERO);
+                    }
+                }
+                _ => {}
+            },
+            Event::App(app_event) => match app_event {
+                // one that was on its way when refreshing got paused
+                AppEvent::Refresh(..) if self.frozen && !self.refresh_requested => {}
+                AppEvent::Refresh(output, scan) => self.refresh(output, scan),
+                AppEvent::Log(derivation, line) => {
+                    if let Some(log) = &mut self.log
+                        && log.derivation == derivation
                     {
-                        self.handle_key_events(key_event)?
+                        log.push(line);
+                    }
+                }
+                AppEvent::Filesystem(filesystem) => self.disk.update(filesystem),
+                AppEvent::StoreSize(size) => self.disk.store = Some(size),
+                AppEvent::Activity(line) => self.queue.push(&line),
+                AppEvent::ActivityError(error) => self.show_notice(error),
+                AppEvent::GcOutput(line) => {
+                    if let Some(gc) = &mut self.gc {
+                        gc.lines.push(line);
+                    }
+                }
+                AppEvent::GcDone(status) => {
+                    self.events.info(format!("Garbage collection {status}"));
+                    if let Some(gc) = &mut self.gc {
+                        gc.status = Some(status);
+                    }
+                }
+                AppEvent::MaxJobs(max_jobs) => self.max_jobs = Some(max_jobs),
+                AppEvent::Tick => {}
+                // a little later, so that a bunch of builds starting at once is one refresh,
+                // and the new build shows up in `nix ps`
+                AppEvent::BuildsChanged if !self.frozen && !self.refresh_soon => {
+                    tracing::debug!("builds changed");
+                    self.refresh_soon = true;
+                    self.schedule_refresh(BUILDS_CHANGED_DELAY);
+                }
+                AppEvent::BuildsChanged => {}
+                AppEvent::WatchError(err) => {
+                    tracing::warn!("not watching for new builds: {err}");
+                    self.events.info(format!(
+                        "Not watching for new builds, only refreshing: {err}"
+                    ));
+                }
+                AppEvent::Derivation(derivation, info) => {
+                    let info = info.map_err(|err| {
+                        tracing::warn!(derivation, "looking up the derivation failed: {err:#}");
+                        format!("{err:#}")
+                    });
+                    self.derivations.insert(derivation, Some(info));
+                    self.update_outputs();
+                }
+                AppEvent::DerivationLines(derivation, lines) => {
+                    if let Some(view) = &mut self.derivation_view
+                        && view.derivation == derivation
+                    {
+                        view.lines = Some(lines.map_err(|err| format!("{err:#}")));
+                    }
+                }
+                AppEvent::WhyDepends(derivation, chain) => {
+                    let chain = chain.map_err(|err| format!("{err:#}"));
+                    self.why.insert(derivation, Some(chain));
+                }
+                AppEvent::Rpc(call) => self.answer(call),
+                AppEvent::Snapshot => self.save_snapshot(),
+                AppEvent::Quit => self.running = false,
+            },
+        }
+
+        self.sync_selected();
+    }
+
+    /// Handles terminal key events.
+    fn handle_key_events(&mut self, key_event: KeyEvent) {
+        self.notice = None;
+
+        if self.editing_filter {
+            self.handle_filter_input(key_event);
+            return;
+        }
+
+        if let Some(view) = &self.environ_view
+            && view.editing_filter
+        {
+            self.handle_environ_filter(key_event);
+            return;
+        }
+
+        if let Some(view) = &self.tree_view
+            && view.editing_query
+        {
+            self.handle_tree_search(key_event);
+            return;
+        }
+
+        if self.column_menu.is_some() {
+            self.handle_column_menu(key_event);
+            return;
+        }
+
+        if self.signal_menu.is_some() {
+            self.handle_signal_menu(key_event);
+            return;
+        }
+
+        if self.palette.is_some() {
+            self.handle_palette(key_event);
+            return;
+        }
+
+        if let Some(confirm) = self.confirm.take() {
+            if !matches!(key_event.code, KeyCode::Char('y' | 'Y') | KeyCode::Enter) {
+                return;
+            }
+            match confirm {
+                Confirm::Signal {
+                    signal,
+                    pid,
+                    target,
+                } => match signal::send(pid, signal) {
+                    Ok(()) => self
+                        .events
+                        .info(format!("Sent {} to {target}", signal.name())),
+                    Err(err) => {
+                        self.show_notice(format!("Failed to send {}: {err}", signal.name()))
                     }
-                    _ => {}
-                },
-                Event::App(app_event) => match app_event {
-                    AppEvent::Refresh(output) => self.refresh(output),
-                    AppEvent::Quit => break,
                 },
+                Confirm::Gc { max } => self.start_gc(max),
             }
+            return;
         }
-        Ok(())
+
+        let action = keys::action_for(&key_event);
+        // `g` has to be pressed twice in a row
+        if action != Some(Action::Top) {
+            self.pending_top = false;
+        }
+        let Some(action) = action else {
+            return;
+        };
+
+        // help is modal, only let through the keys that close it or quit
+        if self.show_help && !matches!(action, Action::ToggleHelp | Action::Quit) {
+            self.handle_help(action);
+            return;
+        }
+
+        // and the derivation view, which scrolls
+        if self.derivation_view.is_some() && action != Action::Quit {
+            self.handle_derivation_view(action);
+            return;
+        }
+
+        // and the environment view
+        if self.environ_view.is_some() && action != Action::Quit {
+            self.handle_environ_view(action);
+            return;
+        }
+
+        // and the open files view
+        if self.files_view.is_some() && action != Action::Quit {
+            self.handle_files_view(action);
+            return;
+        }
+
+        // and the fullscreen tree, under the views it can open
+        if self.tree_view.is_some() && action != Action::Quit {
+            self.handle_tree_view(action);
+            return;
+        }
+
+        // and the garbage collection output, closing it stops the collection
+        if self.gc.is_some() && !matches!(action, Action::Quit) {
+            if matches!(action, Action::ClearSelection | Action::CollectGarbage) {
+                self.gc = None;
+            }
+            return;
+        }
+
+        self.perform(action);
     }
 
-    /// Handles terminal key events.
-    fn handle_key_events(&mut self, key_event: KeyEvent) -> anyhow::Result<()> {
-        match key_event.code {
+    /// Does what a key (or the command palette) asked for.
+    pub(super) fn perform(&mut self, action: Action) {
+        match action {
             // refresh interval
-            KeyCode::Char('-') => {
-                let new = self
-                    .refresh_interval
-                    .saturating_sub(Duration::from_millis(100));
+            Action::FasterRefresh => self.faster_refresh(),
+            Action::SlowerRefresh => self.slower_refresh(),
+            Action::Refresh => {
+                self.refresh_requested = self.frozen;
+                self.schedule_refresh(Duration::ZERO);
+            }
+            Action::ToggleFreeze => {
+                self.refresh_requested = false;
+                self.frozen = !self.frozen;
+                if self.frozen {
+                    self.stop_refreshing();
+                } else {
+                    self.schedule_refresh(Duration::ZERO);
+                }
+            }
 
-                if new.as_millis() >= 100 {
-                    self.refresh_interval = new;
+            // navigation
+            Action::Up => self.move_by(-1),
+            Action::Down => self.move_by(1),
+            Action::Left => match self.focus {
+                Focus::Builds => self.expand_group(false),
+                Focus::Tree => {
+                    if let Some(i) = self.selected_index() {
+                        self.tree.collapse(&self.active_builds[i]);
+                    }
+                }
+                Focus::Log => {}
+            },
+            Action::Right => match self.focus {
+                Focus::Builds => self.expand_group(true),
+                Focus::Tree => self.tree.expand(),
+                Focus::Log => {}
+            },
+            Action::ScrollLeft | Action::ScrollRight if self.focus == Focus::Tree => {
+                let delta = if action == Action::ScrollLeft {
+                    -TREE_SCROLL_STEP
+                } else {
+                    TREE_SCROLL_STEP
+                };
+                if let Some(i) = self.selected_index() {
+                    self.tree.scroll_by(&self.active_builds[i], delta);
                 }
             }
-            KeyCode::Char('=' | '+') => {
-                self.refresh_interval = self
-                    .refresh_interval
-                    .saturating_add(Duration::from_millis(100));
+            Action::ScrollLeft | Action::ScrollRight => {}
+            Action::ToggleWrap => self.wrap_commands = !self.wrap_commands,
+            Action::Top if !mem::take(&mut self.pending_top) => self.pending_top = true,
+            Action::Top => self.move_by(-isize::MAX),
+            Action::Bottom => self.move_by(isize::MAX),
+            Action::PageUp => self.move_by_page(-1.0),
+            Action::PageDown => self.move_by_page(1.0),
+            Action::HalfPageUp => self.move_by_page(-0.5),
+            Action::HalfPageDown => self.move_by_page(0.5),
+            Action::FocusNext if self.tab == Tab::Builds => self.cycle_focus(true),
+            Action::FocusPrevious if self.tab == Tab::Builds => self.cycle_focus(false),
+            Action::FocusNext | Action::FocusPrevious => {}
+            Action::ClearSelection => self.table_state.select(None),
+
+            // sorting
+            Action::CycleSort => {
+                // only the columns that are there
+                let sortable: Vec<_> = self
+                    .visible_columns()
+                    .into_iter()
+                    .filter_map(Column::sort_key)
+                    .collect();
+                let mut key = self.sort.key.next();
+                while !sortable.contains(&key) && key != self.sort.key {
+                    key = key.next();
+                }
+                self.sort.key = key;
+                self.keep_selection(|app| app.sort.apply(&mut app.active_builds));
+            }
+            Action::ReverseSort => {
+                self.sort.reversed = !self.sort.reversed;
+                self.keep_selection(|app| app.sort.apply(&mut app.active_builds));
             }
+            Action::SortBy(key) => {
+                self.sort.key = key;
+                self.keep_selection(|app| app.sort.apply(&mut app.active_builds));
+            }
+            Action::CycleHistoryReport => self.cycle_history_report(),
 
-            // active builds table
-            KeyCode::Up | KeyCode::Char('k') => self.table_state.select_previous(),
-            KeyCode::Down | KeyCode::Char('j') => self.table_state.select_next(),
-            KeyCode::Esc => self.table_state.select(None),
+            Action::Filter => self.editing_filter = true,
+            Action::ViewDerivation => self.view_derivation(),
+            Action::ViewEnviron => self.view_environ(),
+            Action::FullscreenTree => self.open_tree_view(),
+            Action::NextMatch | Action::PreviousMatch => {}
+            Action::ViewFiles => self.view_files(),
+            Action::WhyDepends => self.ask_why(),
+            Action::ToggleGroups => {
+                self.keep_selection(|app| app.grouped = !app.grouped);
+            }
+            Action::ColumnMenu => self.column_menu = Some(0),
 
-            // flip direction
-            KeyCode::Char('/') => {
-                self.direction = match self.direction {
+            // signals
+            Action::TogglePause | Action::Kill(_) | Action::SignalMenu
+                if self.selected_is_remote() =>
+            {
+                self.show_notice("Can't send signals to builds on other machines".to_string());
+            }
+            Action::Renice(_) if self.selected_is_remote() => {
+                self.show_notice("Can't change priority of builds on other machines".to_string());
+            }
+            Action::Renice(delta) => {
+                if let Some(build) = self.selected_build() {
+                    let derivation = build.derivation.clone();
+                    match priority::renice_all(build, delta) {
+                        Ok(nice) => {
+                            self.nice.insert(build.main_pid, nice);
+                            self.events
+                                .info(format!("Set nice of {derivation} to {nice}"));
+                        }
+                        Err(err) => self.show_notice(format!("Failed to change priority: {err}")),
+                    }
+                }
+            }
+            Action::TogglePause => {
+                if let Some(build) = self.selected_build() {
+                    let pid = build.main_pid;
+                    let signal = if self.paused.contains(&pid) {
+                        Signal::Cont
+                    } else {
+                        Signal::Stop
+                    };
+
+                    let derivation = build.derivation.clone();
+                    match signal::send_all(build, signal) {
+                        Ok(()) if signal == Signal::Stop => {
+                            self.paused.insert(pid);
+                            self.events.info(format!("Paused {derivation}"));
+                        }
+                        Ok(()) => {
+                            self.paused.remove(&pid);
+                            self.events.info(format!("Resumed {derivation}"));
+                        }
+                        Err(err) => {
+                            self.show_notice(format!("Failed to send {}: {err}", signal.name()));
+                        }
+                    }
+                }
+            }
+            Action::Kill(signal) => {
+                self.confirm = self.signal_target().map(|(pid, target)| Confirm::Signal {
+                    signal,
+                    pid,
+                    target,
+                });
+            }
+            Action::SignalMenu => self.open_signal_menu(),
+
+            Action::Export => {
+                let path = self
+                    .output
+                    .clone()
+                    .unwrap_or_else(|| export::default_path().into());
+
+                let notice = match export::write(&path, &self.active_builds, &self.cpu) {
+                    Ok(()) => format!("Exported builds to {}", path.display()),
+                    Err(err) => format!("Export failed: {err:#}"),
+                };
+                self.show_notice(notice);
+            }
+
+            // layout
+            Action::ShrinkBuilds => self.split = self.split.saturating_sub(5).max(MIN_SPLIT),
+            Action::GrowBuilds => self.split = (self.split + 5).min(MAX_SPLIT),
+            Action::FlipLayout => {
+                // from then on it stays put, whatever the terminal does
+                self.direction = Some(match self.direction() {
                     Direction::Horizontal => Direction::Vertical,
                     Direction::Vertical => Direction::Horizontal,
+                });
+            }
+
+            Action::NextTab => self.show_tab(self.tab.cycle(true)),
+            Action::PreviousTab => self.show_tab(self.tab.cycle(false)),
+            Action::ShowTab(tab) => self.show_tab(tab),
+            Action::CollectGarbage => {
+                self.confirm = Some(Confirm::Gc { max: self.gc_max });
+            }
+            Action::ToggleDetails => {
+                self.show_details = !self.show_details;
+                if !self.show_details {
+                    self.focus = Focus::Builds;
+                }
+            }
+            Action::ToggleDisk => {
+                self.show_disk = !self.show_disk;
+                if self.show_disk {
+                    self.watch_disk();
+                }
+            }
+            Action::CommandPalette => self.palette = Some(Palette::default()),
+            Action::ToggleHelp => {
+                self.show_help = !self.show_help;
+                self.help_scroll = 0;
+            }
+            Action::Quit => _ = self.sender.send(Event::App(AppEvent::Quit)),
+        }
+    }
+
+    /// Shows a message in the banner until the next key press, and keeps it in the logs.
+    fn show_notice(&mut self, notice: String) {
+        self.events.info(notice.as_str());
+        self.notice = Some(notice);
+    }
+
+    /// Builds that are shown in the table, i.e. not hidden by the filter.
+    fn visible_builds(&self) -> Vec<&ps::Build> {
+        filter_builds(&self.active_builds, &self.clients, &self.filter)
+    }
+
+    /// The selected build, `None` when nothing or a group is selected.
+    fn selected_build(&self) -> Option<&ps::Build> {
+        let i = self.table_state.selected()?;
+        match self.table_rows().into_iter().nth(i)? {
+            TableRow::Build { build, .. } => Some(build),
+            TableRow::Group { .. } => None,
+        }
+    }
+
+    fn selected_is_remote(&self) -> bool {
+        self.selected_build().is_some_and(|b| b.host.is_some())
+    }
+
+    /// Index of the selected build in `active_builds`, for when it has to be borrowed separately.
+    fn selected_index(&self) -> Option<usize> {
+        let selected = self.selected_build()?;
+        self.active_builds
+            .iter()
+            .position(|b| std::ptr::eq(b, selected))
+    }
+
+    /// Modifies the app state, keeping the same build (or group) selected afterwards.
+    fn keep_selection(&mut self, f: impl FnOnce(&mut Self)) {
+        let previous_selection = self
+            .table_state
+            .selected()
+            .and_then(|i| Some(self.table_rows().get(i)?.key()));
+
+        f(self);
+        let new_selection = previous_selection.and_then(|key| {
+            let rows = self.table_rows();
+            rows.iter().position(|row| row.key() == key).or_else(|| {
+                // the group went away, or the build went into one
+                let RowKey::Build(host, pid) = key else {
+                    return None;
                 };
+                let pname = self
+                    .active_builds
+                    .iter()
+                    .find(|b| b.key() == (host.as_deref(), pid))?
+                    .name()
+                    .0;
+                rows.iter()
+                    .position(|row| row.key() == RowKey::Group(pname.to_string()))
+            })
+        });
+
+        self.table_state.select(new_selection);
+    }
+
+    /// The refresh interval, slowed down while nobody's looking.
+    fn current_interval(&self) -> Duration {
+        if self.unfocused {
+            self.refresh_interval * self.unfocused_slowdown
+        } else {
+            self.refresh_interval
+        }
+    }
+
+    fn faster_refresh(&mut self) {
+        let new = self
+            .refresh_interval
+            .saturating_sub(Duration::from_millis(100));
+
+        if new.as_millis() >= 100 {
+            self.refresh_interval = new;
+        }
+    }
+
+    fn slower_refresh(&mut self) {
+        self.refresh_interval = self
+            .refresh_interval
+            .saturating_add(Duration::from_millis(100));
+    }
+
+    /// Goes from this run's finished builds through the reports over saved ones, and back.
+    fn cycle_history_report(&mut self) {
+        self.history_report = match self.history_report {
+            None => Some(Report::ALL[0]),
+            Some(report) => Report::ALL.into_iter().skip_while(|r| *r != report).nth(1),
+        };
+        if self.history_report.is_some() && self.archive.is_none() {
+            match archive::load() {
+                Ok(records) => self.archive = Some(records),
+                Err(err) => {
+                    self.events.error(format!("{err:#}"));
+                    self.history_report = None;
+                }
+            }
+        }
+        self.history_scroll = 0;
+    }
+
+    /// How many rows the history tab has, to scroll through.
+    fn history_len(&self) -> usize {
+        match (self.history_report, &self.archive) {
+            (Some(report), Some(archive)) => report.table(archive, usize::MAX).rows.len(),
+            _ => self.history.finished.len(),
+        }
+    }
+
+    /// Moves the cursor of the focused pane (or scrolls the tab) up or down.
+    fn move_by(&mut self, delta: isize) {
+        let scroll = |scroll: usize, len: usize| {
+            scroll
+                .saturating_add_signed(delta)
+                .min(len.saturating_sub(1))
+        };
+        match self.tab {
+            Tab::Builds => self.move_in(self.focus, delta),
+            Tab::History => self.history_scroll = scroll(self.history_scroll, self.history_len()),
+            Tab::Logs => self.events_scroll = scroll(self.events_scroll, self.events.entries.len()),
+            Tab::System => {}
+        }
+    }
+
+    /// Moves the cursor of the focused pane by a fraction of its height.
+    fn move_by_page(&mut self, pages: f32) {
+        let areas = self.areas(self.area);
+        let height = match (self.tab, self.focus) {
+            // borders and header
+            (Tab::Builds, Focus::Builds) => areas.builds.height.saturating_sub(3),
+            (Tab::Builds, Focus::Tree) if self.tree_view.is_some() => {
+                self.area.height.saturating_sub(3)
+            }
+            (Tab::Builds, Focus::Tree) => areas.tree.height,
+            (Tab::Builds, Focus::Log) => areas.log.height,
+            _ => areas.content.height.saturating_sub(3),
+        };
+        let delta = (height.max(1) as f32 * pages) as isize;
+        self.move_by(delta.signum() * delta.abs().max(1));
+    }
+
+    /// Focuses the next or previous pane, skipping ones that have nothing in them.
+    fn cycle_focus(&mut self, forward: bool) {
+        let mut panes = vec![Focus::Builds];
+        if self.show_details && self.selected_build().is_some() {
+            panes.push(Focus::Tree);
+            if self.log.is_some() {
+                panes.push(Focus::Log);
+            }
+        }
+
+        let current = panes.iter().position(|p| *p == self.focus).unwrap_or(0);
+        let next = if forward {
+            (current + 1) % panes.len()
+        } else {
+            (current + panes.len() - 1) % panes.len()
+        };
+        self.focus = panes[next];
+    }
+
+    /// Moves the cursor of a pane up or down.
+    fn move_in(&mut self, pane: Focus, delta: isize) {
+        match pane {
+            Focus::Builds => {
+                let amount = delta.unsigned_abs().min(u16::MAX as usize) as u16;
+                if delta < 0 {
+                    self.table_state.scroll_up_by(amount);
+                } else {
+                    self.table_state.scroll_down_by(amount);
+                }
+
+                // the table only fixes it when drawing, which is too late for sync_selected
+                let rows = self.table_rows().len();
+                if let Some(i) = self.table_state.selected()
+                    && i >= rows
+                {
+                    self.table_state.select(rows.checked_sub(1));
+                }
+            }
+            Focus::Tree => {
+                if let Some(i) = self.selected_index() {
+                    self.tree.move_by(&self.active_builds[i], delta);
+                    self.scroll_to_cursor();
+                }
+            }
+            Focus::Log => {
+                if let Some(log) = &mut self.log {
+                    if delta < 0 {
+                        log.scroll_up(delta.unsigned_abs());
+                    } else {
+                        log.scroll_down(delta as usize);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Resets the details pane state when a different build gets selected, and starts following
+    /// its log.
+    fn sync_selected(&mut self) {
+        let selected = self.selected_build().map(|b| (b.host.clone(), b.nix_pid));
+        if self.details_for == selected {
+            return;
+        }
+
+        self.details_for = selected;
+        self.tree = TreeState::default();
+        self.tree_view = None;
+        self.log = self.selected_build().and_then(|build| {
+            let command = self.nix_command(build)?;
+            Some(BuildLog::follow(
+                build.derivation.clone(),
+                command,
+                self.sender.clone(),
+            ))
+        });
+        self.lookup_derivation();
+        self.update_outputs();
+        if self.details_for.is_none() {
+            self.focus = Focus::Builds;
+        }
+    }
+
+    /// A command that runs Nix on the machine of the build, if there's one.
+    fn nix_command(&self, build: &ps::Build) -> Option<Command> {
+        // delegated builds have their derivation here, the builder only gets a copy
+        let host = if build.delegated {
+            None
+        } else {
+            build.host.as_deref()
+        };
+        let source = self.sources.iter().find(|s| s.host() == host)?;
+        source.command("nix")
+    }
+
+    /// A command that runs Nix here, with the store of the local source if it has one.
+    pub(super) fn local_nix(&self) -> Command {
+        self.sources
+            .iter()
+            .find(|s| s.host().is_none())
+            .and_then(|s| s.command("nix"))
+            .unwrap_or_else(|| Command::new("nix"))
+    }
+
+    /// Starts looking up the metadata of the selected build, unless it's known already.
+    fn lookup_derivation(&mut self) {
+        let Some(build) = self.selected_build() else {
+            return;
+        };
+        if self.derivations.contains_key(&build.derivation) {
+            return;
+        }
+        let Some(command) = self.nix_command(build) else {
+            return;
+        };
+
+        let derivation = build.derivation.clone();
+        let sender = self.sender.clone();
+        self.derivations.insert(derivation.clone(), None);
+        tokio::spawn(async move {
+            let info = drv::show(command, &derivation).await;
+            _ = sender.send(Event::App(AppEvent::Derivation(derivation, info)));
+        });
+    }
+
+    /// The system a build is for, if its derivation was read.
+    fn system(&self, build: &ps::Build) -> Option<&str> {
+        if let Some(Some(local)) = self.drv_files.get(&build.derivation) {
+            return Some(&local.system);
+        }
+        match self.derivations.get(&build.derivation) {
+            Some(Some(Ok(info))) => Some(&info.system),
+            _ => None,
+        }
+    }
+
+    fn duration_key(&self, build: &ps::Build) -> Option<durations::Key> {
+        let (pname, version) = build.name();
+        Some(durations::Key {
+            pname: pname.to_string(),
+            version: version.to_string(),
+            system: self.system(build)?.to_string(),
+        })
+    }
+
+    /// Whether a build that went away got its outputs, or `None` when that can't be told: for
+    /// builds on other machines, and ones whose derivation couldn't be read here.
+    fn succeeded(&self, build: &ps::Build) -> Option<bool> {
+        match self.drv_files.get(&build.derivation) {
+            Some(Some(local)) if !local.outputs.is_empty() => {
+                Some(local.outputs.iter().all(|path| Path::new(path).exists()))
+            }
+            _ => None,
+        }
+    }
+
+    /// How long the build usually takes, and out of how many earlier builds.
+    pub(super) fn usual_duration(&self, build: &ps::Build) -> Option<(TimeDelta, usize)> {
+        let (pname, version) = build.name();
+        self.durations.usual(pname, version, self.system(build))
+    }
+
+    /// Looks at the outputs of the selected build on disk. Only for builds running here, or
+    /// delegated ones, whose outputs get copied here when they're done.
+    fn update_outputs(&mut self) {
+        self.outputs.clear();
+        let Some(build) = self.selected_build() else {
+            return;
+        };
+        if build.host.is_some() && !build.delegated {
+            return;
+        }
+        let Some(Some(Ok(info))) = self.derivations.get(&build.derivation) else {
+            return;
+        };
+
+        let derivation = build.derivation.clone();
+        let paths: Vec<String> = info
+            .outputs
+            .iter()
+            .filter_map(|(_, path)| path.clone())
+            .collect();
+        self.outputs = paths
+            .into_iter()
+            .map(|path| {
+                let state = drv::output_state(&derivation, &path, &mut self.disk_usage);
+                (path, state)
+            })
+            .collect();
+    }
+
+    /// Starts finding out what pulls the selected build in, from what its client was asked to
+    /// build.
+    fn ask_why(&mut self) {
+        let Some(build) = self.selected_build() else {
+            return;
+        };
+        let derivation = build.derivation.clone();
+        let target = self
+            .client(build)
+            .and_then(|client| Some((client.target()?, client.cwd.clone())));
+        let Some((target, cwd)) = target else {
+            self.show_notice("Don't know what the build was started for".to_string());
+            return;
+        };
+
+        let nix = self.local_nix();
+        let sender = self.sender.clone();
+        self.why.insert(derivation.clone(), None);
+        tokio::spawn(async move {
+            let chain = why::why_depends(nix, &target, cwd.as_deref(), &derivation).await;
+            _ = sender.send(Event::App(AppEvent::WhyDepends(derivation, chain)));
+        });
+    }
+
+    /// Saves the builds with their CPU usage to a file, on SIGUSR1. Nobody might be looking at
+    /// the screen, so it goes to the log and the events tab.
+    fn save_snapshot(&mut self) {
+        match export::snapshot(&self.active_builds, &self.cpu) {
+            Ok(path) => {
+                tracing::info!(path = %path.display(), "saved snapshot");
+                self.events
+                    .info(format!("Saved the builds to {}", path.display()));
+            }
+            Err(err) => {
+                tracing::warn!("couldn't save snapshot: {err:#}");
+                self.events
+                    .error(format!("Couldn't save the builds: {err:#}"));
             }
+        }
+    }
 
-            // quitting
-            KeyCode::Char('q') => _ = self.sender.send(Event::App(AppEvent::Quit)),
-            KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
-                _ = self.sender.send(Event::App(AppEvent::Quit));
+    /// Handles key events while the filter is being typed in.
+    fn handle_filter_input(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char(c) => self.keep_selection(|app| app.filter.push(c)),
+            KeyCode::Backspace => self.keep_selection(|app| _ = app.filter.pop()),
+            KeyCode::Enter => self.editing_filter = false,
+            KeyCode::Esc => {
+                self.editing_filter = false;
+                self.keep_selection(|app| app.filter.clear());
             }
             _ => {}
         }
-        Ok(())
     }
 
     /// Processes a received `nix ps` output and schedules the next one to run.
-    fn refresh(&mut self, output: anyhow::Result<ps::Output>) {
-        if let Ok(builds) = output {
-            // TODO: handle errors
-            let previous_selection = self
-                .table_state
-                .selected()
-                .and_then(|i| self.active_builds.get(i))
-                .map(|b| b.main_pid);
+    fn refresh(&mut self, outputs: Vec<anyhow::Result<ps::Output>>, scan: Option<Box<LocalScan>>) {
+        self.refresh_soon = false;
+        let mut builds = vec![];
+        let mut errors = vec![];
+        for (source, output) in self.sources.iter().zip(outputs) {
+            match output {
+                Ok(output) => builds.extend(output),
+                Err(err) => {
+                    // keep showing the old data, the banner tells that it's stale
+                    errors.push(format!("{err:#}"));
+                    builds.extend(
+                        self.active_builds
+                            .iter()
+                            .filter(|b| b.host.as_deref() == source.host())
+                            .cloned(),
+                    );
+                }
+            }
+        }
+        tracing::debug!(builds = builds.len(), errors = errors.len(), "refreshed");
+
+        // only there when the fetch got that far, the last one stays until the next then
+        let found_delegated = match scan.map(|scan| *scan) {
+            Some(scan) => {
+                self.host = scan.host;
+                self.substitutions = scan.substitutions;
+                self.collectors = scan.collectors;
+                self.drv_files = scan.drv_files;
+                self.nice = scan.nice;
+                self.build_users = scan.build_users;
+                self.clients = scan.clients;
+                scan.delegated
+            }
+            None => vec![],
+        };
+        let delegated = self.delegated_builds(&builds, found_delegated);
+        builds.extend(delegated);
+        let seen = builds.len();
+        builds.retain(|b| {
+            let drv = self.drv_files.get(&b.derivation).and_then(Option::as_ref);
+            !self.exclude.excludes(b, drv)
+        });
+        self.excluded = seen - builds.len();
 
-            self.active_builds = builds;
-            let new_selection = previous_selection
-                .and_then(|pid| self.active_builds.iter().position(|b| b.main_pid == pid));
+        for build in &builds {
+            if !self.active_builds.iter().any(|b| b.key() == build.key()) {
+                self.events
+                    .info(format!("Started {}{}", build.derivation, on_host(build)));
+            }
+        }
 
-            self.table_state.select(new_selection);
+        self.sort.apply(&mut builds);
+        self.cpu.update(&builds);
+        self.io.update(&builds);
+        self.net.update(&builds);
+        // old data would make every build look idle
+        let newly_stuck = if errors.is_empty() {
+            self.stuck.update(&builds)
+        } else {
+            vec![]
+        };
+        for build in newly_stuck {
+            self.events.error(format!(
+                "{}{} looks stuck, no CPU or I/O for {} refreshes",
+                build.derivation,
+                on_host(build),
+                self.stuck.idle_for(build),
+            ));
         }
+        for (build, rule) in self.alerts.update(&builds) {
+            let message = format!(
+                "{}{} is taking longer than {}",
+                build.derivation,
+                on_host(build),
+                show_duration(rule.limit()),
+            );
+            if rule.notify {
+                notify::send("Build taking long", &message);
+            }
+            self.events.error(message);
+        }
+        self.cpu_history.update(&builds, &self.cpu);
+        let finished = self.history.update(&self.active_builds, &builds);
+        self.build_counts.push(builds.len());
+        for finished in self.history.finished.iter().take(finished) {
+            self.notifications.finished(finished);
+            // builds on machines that don't tell have neither
+            let mut peaks = vec![];
+            if finished.peaks.processes > 0 {
+                peaks.push(format!("{} processes", finished.peaks.processes));
+            }
+            if finished.peaks.rss.is_some() {
+                peaks.push(show_bytes(finished.peaks.rss));
+            }
+            let peaks = if peaks.is_empty() {
+                String::new()
+            } else {
+                format!(", at most {}", peaks.join(" and "))
+            };
+            self.events.info(format!(
+                "Finished {}{} after {}, {} of CPU{peaks}",
+                finished.build.derivation,
+                on_host(&finished.build),
+                show_duration(finished.duration()),
+                show_duration(finished.build.cpu_time()),
+            ));
+
+            let record = archive::Record::new(finished);
+            if let Err(err) = archive::append(&record) {
+                self.events
+                    .error(format!("Couldn't save finished build: {err:#}"));
+            }
+            if let Some(archive) = &mut self.archive {
+                archive.push(record);
+            }
 
-        // schedule next refresh
-        let duration = self.refresh_interval;
+            // only failed builds are left out, the ones that can't be checked probably worked
+            if let Some(key) = self.duration_key(&finished.build)
+                && self.succeeded(&finished.build) != Some(false)
+                && let Err(err) = self.durations.record(key, finished.duration())
+            {
+                self.events
+                    .error(format!("Couldn't save build duration: {err:#}"));
+            }
+        }
+        self.keep_selection(|app| app.active_builds = builds);
+        self.update_outputs();
+
+        // forget about paused builds that are gone
+        let active = &self.active_builds;
+        self.paused.retain(|pid| {
+            active
+                .iter()
+                .any(|b| b.host.is_none() && b.main_pid == *pid)
+        });
+        self.details_scroll.retain(|(host, nix_pid), _| {
+            active
+                .iter()
+                .any(|b| b.host == *host && b.nix_pid == *nix_pid)
+        });
+
+        if errors.is_empty() {
+            if self.refresh_error.take().is_some() {
+                self.events.info("Getting builds works again");
+            }
+            self.failures = 0;
+            self.retry_at = None;
+            self.last_refresh = Some(Utc::now());
+        } else {
+            self.failures = self.failures.saturating_add(1);
+            let error = errors.join("; ");
+            // only once, not every refresh
+            if self.refresh_error.as_ref() != Some(&error) {
+                self.events.error(error.as_str());
+            }
+            self.refresh_error = Some(error);
+        }
+
+        if self.frozen {
+            self.refresh_requested = false;
+            self.stop_refreshing();
+        } else if self.failures > 0 {
+            // the daemon might be restarting, no need to keep hammering it
+            let delay = self
+                .current_interval()
+                .saturating_mul(2u32.saturating_pow(self.failures.min(16)))
+                .min(MAX_BACKOFF);
+            self.retry_at = TimeDelta::from_std(delay).ok().map(|d| Utc::now() + d);
+            self.schedule_refresh(delay);
+        } else {
+            self.schedule_refresh(self.current_interval());
+        }
+    }
+
+    /// Refreshes as soon as a local build starts or finishes, on top of the timer.
+    fn watch_builds(&self) {
         let sender = self.sender.clone();
-        tokio::spawn(async move {
+        // a plain thread since it blocks forever, tokio would wait for it when quitting
+        thread::spawn(move || {
+            let result =
+                watch::watch_logs(|| sender.send(Event::App(AppEvent::BuildsChanged)).is_ok());
+            if let Err(err) = result {
+                _ = sender.send(Event::App(AppEvent::WatchError(err.to_string())));
+            }
+        });
+    }
+
+    /// Cancels the scheduled refresh, for while refreshing is paused.
+    fn stop_refreshing(&mut self) {
+        if let Some(task) = self.refresh_task.take() {
+            task.abort();
+        }
+    }
+
+    /// Gets the builds, then looks at this machine on a blocking thread, so a busy one doesn't
+    /// hold up the UI.
+    fn fetch(&self) -> impl Future<Output = AppEvent> + Send + 'static {
+        let sources = self.sources.clone();
+        let scanner = self.scanner.clone();
+        let local = self.watches_local();
+        let delegated: Vec<String> = self
+            .queue
+            .delegated()
+            .map(|(_, activity)| activity.target.clone())
+            .collect();
+        async move {
+            let outputs = ps::get(&sources).await;
+            let builds: Vec<ps::Build> = outputs
+                .iter()
+                .filter_map(|output| output.as_ref().ok())
+                .flatten()
+                .cloned()
+                .collect();
+            let scan = tokio::task::spawn_blocking(move || {
+                let mut scanner = scanner.lock().unwrap_or_else(|e| e.into_inner());
+                Box::new(scanner.scan(&builds, &delegated, local))
+            })
+            .await;
+            AppEvent::Refresh(outputs, scan.ok())
+        }
+    }
+
+    /// Gets builds again after `delay`, replacing the refresh that was scheduled before.
+    fn schedule_refresh(&mut self, delay: Duration) {
+        tracing::trace!(?delay, "scheduling a refresh");
+        if let Some(task) = self.refresh_task.take() {
+            task.abort();
+        }
+
+        let fetch = self.fetch();
+        let sender = self.sender.clone();
+        self.refresh_task = Some(tokio::spawn(async move {
             tokio::select! {
                 _ = sender.closed() => {},
-                _ = time::sleep(duration) => {
+                _ = time::sleep(delay) => {
                     // SELECT AGAIN !! to handle exiting mid-thing
                     tokio::select! {
                         _ = sender.closed() => {},
-                        output = ps::get() => {
-                            _ = sender.send(Event::App(AppEvent::Refresh(output)));
+                        refresh = fetch => {
+                            _ = sender.send(Event::App(refresh));
                         },
                     }
                 }
             }
-        });
+        }));
+    }
+
+    fn build_row<'a>(&self, build: &'a ps::Build, nested: bool) -> Row<'a> {
+        let cells: Vec<_> = self
+            .visible_columns()
+            .into_iter()
+            .map(|column| self.column_cell(column, build, nested))
+            .collect();
+
+        let mut row = Row::new(cells);
+        if self.is_paused(build) {
+            return row.dim();
+        }
+        if self.alerts.is_overdue(build) {
+            row = row.bold();
+        }
+        match self.stuck.level(build) {
+            Some(stuck::Level::Idle) => row.fg(self.theme.warning),
+            Some(stuck::Level::Stuck) => row.fg(self.theme.danger),
+            None => row,
+        }
+    }
+
+    fn is_paused(&self, build: &ps::Build) -> bool {
+        build.host.is_none() && self.paused.contains(&build.main_pid)
+    }
+
+    /// Client that started a local build, if it could be found.
+    fn client(&self, build: &ps::Build) -> Option<&Client> {
+        if build.host.is_some() {
+            return None;
+        }
+        self.clients.get(&build.nix_pid)?.as_ref()
     }
 
     fn render_builds(&mut self, frame: &mut Frame, rect: Rect) {
+        let visible = filter_builds(&self.active_builds, &self.clients, &self.filter);
+        let hidden = self.active_builds.len() - visible.len();
+
+        let theme = &self.theme;
+        let mut title = line!["Active builds".fg(theme.title)];
+        if self.editing_filter || !self.filter.is_empty() {
+            title.push_span(" filter: ".dim());
+            title.push_span(self.filter.as_str().fg(theme.accent));
+            if self.editing_filter {
+                title.push_span("_".fg(theme.accent).slow_blink());
+            }
+        }
+        if hidden > 0 {
+            title.push_span(format!(" ({hidden} hidden)").dim());
+        }
+        if self.excluded > 0 {
+            title.push_span(format!(" ({} excluded)", self.excluded).dim());
+        }
+
+        let refresh = if self.frozen {
+            let age = self
+                .last_refresh
+                .map(|at| format!(" data from {} ago ", show_duration(self.now - at)))
+                .unwrap_or_default();
+            line![
+                " PAUSED ".fg(theme.error_fg).bg(theme.danger).bold(),
+                age.fg(theme.hint)
+            ]
+        } else {
+            // before the interval, so that clicking on - and + still works the same
+            let slowdown = if self.unfocused && self.unfocused_slowdown > 1 {
+                format!(
+                    "{}{} while unfocused ",
+                    glyphs::pick("×", "x"),
+                    self.unfocused_slowdown
+                )
+            } else {
+                String::new()
+            };
+            line![
+                slowdown.dim(),
+                "-".fg(theme.key),
+                format!(" {}ms ", self.refresh_interval.as_millis()).fg(theme.hint),
+                "+".fg(theme.key),
+            ]
+        };
+
         let block = Block::bordered()
-            .title_top(line!["Active builds".cyan()])
-            .title_top(
-                line![
-                    "-".red(),
-                    format!(" {}ms ", self.refresh_interval.as_millis()).white(),
-                    "+".red(),
-                ]
-                .alignment(Alignment::Right),
-            )
-            .title_bottom(line!["↑".red(), " select ".white(), "↓".red()])
-            .title_bottom(line!["/".red(), " change layout".white()].alignment(Alignment::Right))
-            .border_type(BorderType::Rounded)
-            .border_style(Style::new().black())
+            .title_top(title)
+            .title_top(refresh.alignment(Alignment::Right))
+            .title_bottom(self.build_totals())
+            .border_set(theme.border_set())
+            .border_style(theme.border)
             .padding(Padding::horizontal(1));
 
-        let header = Row::new(vec![
-            Cell::from(text!("PID").alignment(Alignment::Right)),
-            Cell::from("Package"),
-            Cell::from("Version"),
-            Cell::from("Time"),
-        ])
-        .dim()
-        .underlined();
-
-        let table = Table::new(
-            &self.active_builds,
-            constraints![
-                ==7,
-                ==80%,
-                ==20%,
-                ==10
-            ],
-        )
-        .block(block)
-        .header(header)
-        .row_highlight_style(Style::new().bg(Color::Rgb(19, 57, 117)));
+        let columns = self.visible_columns();
+        let header: Vec<_> = columns.iter().map(|c| self.column_header(*c)).collect();
+        let header = Row::new(header).dim().underlined();
+        let widths: Vec<_> = columns.iter().map(|c| self.column_width(*c)).collect();
 
-        frame.render_stateful_widget(table, rect, &mut self.table_state);
-    }
+        let rows: Vec<_> = self
+            .table_rows()
+            .into_iter()
+            .map(|row| match row {
+                TableRow::Build { build, nested } => self.build_row(build, nested),
+                TableRow::Group {
+                    pname,
+                    builds,
+                    expanded,
+                } => self.group_row(pname, &builds, expanded),
+            })
+            .collect();
 
-    fn render_build_details(&self, frame: &mut Frame, rect: Rect, build: &ps::Build) {
-        let block = Block::bordered()
-            .title_top(line!["Build".cyan()])
-            .border_type(BorderType::Rounded)
-            .border_style(Style::new().black())
-            .padding(Padding::uniform(1));
+        let table = Table::new(rows, widths)
+            .block(block)
+            .header(header)
+            .row_highlight_style(Style::new().bg(theme.highlight));
 
-        let layout = vertical![==5, ==100%].split(block.inner(rect));
+        // the rows borrow all of self
+        let mut table_state = self.table_state;
+        frame.render_stateful_widget(table, rect, &mut table_state);
+        self.table_state = table_state;
+    }
 
-        let rows = vec![
+    /// Rows of the table at the top of the details pane.
+    fn build_properties<'a>(&'a self, build: &'a ps::Build) -> Vec<Row<'a>> {
+        let theme = &self.theme;
+        let mut rows = vec![
             row![
                 text!("Derivation").alignment(Alignment::Right).dim(),
-                format!("/nix/store/{}", build.derivation).magenta(),
+                store::drv_path(&build.derivation).fg(theme.derivation),
             ],
             row![
                 text!("Started at").alignment(Alignment::Right).dim(),
-                format!("{}", build.started()).yellow(),
+                match build.started() {
+                    Some(started) => line![
+                        started.to_string().fg(theme.accent),
+                        format!(" ({} ago)", show_duration(self.now - started)).dim(),
+                    ],
+                    None => line!["unknown".fg(theme.accent)],
+                },
             ],
             row![
                 text!("Main PID").alignment(Alignment::Right).dim(),
@@ -251,114 +1689,696 @@ impl App {
             ],
         ];
 
+        if let Some(cgroup) = build.cgroup {
+            let pids = cgroup.pids.map_or("-".to_string(), |p| p.to_string());
+            rows.push(row![
+                text!("Processes").alignment(Alignment::Right).dim(),
+                line![pids, " (from cgroup)".dim()],
+            ]);
+        }
+
+        if let Some((id, activity)) = self.queue.build(&build.derivation) {
+            let mut line = line![
+                activity
+                    .phase
+                    .as_deref()
+                    .unwrap_or("started")
+                    .fg(theme.accent)
+            ];
+            if let Some((done, expected)) = self.queue.progress_under(id) {
+                line.push_span(format!(
+                    "  {} {}/{}",
+                    progress_bar(done, expected, 20),
+                    show_bytes(Some(done)),
+                    show_bytes(Some(expected))
+                ));
+            }
+            rows.push(row![
+                text!("Progress").alignment(Alignment::Right).dim(),
+                line
+            ]);
+        }
+
+        if let Some(rule) = self.alerts.rule(build) {
+            let limit = show_duration(rule.limit());
+            let line = if self.alerts.is_overdue(build) {
+                line![limit.fg(theme.danger).bold(), " (over)".fg(theme.danger)]
+            } else {
+                line![limit]
+            };
+            rows.push(row![
+                text!("Time limit").alignment(Alignment::Right).dim(),
+                line
+            ]);
+        }
+
+        if let Some((usual, count)) = self.usual_duration(build) {
+            let builds = if count == 1 { "build" } else { "builds" };
+            rows.push(row![
+                text!("Usually takes").alignment(Alignment::Right).dim(),
+                line![
+                    show_duration(usual),
+                    format!(" (last {count} {builds})").dim()
+                ],
+            ]);
+        }
+
+        if let Some(level) = self.stuck.level(build) {
+            let (label, color) = match level {
+                stuck::Level::Idle => ("idle", theme.warning),
+                stuck::Level::Stuck => ("stuck", theme.danger),
+            };
+            rows.push(row![
+                text!("Activity").alignment(Alignment::Right).dim(),
+                line![
+                    label.fg(color).bold(),
+                    format!(
+                        ", no CPU or I/O for {} refreshes",
+                        self.stuck.idle_for(build)
+                    )
+                    .dim(),
+                ],
+            ]);
+        }
+
+        if let Some(io) = build.io() {
+            let (read, write) = self.io.build(build).unzip();
+            rows.push(row![
+                text!("I/O").alignment(Alignment::Right).dim(),
+                line![
+                    format!("read {}", show_bytes(Some(io.read_bytes))),
+                    format!(" ({})", show_rate(read)).dim(),
+                    format!(", wrote {}", show_bytes(Some(io.write_bytes))),
+                    format!(" ({})", show_rate(write)).dim(),
+                ],
+            ]);
+        }
+
+        if let Some(net) = build.net {
+            let (down, up) = self.net.build(build).unzip();
+            rows.push(row![
+                text!("Network").alignment(Alignment::Right).dim(),
+                line![
+                    format!("received {}", show_bytes(Some(net.received_bytes))),
+                    format!(" ({})", show_rate(down)).dim(),
+                    format!(", sent {}", show_bytes(Some(net.sent_bytes))),
+                    format!(" ({})", show_rate(up)).dim(),
+                ],
+            ]);
+        }
+
+        if let Some(host) = &build.host {
+            rows.push(row![
+                text!("Host").alignment(Alignment::Right).dim(),
+                host.as_str(),
+            ]);
+        } else if let Some(nice) = self.nice.get(&build.main_pid) {
+            rows.push(row![
+                text!("Nice").alignment(Alignment::Right).dim(),
+                line![
+                    nice.to_string(),
+                    format!(" ({} to {})", priority::MIN_NICE, priority::MAX_NICE).dim(),
+                ],
+            ]);
+        }
+
+        if let Some(user) = self.build_users.get(&build.main_pid)
+            && build.host.is_none()
+        {
+            let note = if user.is_auto() {
+                " (auto-allocated)".to_string()
+            } else {
+                format!(" (UID {})", user.uid)
+            };
+            rows.push(row![
+                text!("Build user").alignment(Alignment::Right).dim(),
+                line![user.label(), note.dim()],
+            ]);
+        }
+
+        if let Some(Some(client)) = self.clients.get(&build.nix_pid)
+            && build.host.is_none()
+        {
+            let mut line = line![client.command()];
+            if let Some(user) = &client.user {
+                line.push_span(format!(" ({user}, PID {})", client.pid).dim());
+            }
+            rows.push(row![
+                text!("Client").alignment(Alignment::Right).dim(),
+                line
+            ]);
+            if let Some(flake) = client.flake() {
+                rows.push(row![
+                    text!("Flake").alignment(Alignment::Right).dim(),
+                    flake.fg(theme.accent),
+                ]);
+            }
+        }
+
+        match self.why.get(&build.derivation) {
+            Some(Some(Ok(chain))) => {
+                for (i, name) in chain.iter().enumerate() {
+                    let (label, arrow) = if i == 0 {
+                        ("Why", "")
+                    } else {
+                        ("", glyphs::pick("→ ", "-> "))
+                    };
+                    rows.push(row![
+                        text!(label).alignment(Alignment::Right).dim(),
+                        line![arrow.dim(), name.as_str().fg(theme.pname)],
+                    ]);
+                }
+            }
+            Some(Some(Err(err))) => rows.push(row![
+                text!("Why").alignment(Alignment::Right).dim(),
+                err.as_str().dim(),
+            ]),
+            Some(None) => rows.push(row![
+                text!("Why").alignment(Alignment::Right).dim(),
+                "asking nix why-depends...".dim(),
+            ]),
+            None => {}
+        }
+
+        match self.derivations.get(&build.derivation) {
+            Some(Some(Ok(info))) => {
+                if let Some(pname) = &info.pname {
+                    let version = info.version.as_deref().unwrap_or_default();
+                    rows.push(row![
+                        text!("Package").alignment(Alignment::Right).dim(),
+                        line![
+                            pname.as_str().fg(theme.pname),
+                            " ",
+                            version.fg(theme.version)
+                        ],
+                    ]);
+                }
+                rows.push(row![
+                    text!("System").alignment(Alignment::Right).dim(),
+                    info.system.as_str(),
+                ]);
+                rows.push(row![
+                    text!("Builder").alignment(Alignment::Right).dim(),
+                    info.command(),
+                ]);
+                for (i, (name, path)) in info.outputs.iter().enumerate() {
+                    let label = if i == 0 { "Outputs" } else { "" };
+                    let mut line = line![
+                        format!("{name} ").fg(theme.accent),
+                        path.as_deref().unwrap_or("(content-addressed)"),
+                    ];
+                    match path.as_ref().and_then(|path| self.outputs.get(path)) {
+                        Some(OutputState::Missing) => line.push_span(" not there yet".dim()),
+                        Some(OutputState::Sandbox(bytes)) => line.push_span(
+                            format!(" being written, {}", show_bytes(Some(*bytes)))
+                                .fg(theme.accent),
+                        ),
+                        Some(OutputState::Store(bytes)) => line.push_span(
+                            format!(" in the store, {}", show_bytes(Some(*bytes))).dim(),
+                        ),
+                        None => {}
+                    }
+                    rows.push(row![text!(label).alignment(Alignment::Right).dim(), line]);
+                }
+            }
+            Some(Some(Err(err))) => rows.push(row![
+                text!("Info").alignment(Alignment::Right).dim(),
+                err.lines().next().unwrap_or_default().dim(),
+            ]),
+            Some(None) => rows.push(row![
+                text!("Info").alignment(Alignment::Right).dim(),
+                "loading...".dim(),
+            ]),
+            None => {}
+        }
+
+        rows
+    }
+
+    fn render_build_details(&self, frame: &mut Frame, areas: &Areas, build: &ps::Build) {
+        // padding has to match the one in App::areas
+        let theme = &self.theme;
+        let block = Block::bordered()
+            .title_top(line!["Build".fg(theme.title)])
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::uniform(1));
+
+        let rows = self
+            .build_properties(build)
+            .into_iter()
+            .skip(areas.properties_offset);
         let properties = Table::new(rows, constraints![==10, ==100%]);
-        let p = Paragraph::new(render_tree(build, build.main_pid));
 
-        frame.render_widget(block, rect);
-        frame.render_widget(properties, layout[0]);
-        frame.render_widget(p, layout[1]);
+        frame.render_widget(block, areas.details);
+        frame.render_widget(properties, areas.properties);
+        self.render_tree(frame, areas, build);
+        self.render_log(frame, areas.log);
     }
 
-    fn render_details(&self, frame: &mut Frame, rect: Rect) {
-        if let Some(selected) = self
-            .table_state
-            .selected()
-            .and_then(|i| self.active_builds.get(i))
-        {
-            self.render_build_details(frame, rect, selected);
-        } else {
-            let text = text!("Select a build to show its details").dim();
-            let area = rect.centered(constraint!(==text.width() as u16), constraint!(==1));
-            frame.render_widget(text, area);
+    fn render_tree(&self, frame: &mut Frame, areas: &Areas, build: &ps::Build) {
+        let theme = &self.theme;
+        let lines = self.tree.lines(build);
+        let items = self.tree_items(build, &lines, areas.tree.width);
+
+        let mut list = List::new(items);
+        let mut state = ListState::default().with_offset(areas.tree_offset);
+        if self.focus == Focus::Tree {
+            list = list.highlight_style(Style::new().bg(theme.highlight));
+            // a cursor scrolled off the top would drag the list back up
+            state.select(
+                self.tree
+                    .cursor_index(&lines)
+                    .filter(|i| *i >= areas.tree_offset),
+            );
         }
+
+        frame.render_stateful_widget(list, areas.tree, &mut state);
     }
 
-    fn render(&mut self, frame: &mut Frame) {
-        let layout = Layout::new(self.direction, constraints![==40%, ==60%]).split(frame.area());
-        self.render_builds(frame, layout[0]);
-        self.render_details(frame, layout[1]);
+    /// Lines of the process tree with their CPU and memory, fit (or wrapped) to the width.
+    pub(super) fn tree_items<'a>(
+        &'a self,
+        build: &ps::Build,
+        lines: &'a [TreeLine],
+        width: u16,
+    ) -> Vec<Text<'a>> {
+        let theme = &self.theme;
+        lines
+            .iter()
+            .map(|line| {
+                let cpu = show_cpu(self.cpu.process(build, line.pid));
+                let rss = show_bytes(build.process(line.pid).and_then(|p| p.rss));
+                let mut item = line![
+                    format!("{cpu:>5} ").fg(theme.cpu),
+                    format!("{rss:>5} ").fg(theme.memory),
+                    line.prefix.as_str(),
+                ];
+                if let Some(hidden) = line.hidden {
+                    item.push_span(format!("[+{hidden}] ").fg(theme.accent));
+                }
+
+                let style = line
+                    .argv
+                    .first()
+                    .and_then(|program| self.categories.of(program))
+                    .map_or_else(Style::new, |category| {
+                        Style::new().fg(theme.category(category))
+                    });
+                let mut text = if self.wrap_commands {
+                    let mut wrapped = self.wrapped_command(line, width).into_iter();
+                    item.push_span(Span::styled(wrapped.next().unwrap_or_default(), style));
+                    let mut text = Text::from(item);
+                    for rest in wrapped {
+                        text.push_line(line![
+                            "            ",
+                            line.indent.as_str(),
+                            Span::styled(rest, style),
+                        ]);
+                    }
+                    text
+                } else {
+                    // the command scrolls, with … where it goes on off-screen
+                    let room = (width as usize).saturating_sub(item.width());
+                    let command = line.command();
+                    let mut shown: String = command.chars().skip(self.tree.scroll).collect();
+                    if self.tree.scroll > 0 && !shown.is_empty() {
+                        item.push_span(glyphs::pick("…", "<").fg(theme.accent));
+                        shown.remove(0);
+                    }
+                    if shown.chars().count() > room {
+                        let cut: String = shown.chars().take(room.saturating_sub(1)).collect();
+                        item.push_span(Span::styled(cut, style));
+                        item.push_span(glyphs::pick("…", ">").fg(theme.accent));
+                        return Text::from(item);
+                    }
+                    item.push_span(Span::styled(shown, style));
+                    Text::from(item)
+                };
+
+                // only for processes that are actually doing some I/O, to keep it quiet
+                if let Some((read, write)) = self.io.process(build, line.pid)
+                    && read + write > 0.0
+                    && let Some(last) = text.lines.last_mut()
+                {
+                    last.push_span(
+                        format!("  r {} w {}", show_rate(Some(read)), show_rate(Some(write))).dim(),
+                    );
+                }
+                text
+            })
+            .collect()
     }
-}
 
-impl<'a> From<&'a ps::Build> for Row<'a> {
-    fn from(value: &'a ps::Build) -> Row<'a> {
-        // drop hash prefix and .drv suffix
-        let name = &value.derivation[33..value.derivation.len() - 4];
+    /// A command of the process tree, wrapped to fit next to its CPU, memory and place in the tree.
+    fn wrapped_command(&self, line: &TreeLine, width: u16) -> Vec<String> {
+        // CPU and memory take 12
+        let badge = line
+            .hidden
+            .map_or(0, |hidden| format!("[+{hidden}] ").len());
+        let first = (width as usize).saturating_sub(12 + line.prefix.chars().count() + badge);
+        let rest = (width as usize).saturating_sub(12 + line.indent.chars().count());
+        tree::wrap(&line.command(), first, rest)
+    }
 
-        let (pname, version) = if let Some((pname, version)) = name.rsplit_once('-') {
-            (pname, version)
+    /// How many rows each line of the process tree takes on screen.
+    pub(super) fn tree_heights(&self, lines: &[TreeLine], width: u16) -> Vec<usize> {
+        lines
+            .iter()
+            .map(|line| {
+                if self.wrap_commands {
+                    self.wrapped_command(line, width).len()
+                } else {
+                    1
+                }
+            })
+            .collect()
+    }
+
+    fn render_log(&self, frame: &mut Frame, rect: Rect) {
+        let Some(log) = &self.log else {
+            return;
+        };
+
+        let theme = &self.theme;
+        let border = if self.focus == Focus::Log {
+            theme.focus
         } else {
-            (name, "")
+            theme.border
         };
 
-        row![
-            text!(format!("{}", value.main_pid)).alignment(Alignment::Right),
-            pname.light_green(),
-            version.light_cyan(),
-            show_duration(Utc::now() - value.started()),
-        ]
-    }
-}
+        let mut block = Block::bordered()
+            .title_top(line!["Log".fg(theme.title)])
+            .border_set(theme.border_set())
+            .border_style(border);
 
-fn show_duration(duration: TimeDelta) -> String {
-    let mut duration = duration;
-    let mut components = vec![];
+        if log.scroll > 0 {
+            block = block.title_top(
+                line![format!("{} lines below", log.scroll).fg(theme.accent)]
+                    .alignment(Alignment::Right),
+            );
+        }
 
-    if duration.num_days() > 0 {
-        components.push(format!("{}d", duration.num_days()));
-        duration = duration - TimeDelta::days(duration.num_days());
-    }
+        let inner = block.inner(rect);
+        let lines: Vec<_> = log
+            .window(inner.height as usize)
+            .iter()
+            .map(|line| line![line.as_str()])
+            .collect();
 
-    if duration.num_hours() > 0 {
-        components.push(format!("{}h", duration.num_hours()));
-        duration = duration - TimeDelta::hours(duration.num_hours());
+        frame.render_widget(block, rect);
+        frame.render_widget(Paragraph::new(lines), inner);
     }
 
-    if duration.num_minutes() > 0 && components.len() < 2 {
-        components.push(format!("{}m", duration.num_minutes()));
-        duration = duration - TimeDelta::minutes(duration.num_minutes());
+    fn render_details(&self, frame: &mut Frame, areas: &Areas) {
+        let rect = areas.details;
+        if let Some(selected) = self.selected_build() {
+            self.render_build_details(frame, areas, selected);
+        } else {
+            let text = text!("Select a build to show its details").dim();
+            let area = rect.centered(constraint!(==text.width() as u16), constraint!(==1));
+            frame.render_widget(text, area);
+        }
     }
 
-    if duration.num_seconds() > 0 && components.len() < 2 {
-        components.push(format!("{}s", duration.num_seconds()));
+    fn render_error(&self, frame: &mut Frame, rect: Rect, error: &str) {
+        let mut status = match self.last_refresh {
+            Some(at) => format!("data is {} old", show_duration(self.now - at)),
+            None => "no data yet".to_string(),
+        };
+        match self.retry_at {
+            Some(at) if at > self.now => {
+                status.push_str(&format!(", retrying in {}", show_duration(at - self.now)));
+            }
+            _ => status.push_str(", retrying"),
+        }
+
+        // the error can be long, so it goes last
+        let banner = line![
+            " Refresh failed ".bold(),
+            format!("({status}): ").dim(),
+            error.lines().next().unwrap_or_default().to_string(),
+        ]
+        .fg(self.theme.error_fg)
+        .bg(self.theme.error_bg);
+
+        frame.render_widget(Paragraph::new(banner), rect);
     }
 
-    components.join(" ")
+    /// Draws everything as of `now`. The clock isn't looked at while drawing, so the same state
+    /// and time always give the same picture.
+    pub fn render(&mut self, frame: &mut Frame, now: DateTime<Utc>) {
+        self.now = now;
+        self.area = frame.area();
+        if self.too_small() {
+            self.render_compact(frame);
+            return;
+        }
+        let areas = self.areas(self.area);
+
+        self.render_tabs(frame, areas.tabs);
+        if let Some(rect) = areas.hints {
+            self.render_hints(frame, rect);
+        }
+        if let Some(rect) = areas.banner {
+            if let Some(error) = &self.refresh_error {
+                self.render_error(frame, rect, error);
+            } else if let Some(notice) = &self.notice {
+                let line = line![format!(" {notice} ")]
+                    .fg(self.theme.accent)
+                    .reversed();
+                frame.render_widget(Paragraph::new(line), rect);
+            } else if !self.collectors.is_empty() {
+                let pids: Vec<_> = self.collectors.iter().map(|p| p.to_string()).collect();
+                let line = line![
+                    " Garbage collection running ".bold(),
+                    format!("(PID {}), new builds will wait for it ", pids.join(", ")),
+                ]
+                .fg(self.theme.error_fg)
+                .bg(self.theme.danger);
+                frame.render_widget(Paragraph::new(line), rect);
+            }
+        }
+
+        match self.tab {
+            Tab::Builds => {
+                if let Some(rect) = areas.summary {
+                    self.render_summary(frame, rect);
+                }
+                self.render_builds(frame, areas.builds);
+                if let Some(rect) = areas.downloads {
+                    self.render_downloads(frame, rect);
+                }
+                if let Some(rect) = areas.queue {
+                    self.render_queue(frame, rect);
+                }
+                if let Some(rect) = areas.disk {
+                    self.render_disk(frame, rect);
+                }
+                if self.show_details {
+                    self.render_details(frame, &areas);
+                }
+            }
+            Tab::History => match (self.history_report, &self.archive) {
+                (Some(report), Some(archive)) => history::render_report(
+                    frame,
+                    areas.content,
+                    &self.theme,
+                    report,
+                    &report.table(archive, usize::MAX),
+                    self.history_scroll,
+                ),
+                _ => history::render(
+                    frame,
+                    areas.content,
+                    &self.theme,
+                    &self.history,
+                    self.history_scroll,
+                ),
+            },
+            Tab::System => self.render_system(frame, areas.content),
+            Tab::Logs => self.render_logs(frame, areas.content),
+        }
+
+        if let Some(view) = &self.tree_view {
+            // everything but the hints
+            let mut rect = frame.area();
+            rect.height -= areas.hints.map_or(0, |hints| hints.height);
+            self.render_tree_view(frame, rect, view);
+        }
+
+        if let Some(confirm) = &self.confirm {
+            confirm.render(frame, &self.theme);
+        }
+
+        if let Some(gc) = &self.gc {
+            self.render_gc(frame, gc);
+        }
+
+        if let Some(view) = &self.derivation_view {
+            self.render_derivation_view(frame, view);
+        }
+
+        if let Some(view) = &self.environ_view {
+            self.render_environ_view(frame, view);
+        }
+
+        if let Some(view) = &self.files_view {
+            self.render_files_view(frame, view);
+        }
+
+        if let Some(cursor) = self.column_menu {
+            self.render_column_menu(frame, cursor);
+        }
+
+        if let Some(menu) = &self.signal_menu {
+            self.render_signal_menu(frame, menu);
+        }
+
+        if let Some(palette) = &self.palette {
+            self.render_palette(frame, palette);
+        }
+
+        if self.show_help {
+            help::render(frame, &self.theme, self.help_scroll);
+        }
+    }
 }
 
-fn render_tree(build: &ps::Build, pid: usize) -> String {
-    let mut components = vec![];
+/// ` on host` for remote builds, for messages.
+fn on_host(build: &ps::Build) -> String {
+    build
+        .host
+        .as_ref()
+        .map(|host| format!(" on {host}"))
+        .unwrap_or_default()
+}
 
-    let Some(top) = build.processes.iter().find(|p| p.pid == pid) else {
-        return "".to_string();
+/// Builds whose derivation, host or client user has the filter in it. `user:name` only matches
+/// builds of that user.
+fn filter_builds<'a>(
+    builds: &'a [ps::Build],
+    clients: &HashMap<usize, Option<Client>>,
+    filter: &str,
+) -> Vec<&'a ps::Build> {
+    let user = |build: &ps::Build| {
+        let client = clients.get(&build.nix_pid)?.as_ref()?;
+        build.host.is_none().then_some(client.user.as_deref()?)
     };
 
-    components.push(top.argv.join(" "));
+    if let Some(name) = filter.strip_prefix("user:") {
+        return builds
+            .iter()
+            .filter(|b| user(b).is_some_and(|u| u == name.trim()))
+            .collect();
+    }
 
-    let children: Vec<&ps::BuildProcess> = build
-        .processes
+    let filter = filter.to_lowercase();
+    let matches = |s: &str| s.to_lowercase().contains(&filter);
+    builds
         .iter()
-        .filter(|p| p.parent_pid == pid)
-        .collect();
-    for (i, child) in children.iter().enumerate() {
-        let last = i == children.len() - 1;
-        let subtree = render_tree(build, child.pid);
-        let mut lines = subtree.lines();
-        if let Some(line) = lines.next() {
-            if last {
-                components.push(format!("└─── {line}"));
-            } else {
-                components.push(format!("├─── {line}"));
-            }
+        .filter(|b| {
+            matches(&b.derivation)
+                || b.host.as_deref().is_some_and(matches)
+                || user(b).is_some_and(matches)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyEventKind, KeyEventState, KeyModifiers};
+    use futures::{FutureExt, future::BoxFuture};
+    use ratatui::{Terminal, backend::TestBackend};
+
+    /// Made up `nix ps` output, derivations without the store dir like Nix gives them.
+    const OUTPUT: &str = r#"[
+        {
+            "derivation": "0c7c1ljqnvjkpkqdkyvb6zzba6l1pc98-hello-2.12.1.drv",
+            "mainPid": 2001,
+            "nixPid": 2000,
+            "startTime": 1700000000,
+            "processes": [{"pid": 2001, "argv": ["bash", "-e", "builder.sh"], "utime": 90, "stime": 30}]
+        },
+        {
+            "derivation": "5gq7bf8xx8c0pwkdfrh3bn5l0sxm5jvz-firefox-130.0.drv",
+            "mainPid": 2011,
+            "nixPid": 2010,
+            "startTime": 1699999000,
+            "processes": [{"pid": 2011, "argv": ["make", "-j8"], "utime": 500, "stime": 100}]
         }
-        for line in lines {
-            if last {
-                components.push(format!("     {line}"));
-            } else {
-                components.push(format!("│    {line}"));
-            }
+    ]"#;
+
+    /// Always the same builds, on another machine so nothing here gets looked at for them.
+    #[derive(Debug)]
+    struct Fixed;
+
+    impl DataSource for Fixed {
+        fn fetch(&self) -> BoxFuture<'_, anyhow::Result<ps::Output>> {
+            async { Ok(ps::parse(OUTPUT.as_bytes())?) }.boxed()
+        }
+
+        fn host(&self) -> Option<&str> {
+            Some("builder")
         }
+
+        fn command(&self, _program: &str) -> Option<Command> {
+            None
+        }
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Terminal(TerminalEvent::Key(KeyEvent {
+            code,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }))
+    }
+
+    fn screen(app: &mut App, now: DateTime<Utc>) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| app.render(frame, now)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width.into())
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 
-    components.join("\n")
+    #[tokio::test]
+    async fn typing_a_filter_hides_other_builds() {
+        let mut app = App::new(Config::default(), Theme::dark());
+        app.sources = Arc::new([Box::new(Fixed) as Box<dyn DataSource>]);
+        // what looking at this machine found, which is nothing for builds on another one
+        let outputs = ps::get(&app.sources).await;
+        let scan = Box::new(LocalScan::default());
+        app.handle_event(Event::App(AppEvent::Refresh(outputs, Some(scan))));
+        let now = DateTime::from_timestamp_secs(1_700_000_300).unwrap();
+
+        let before = screen(&mut app, now);
+        assert!(before.contains("hello"));
+        assert!(before.contains("firefox"));
+        // how long each has been running, from the fixed now
+        assert!(before.contains("21m 40s"));
+        assert!(before.contains("5m "));
+
+        for code in [
+            KeyCode::Char('f'),
+            KeyCode::Char('f'),
+            KeyCode::Char('i'),
+            KeyCode::Char('r'),
+            KeyCode::Enter,
+        ] {
+            app.handle_event(key(code));
+        }
+        let after = screen(&mut app, now);
+        assert!(after.contains("filter: fir"));
+        assert!(after.contains("firefox"));
+        assert!(!after.contains("hello"));
+        assert!(after.contains("(1 hidden)"));
+    }
 }
@@ -0,0 +1,258 @@
+use super::{App, sort::SortKey};
+use crate::{
+    columns::Column,
+    glyphs,
+    phase::{self, Phase},
+    ps,
+    stats::{show_bytes, show_cpu, show_duration, show_elapsed, show_rate, sparkline},
+};
+use crossterm::event::{KeyCode, KeyEvent};
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint},
+    macros::{constraint, line},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, Cell, Clear, List, ListState, Padding},
+};
+
+impl Column {
+    pub fn sort_key(self) -> Option<SortKey> {
+        match self {
+            Column::Pid => Some(SortKey::Pid),
+            Column::Host => Some(SortKey::Host),
+            Column::Package => Some(SortKey::Package),
+            Column::Version => Some(SortKey::Version),
+            Column::CpuTime => Some(SortKey::CpuTime),
+            Column::Time => Some(SortKey::Time),
+            _ => None,
+        }
+    }
+}
+
+impl App {
+    /// Columns that are actually shown, in order.
+    pub(super) fn visible_columns(&self) -> Vec<Column> {
+        self.columns
+            .iter()
+            .copied()
+            .filter(|c| *c != Column::Host || self.sources.len() > 1)
+            .collect()
+    }
+
+    pub(super) fn column_width(&self, column: Column) -> Constraint {
+        match self.column_widths.get(&column) {
+            Some(width) => Constraint::Length(*width),
+            None => column.default_width(),
+        }
+    }
+
+    pub(super) fn column_header(&self, column: Column) -> Cell<'static> {
+        let indicator = column.sort_key().map_or("", |key| self.sort.indicator(key));
+        let line = Line::from(format!("{}{indicator}", column.title()));
+        if column.right_aligned() {
+            Cell::from(line.alignment(Alignment::Right))
+        } else {
+            Cell::from(line)
+        }
+    }
+
+    /// A cell of a build's row. Nested builds are the ones under a group.
+    pub(super) fn column_cell<'a>(
+        &self,
+        column: Column,
+        build: &'a ps::Build,
+        nested: bool,
+    ) -> Cell<'a> {
+        let theme = &self.theme;
+        let (pname, version) = build.name();
+        let line = match column {
+            Column::Pid if self.is_paused(build) => {
+                line![
+                    glyphs::pick("⏸ ", "= ").fg(theme.accent),
+                    build.main_pid.to_string()
+                ]
+            }
+            Column::Pid if build.main_pid == 0 => line!["-".dim()],
+            Column::Pid => line![build.main_pid.to_string()],
+            Column::Host => line![build.host.as_deref().unwrap_or("local")],
+            Column::User => line![
+                self.client(build)
+                    .and_then(|c| c.user.clone())
+                    .unwrap_or_else(|| "-".to_string())
+            ],
+            Column::BuildUser => match self.build_users.get(&build.main_pid) {
+                Some(user) if build.host.is_none() => line![user.label()],
+                _ => line!["-".dim()],
+            },
+            Column::Client => line![
+                self.client(build)
+                    .map(|c| c.command())
+                    .unwrap_or_else(|| "-".to_string())
+            ],
+            Column::Package => {
+                let indent = if nested { "  " } else { "" };
+                let mut line = line![indent, pname.fg(theme.pname)];
+                if build.delegated {
+                    line.push_span(" remote".fg(theme.hint));
+                }
+                line
+            }
+            Column::Version => line![version.fg(theme.version)],
+            Column::Phase => match self.phase(build) {
+                Some(phase) => line![phase.name().fg(theme.accent)],
+                None => line!["-".dim()],
+            },
+            Column::Cpu => line![show_cpu(self.cpu.build(build))],
+            Column::CpuTime => line![show_duration(build.cpu_time())],
+            Column::CpuGraph => {
+                let width = match self.column_width(column) {
+                    Constraint::Length(width) => width as usize,
+                    _ => 20,
+                };
+                let samples = self.cpu_history.build(build);
+                let graph = samples.map(|s| sparkline(s, width)).unwrap_or_default();
+                line![graph.fg(theme.cpu)]
+            }
+            // delegated builds run their processes on another machine
+            Column::Processes if build.processes.is_empty() => line!["-".dim()],
+            Column::Processes => line![build.processes.len().to_string()],
+            Column::Memory => line![show_bytes(build.rss())],
+            Column::Read => line![show_rate(self.io.build(build).map(|(read, _)| read))],
+            Column::Write => line![show_rate(self.io.build(build).map(|(_, write)| write))],
+            Column::Download => line![show_rate(self.net.build(build).map(|(down, _)| down))],
+            Column::Upload => line![show_rate(self.net.build(build).map(|(_, up)| up))],
+            Column::Time if self.alerts.is_overdue(build) => {
+                line![show_elapsed(build.elapsed(self.now)).fg(theme.danger)]
+            }
+            Column::Time => line![show_elapsed(build.elapsed(self.now))],
+            Column::Eta => match (self.usual_duration(build), build.elapsed(self.now)) {
+                (Some((usual, _)), Some(elapsed))
+                    if elapsed.num_seconds() <= usual.num_seconds() =>
+                {
+                    line![show_duration(usual - elapsed)]
+                }
+                // taking longer than usual
+                (Some((usual, _)), Some(elapsed)) => {
+                    line![format!("+{}", show_duration(elapsed - usual)).fg(theme.warning)]
+                }
+                _ => line!["-".dim()],
+            },
+        };
+
+        if column.right_aligned() {
+            Cell::from(line.alignment(Alignment::Right))
+        } else {
+            Cell::from(line)
+        }
+    }
+
+    /// The phase of a build. The client's activity log and the build log know for sure, guessing
+    /// from the processes is the last resort.
+    fn phase(&self, build: &ps::Build) -> Option<Phase> {
+        let from_activity = self
+            .queue
+            .build(&build.derivation)
+            .and_then(|(_, activity)| activity.phase.as_deref())
+            .and_then(Phase::from_function);
+        if from_activity.is_some() {
+            return from_activity;
+        }
+
+        let from_log = self
+            .log
+            .as_ref()
+            .filter(|log| log.derivation == build.derivation)
+            .and_then(|log| phase::from_log(&log.lines));
+        from_log.or_else(|| phase::detect(build))
+    }
+
+    /// Columns in the order the menu lists them: shown ones first, then the rest.
+    fn menu_columns(&self) -> Vec<(Column, bool)> {
+        let hidden = Column::ALL
+            .into_iter()
+            .filter(|c| !self.columns.contains(c));
+        self.columns
+            .iter()
+            .map(|c| (*c, true))
+            .chain(hidden.map(|c| (c, false)))
+            .collect()
+    }
+
+    /// Handles key events while the column menu is open.
+    pub(super) fn handle_column_menu(&mut self, key_event: KeyEvent) {
+        let Some(cursor) = self.column_menu else {
+            return;
+        };
+        let entries = self.menu_columns();
+        let (column, shown) = entries[cursor];
+
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.column_menu = Some(cursor.saturating_sub(1));
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.column_menu = Some((cursor + 1).min(entries.len() - 1));
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                if shown {
+                    self.columns.retain(|c| *c != column);
+                } else {
+                    self.columns.push(column);
+                }
+                // keep the cursor on the same column
+                let entries = self.menu_columns();
+                self.column_menu = entries.iter().position(|(c, _)| *c == column);
+            }
+            // moving shown columns around
+            KeyCode::Char('K') if shown && cursor > 0 => {
+                self.columns.swap(cursor, cursor - 1);
+                self.column_menu = Some(cursor - 1);
+            }
+            KeyCode::Char('J') if shown && cursor + 1 < self.columns.len() => {
+                self.columns.swap(cursor, cursor + 1);
+                self.column_menu = Some(cursor + 1);
+            }
+            KeyCode::Esc | KeyCode::Char('c' | 'q') => self.column_menu = None,
+            _ => {}
+        }
+    }
+
+    pub(super) fn render_column_menu(&self, frame: &mut Frame, cursor: usize) {
+        let theme = &self.theme;
+        let items: Vec<_> = self
+            .menu_columns()
+            .into_iter()
+            .map(|(column, shown)| {
+                let check = if shown { "[x] " } else { "[ ] " };
+                line![check.fg(theme.accent), column.title()]
+            })
+            .collect();
+
+        let height = items.len() as u16 + 4;
+        let block = Block::bordered()
+            .title_top(line!["Columns".fg(theme.title)])
+            .title_bottom(line![
+                "space".fg(theme.key),
+                " toggle ".fg(theme.hint),
+                "J/K".fg(theme.key),
+                " move".fg(theme.hint),
+            ])
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::uniform(1));
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::new().bg(theme.highlight));
+        let mut state = ListState::default().with_selected(Some(cursor));
+
+        let area = frame
+            .area()
+            .centered(constraint!(==30), constraint!(==height));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+}
@@ -0,0 +1,44 @@
+use super::App;
+use crate::stats::show_elapsed;
+
+use ratatui::{
+    Frame,
+    macros::{line, vertical},
+    style::Stylize,
+    widgets::Paragraph,
+};
+
+/// Below this the panes get cut into garbage, so there's just a list of the builds instead.
+const MIN_WIDTH: u16 = 50;
+const MIN_HEIGHT: u16 = 12;
+
+impl App {
+    pub(super) fn too_small(&self) -> bool {
+        self.area.width < MIN_WIDTH || self.area.height < MIN_HEIGHT
+    }
+
+    /// How long each build has been going, and what size the terminal needs to be for the rest.
+    pub(super) fn render_compact(&self, frame: &mut Frame) {
+        let theme = &self.theme;
+        let [builds, bottom] = frame.area().layout(&vertical![*=1, ==1]);
+
+        let mut lines = vec![line![
+            format!("{} active builds", self.active_builds.len())
+                .fg(theme.title)
+                .bold()
+        ]];
+        lines.extend(self.active_builds.iter().map(|build| {
+            line![
+                format!("{:>7} ", show_elapsed(build.elapsed(self.now))).fg(theme.accent),
+                build.drv_name().pname.fg(theme.pname),
+            ]
+        }));
+        frame.render_widget(Paragraph::new(lines), builds);
+
+        let message = format!(
+            "{}x{}, needs {MIN_WIDTH}x{MIN_HEIGHT} for more",
+            self.area.width, self.area.height
+        );
+        frame.render_widget(Paragraph::new(line![message.dim()]), bottom);
+    }
+}
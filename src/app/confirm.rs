@@ -0,0 +1,83 @@
+use crate::{signal::Signal, stats::show_bytes, theme::Theme};
+
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    macros::{constraint, line, text},
+    style::Stylize,
+    text::Text,
+    widgets::{Block, Clear, Padding, Paragraph, Wrap},
+};
+
+/// Something dangerous waiting for the user to confirm it.
+#[derive(Debug, Clone)]
+pub enum Confirm {
+    Signal {
+        signal: Signal,
+        pid: usize,
+        /// What is being signaled, shown to the user.
+        target: String,
+    },
+    /// Running `nix store gc`, freeing at most this many bytes.
+    Gc { max: u64 },
+}
+
+impl Confirm {
+    fn text(&self, theme: &Theme) -> Text<'_> {
+        match self {
+            Confirm::Signal {
+                signal,
+                pid,
+                target,
+            } => text![
+                line![
+                    "Send ",
+                    signal.name().fg(theme.danger).bold(),
+                    " to PID ",
+                    pid.to_string().fg(theme.accent),
+                    "?"
+                ],
+                line![target.as_str().fg(theme.pname)],
+            ],
+            Confirm::Gc { max } => text![
+                line!["Run ", "garbage collection".fg(theme.danger).bold(), "?"],
+                line![
+                    "Frees up to ",
+                    show_bytes(Some(*max)).fg(theme.accent),
+                    ", builds may have to wait"
+                ],
+            ],
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, theme: &Theme) {
+        let verb = match self {
+            Confirm::Signal { .. } => " send  ",
+            Confirm::Gc { .. } => " run  ",
+        };
+        let block = Block::bordered()
+            .title_top(line!["Confirm".fg(theme.title)])
+            .title_bottom(
+                line![
+                    "y".fg(theme.key),
+                    verb.fg(theme.hint),
+                    "n".fg(theme.key),
+                    " cancel".fg(theme.hint)
+                ]
+                .alignment(Alignment::Right),
+            )
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::uniform(1));
+
+        let paragraph = Paragraph::new(self.text(theme))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .block(block);
+
+        let area = frame.area().centered(constraint!(==60), constraint!(==7));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
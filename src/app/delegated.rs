@@ -0,0 +1,43 @@
+use super::App;
+use crate::ps;
+
+impl App {
+    /// Builds the local Nix handed to remote builders, which no `nix ps` shows. `found` are the
+    /// ones from `/proc` when watching this machine, the client's activity log adds to them and
+    /// also knows for sure which machine each one went to.
+    pub(super) fn delegated_builds(
+        &self,
+        builds: &[ps::Build],
+        found: Vec<ps::Build>,
+    ) -> Vec<ps::Build> {
+        let mut delegated = found;
+
+        for (id, activity) in self.queue.delegated() {
+            if let Some(build) = delegated
+                .iter_mut()
+                .find(|b| b.derivation == activity.target)
+            {
+                // `/proc` has to guess when one daemon worker delegates several at once
+                build.host = activity.machine.clone();
+                continue;
+            }
+            if builds.iter().any(|b| b.derivation == activity.target) {
+                continue;
+            }
+
+            delegated.push(ps::Build {
+                derivation: activity.target.clone(),
+                // there are no processes to go by, so the activity ID stands in for the Nix PID
+                main_pid: 0,
+                nix_pid: id as usize,
+                processes: vec![],
+                start_time: Some(activity.started.timestamp_millis() as f64 / 1000.0),
+                host: activity.machine.clone(),
+                cgroup: None,
+                net: None,
+                delegated: true,
+            });
+        }
+        delegated
+    }
+}
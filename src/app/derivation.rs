@@ -0,0 +1,117 @@
+use super::{App, AppEvent, Event, keys::Action};
+use crate::drv;
+
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    macros::{constraint, line},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Clear, Padding, Paragraph, Wrap},
+};
+
+/// The full-screen view of the selected build's derivation.
+#[derive(Debug)]
+pub struct DerivationView {
+    pub derivation: String,
+    /// `None` while `nix derivation show` runs.
+    pub lines: Option<Result<Vec<String>, String>>,
+    pub scroll: usize,
+}
+
+impl App {
+    /// Opens the derivation of the selected build and starts looking it up.
+    pub(super) fn view_derivation(&mut self) {
+        let Some(build) = self.selected_build() else {
+            return;
+        };
+        let derivation = build.derivation.clone();
+        let Some(command) = self.nix_command(build) else {
+            self.show_notice("Can't look at derivations of replayed builds".to_string());
+            return;
+        };
+
+        let sender = self.sender.clone();
+        let lookup = derivation.clone();
+        tokio::spawn(async move {
+            let lines = drv::show_all(command, &lookup).await;
+            _ = sender.send(Event::App(AppEvent::DerivationLines(lookup, lines)));
+        });
+
+        self.derivation_view = Some(DerivationView {
+            derivation,
+            lines: None,
+            scroll: 0,
+        });
+    }
+
+    /// Scrolls or closes the derivation view. Everything else is ignored while it's open.
+    pub(super) fn handle_derivation_view(&mut self, action: Action) {
+        let height = self.area.height.saturating_sub(6) as usize;
+        let Some(view) = &mut self.derivation_view else {
+            return;
+        };
+        let max = match &view.lines {
+            Some(Ok(lines)) => lines.len().saturating_sub(1),
+            _ => 0,
+        };
+
+        let scroll = |delta: isize| view.scroll.saturating_add_signed(delta).min(max);
+        view.scroll = match action {
+            Action::Up => scroll(-1),
+            Action::Down => scroll(1),
+            Action::PageUp => scroll(-(height as isize)),
+            Action::PageDown => scroll(height as isize),
+            Action::HalfPageUp => scroll(-(height as isize) / 2),
+            Action::HalfPageDown => scroll(height as isize / 2),
+            Action::Top => 0,
+            Action::Bottom => max,
+            Action::ClearSelection | Action::ViewDerivation => {
+                self.derivation_view = None;
+                return;
+            }
+            _ => view.scroll,
+        };
+    }
+
+    pub(super) fn render_derivation_view(&self, frame: &mut Frame, view: &DerivationView) {
+        let theme = &self.theme;
+        let lines: Vec<Line> = match &view.lines {
+            None => vec![line!["Loading...".dim()]],
+            Some(Err(err)) => vec![line![err.as_str().fg(theme.danger)]],
+            Some(Ok(lines)) => lines
+                .iter()
+                .skip(view.scroll)
+                .map(|line| {
+                    // section titles aren't indented
+                    if line.starts_with(' ') {
+                        line![line.as_str()]
+                    } else {
+                        line![line.as_str().fg(theme.title).bold()]
+                    }
+                })
+                .collect(),
+        };
+
+        let block = Block::bordered()
+            .title_top(line![view.derivation.as_str().fg(theme.derivation)])
+            .title_bottom(
+                line!["Esc".fg(theme.key), " close".fg(theme.hint)].alignment(Alignment::Right),
+            )
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::uniform(1));
+
+        let area = frame
+            .area()
+            .centered(constraint!(==90%), constraint!(==90%));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use super::{App, AppEvent, Event};
+use crate::{
+    disk::{self, Filesystem},
+    stats::{show_bytes, show_rate, sparkline},
+    store,
+};
+use std::{path::Path, time::Duration};
+use tokio::time;
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    macros::line,
+    style::Stylize,
+    widgets::{Block, Padding, Paragraph},
+};
+
+/// statvfs is cheap, adding up the store isn't.
+const FILESYSTEM_INTERVAL: Duration = Duration::from_secs(10);
+const STORE_SIZE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+impl App {
+    /// Starts the tasks that keep the disk usage up to date, unless they're running already.
+    pub(super) fn watch_disk(&mut self) {
+        if self.watching_disk {
+            return;
+        }
+        self.watching_disk = true;
+
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(FILESYSTEM_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Ok(filesystem) = Filesystem::of(store::store_dir()) {
+                    let event = Event::App(AppEvent::Filesystem(filesystem));
+                    if sender.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(STORE_SIZE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Ok(size) =
+                    tokio::task::spawn_blocking(|| disk::size(Path::new(store::store_dir()))).await
+                else {
+                    break;
+                };
+                if sender.send(Event::App(AppEvent::StoreSize(size))).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    pub(super) fn render_disk(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let disk = &self.disk;
+
+        let store = match disk.store {
+            Some(size) => show_bytes(Some(size)),
+            None => "adding up...".to_string(),
+        };
+        let mut line = line!["Store ".dim(), store.fg(theme.accent)];
+
+        if let Some(filesystem) = disk.filesystem {
+            let percent = filesystem.available as f64 / filesystem.total.max(1) as f64 * 100.0;
+            let free = format!("{} ({percent:.0}%)", show_bytes(Some(filesystem.available)));
+            let free = if percent < 10.0 {
+                free.fg(theme.danger).bold()
+            } else {
+                free.fg(theme.accent)
+            };
+            line.push_span("  Free ".dim());
+            line.push_span(free);
+            line.push_span(format!(" of {}", show_bytes(Some(filesystem.total))).dim());
+        }
+
+        if let Some(growth) = disk.growth() {
+            let sign = if growth < 0.0 { "-" } else { "+" };
+            let bytes = show_bytes(Some(growth.abs() as u64));
+            line.push_span("  Growth ".dim());
+            line.push_span(format!("{sign}{bytes}/h").fg(theme.accent));
+        }
+
+        let block = Block::bordered()
+            .title_top(line!["Nix store".fg(theme.title)])
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+
+        frame.render_widget(Paragraph::new(line).block(block), rect);
+    }
+
+    /// Reads, writes and how busy the store's disk is, with a graph of the last minute.
+    pub(super) fn render_disk_io(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let Some(io) = &self.host.disk_io else {
+            return;
+        };
+
+        let busy = format!("{:.0}%", io.busy);
+        let busy = if io.busy >= 90.0 {
+            busy.fg(theme.danger).bold()
+        } else {
+            busy.fg(theme.accent)
+        };
+        let mut line = line![
+            "Read ".dim(),
+            show_rate(Some(io.read)).fg(theme.accent),
+            "  Write ".dim(),
+            show_rate(Some(io.write)).fg(theme.accent),
+            "  Busy ".dim(),
+            busy,
+            " ",
+        ];
+        let width = (rect.width as usize).saturating_sub(4 + line.width());
+        line.push_span(sparkline(&self.host.disk_history(), width).fg(theme.accent));
+
+        let device = match &io.device {
+            Some(device) => format!(" {device}"),
+            None => " all disks".to_string(),
+        };
+        let block = Block::bordered()
+            .title_top(line!["Disk I/O".fg(theme.title), device.dim()])
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+
+        frame.render_widget(Paragraph::new(line).block(block), rect);
+    }
+}
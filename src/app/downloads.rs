@@ -0,0 +1,136 @@
+use super::App;
+use crate::{
+    stats::{progress_bar, show_bytes},
+    store,
+};
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    macros::{constraints, line, row, text},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Padding, Row, Table},
+};
+
+/// Most downloads shown at once, so that they don't push the builds away.
+pub const MAX_SHOWN: usize = 5;
+
+/// Width of the progress bars.
+const BAR_WIDTH: usize = 12;
+
+/// A download seen in `/proc`, in the client's activity log, or both.
+#[derive(Debug)]
+pub struct Download {
+    pub pid: Option<usize>,
+    /// Store path without `/nix/store/`, or a URL.
+    pub name: String,
+    /// Bytes written to the store so far.
+    pub written: Option<u64>,
+    /// Done and expected bytes, from the activity log.
+    pub progress: Option<(u64, u64)>,
+}
+
+impl App {
+    /// Downloads on this machine, with progress from the activity log when it knows about them,
+    /// then the rest of the ones the activity log knows about.
+    pub(super) fn downloads(&self) -> Vec<Download> {
+        let transfers: Vec<_> = self.queue.transfers().collect();
+        let progress = |name: &str| {
+            transfers
+                .iter()
+                .find(|t| t.target == name && t.expected > 0)
+                .map(|t| (t.done, t.expected))
+        };
+
+        let mut downloads: Vec<_> = self
+            .substitutions
+            .iter()
+            .map(|substitution| {
+                let name = store::strip_store_dir(&substitution.path)
+                    .unwrap_or_default()
+                    .to_string();
+                Download {
+                    pid: Some(substitution.pid),
+                    progress: progress(&name),
+                    name,
+                    written: Some(substitution.bytes),
+                }
+            })
+            .collect();
+
+        for transfer in transfers {
+            if !downloads.iter().any(|d| d.name == transfer.target) {
+                downloads.push(Download {
+                    pid: None,
+                    name: transfer.target.clone(),
+                    written: None,
+                    progress: (transfer.expected > 0).then_some((transfer.done, transfer.expected)),
+                });
+            }
+        }
+        downloads
+    }
+
+    pub(super) fn render_downloads(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let downloads = self.downloads();
+        let rows: Vec<_> = downloads
+            .iter()
+            .take(MAX_SHOWN)
+            .map(|download| {
+                // drop the hash, URLs don't have one
+                let name = if download.name.contains("://") {
+                    download.name.as_str()
+                } else {
+                    download.name.get(33..).unwrap_or(&download.name)
+                };
+                let progress = match download.progress {
+                    Some((done, expected)) => line![
+                        progress_bar(done, expected, BAR_WIDTH).fg(theme.accent),
+                        format!(" {}/{}", show_bytes(Some(done)), show_bytes(Some(expected))).dim(),
+                    ],
+                    None => Line::default(),
+                };
+                row![
+                    text!(download.pid.map_or("-".to_string(), |p| p.to_string()))
+                        .alignment(Alignment::Right),
+                    name.fg(theme.pname),
+                    progress,
+                    text!(show_bytes(download.written)).alignment(Alignment::Right),
+                ]
+            })
+            .collect();
+
+        let mut title = line!["Downloads".fg(theme.title)];
+        if let Some(hidden) = downloads.len().checked_sub(MAX_SHOWN)
+            && hidden > 0
+        {
+            title.push_span(format!(" ({hidden} more)").dim());
+        }
+
+        let block = Block::bordered()
+            .title_top(title)
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+
+        let header = Row::new(vec![
+            text!("PID").alignment(Alignment::Right),
+            text!("Path"),
+            text!("Progress"),
+            text!("Written").alignment(Alignment::Right),
+        ])
+        .dim()
+        .underlined();
+
+        let table = Table::new(
+            rows,
+            constraints![==9, ==100%, ==(BAR_WIDTH as u16 + 12), ==8],
+        )
+        .header(header)
+        .block(block);
+
+        frame.render_widget(table, rect);
+    }
+}
@@ -0,0 +1,177 @@
+use super::{App, keys::Action};
+use crate::proc;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    macros::{constraint, constraints, line, row},
+    style::Stylize,
+    widgets::{Block, Clear, Padding, Paragraph, Row, Table},
+};
+
+/// The environment variables of a process of the selected build.
+#[derive(Debug)]
+pub struct EnvironView {
+    pub pid: usize,
+    pub vars: Result<Vec<(String, String)>, String>,
+    pub filter: String,
+    pub editing_filter: bool,
+    pub scroll: usize,
+}
+
+impl EnvironView {
+    /// Variables whose name or value contains the filter, ignoring case.
+    fn visible(&self) -> Vec<&(String, String)> {
+        let Ok(vars) = &self.vars else {
+            return vec![];
+        };
+        let filter = self.filter.to_lowercase();
+        vars.iter()
+            .filter(|(name, value)| {
+                name.to_lowercase().contains(&filter) || value.to_lowercase().contains(&filter)
+            })
+            .collect()
+    }
+}
+
+impl App {
+    /// Opens the environment of the process under the cursor in the tree, or the main process of
+    /// the selected build.
+    pub(super) fn view_environ(&mut self) {
+        let Some(build) = self.selected_build() else {
+            return;
+        };
+        if build.host.is_some() {
+            self.show_notice("Can't read environments of builds on other machines".to_string());
+            return;
+        }
+
+        let pid = self
+            .tree
+            .cursor
+            .filter(|pid| build.process(*pid).is_some())
+            .unwrap_or(build.main_pid);
+        self.environ_view = Some(EnvironView {
+            pid,
+            vars: proc::environ(pid).map_err(|err| err.to_string()),
+            filter: String::new(),
+            editing_filter: false,
+            scroll: 0,
+        });
+    }
+
+    /// Typing into the filter box of the environment view.
+    pub(super) fn handle_environ_filter(&mut self, key_event: KeyEvent) {
+        let Some(view) = &mut self.environ_view else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c) => view.filter.push(c),
+            KeyCode::Backspace => _ = view.filter.pop(),
+            KeyCode::Enter => view.editing_filter = false,
+            KeyCode::Esc => {
+                view.editing_filter = false;
+                view.filter.clear();
+            }
+            _ => {}
+        }
+        view.scroll = 0;
+    }
+
+    /// Scrolls, filters or closes the environment view.
+    pub(super) fn handle_environ_view(&mut self, action: Action) {
+        let height = self.area.height.saturating_sub(7) as usize;
+        let Some(view) = &mut self.environ_view else {
+            return;
+        };
+        let max = view.visible().len().saturating_sub(1);
+
+        let scroll = |delta: isize| view.scroll.saturating_add_signed(delta).min(max);
+        view.scroll = match action {
+            Action::Up => scroll(-1),
+            Action::Down => scroll(1),
+            Action::PageUp => scroll(-(height as isize)),
+            Action::PageDown => scroll(height as isize),
+            Action::HalfPageUp => scroll(-(height as isize) / 2),
+            Action::HalfPageDown => scroll(height as isize / 2),
+            Action::Top => 0,
+            Action::Bottom => max,
+            Action::Filter => {
+                view.editing_filter = true;
+                view.scroll
+            }
+            Action::ClearSelection | Action::ViewEnviron => {
+                self.environ_view = None;
+                return;
+            }
+            _ => view.scroll,
+        };
+    }
+
+    pub(super) fn render_environ_view(&self, frame: &mut Frame, view: &EnvironView) {
+        let theme = &self.theme;
+        let visible = view.visible();
+
+        let mut title = line![
+            "Environment".fg(theme.title),
+            format!(" of PID {}", view.pid).dim(),
+        ];
+        if view.editing_filter || !view.filter.is_empty() {
+            title.push_span(" filter: ".dim());
+            title.push_span(view.filter.as_str().fg(theme.accent));
+            if view.editing_filter {
+                title.push_span("_".fg(theme.accent).slow_blink());
+            }
+        }
+        if let Ok(vars) = &view.vars
+            && visible.len() < vars.len()
+        {
+            title.push_span(format!(" ({} hidden)", vars.len() - visible.len()).dim());
+        }
+
+        let block = Block::bordered()
+            .title_top(title)
+            .title_bottom(
+                line![
+                    "f".fg(theme.key),
+                    " filter  ".fg(theme.hint),
+                    "Esc".fg(theme.key),
+                    " close".fg(theme.hint),
+                ]
+                .alignment(Alignment::Right),
+            )
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::uniform(1));
+
+        let area = frame
+            .area()
+            .centered(constraint!(==90%), constraint!(==90%));
+        frame.render_widget(Clear, area);
+
+        if let Err(err) = &view.vars {
+            let message =
+                line![format!("Can't read /proc/{}/environ: {err}", view.pid).fg(theme.danger)];
+            frame.render_widget(Paragraph::new(message).block(block), area);
+            return;
+        }
+
+        let name_width = visible
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .min(40) as u16;
+        let rows: Vec<_> = visible
+            .into_iter()
+            .skip(view.scroll)
+            .map(|(name, value)| row![name.as_str().fg(theme.accent), value.as_str()])
+            .collect();
+
+        let table = Table::new(rows, constraints![==name_width, *=1])
+            .header(Row::new(["Name", "Value"]).dim().underlined())
+            .block(block);
+        frame.render_widget(table, area);
+    }
+}
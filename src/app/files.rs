@@ -0,0 +1,133 @@
+use super::{App, keys::Action};
+use crate::{
+    files::{self, OpenFile},
+    store,
+};
+
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    macros::{constraint, constraints, line, row},
+    style::Stylize,
+    widgets::{Block, Clear, Padding, Paragraph, Row, Table},
+};
+
+/// The open files and sockets of a process of the selected build.
+#[derive(Debug)]
+pub struct FilesView {
+    pub pid: usize,
+    pub files: Result<Vec<OpenFile>, String>,
+    pub scroll: usize,
+}
+
+impl App {
+    /// Opens the files of the process under the cursor in the tree, or the main process of the
+    /// selected build.
+    pub(super) fn view_files(&mut self) {
+        let Some(build) = self.selected_build() else {
+            return;
+        };
+        if build.host.is_some() {
+            self.show_notice("Can't look at files of builds on other machines".to_string());
+            return;
+        }
+
+        let pid = self
+            .tree
+            .cursor
+            .filter(|pid| build.process(*pid).is_some())
+            .unwrap_or(build.main_pid);
+        self.files_view = Some(FilesView {
+            pid,
+            files: files::open_files(pid).map_err(|err| err.to_string()),
+            scroll: 0,
+        });
+    }
+
+    /// Scrolls, reloads or closes the files view.
+    pub(super) fn handle_files_view(&mut self, action: Action) {
+        let height = self.area.height.saturating_sub(7) as usize;
+        let Some(view) = &mut self.files_view else {
+            return;
+        };
+        let max = view.files.as_ref().map_or(0, |f| f.len().saturating_sub(1));
+
+        let scroll = |delta: isize| view.scroll.saturating_add_signed(delta).min(max);
+        view.scroll = match action {
+            Action::Up => scroll(-1),
+            Action::Down => scroll(1),
+            Action::PageUp => scroll(-(height as isize)),
+            Action::PageDown => scroll(height as isize),
+            Action::HalfPageUp => scroll(-(height as isize) / 2),
+            Action::HalfPageDown => scroll(height as isize / 2),
+            Action::Top => 0,
+            Action::Bottom => max,
+            Action::Refresh => {
+                view.files = files::open_files(view.pid).map_err(|err| err.to_string());
+                view.scroll
+            }
+            Action::ClearSelection | Action::ViewFiles => {
+                self.files_view = None;
+                return;
+            }
+            _ => view.scroll,
+        };
+    }
+
+    pub(super) fn render_files_view(&self, frame: &mut Frame, view: &FilesView) {
+        let theme = &self.theme;
+        let block = Block::bordered()
+            .title_top(line![
+                "Open files".fg(theme.title),
+                format!(" of PID {}", view.pid).dim(),
+            ])
+            .title_bottom(
+                line![
+                    "r".fg(theme.key),
+                    " reload  ".fg(theme.hint),
+                    "Esc".fg(theme.key),
+                    " close".fg(theme.hint),
+                ]
+                .alignment(Alignment::Right),
+            )
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::uniform(1));
+
+        let area = frame
+            .area()
+            .centered(constraint!(==90%), constraint!(==90%));
+        frame.render_widget(Clear, area);
+
+        let files = match &view.files {
+            Ok(files) => files,
+            Err(err) => {
+                let message = format!("Can't read /proc/{}/fd: {err}", view.pid);
+                let paragraph = Paragraph::new(line![message.fg(theme.danger)]).block(block);
+                frame.render_widget(paragraph, area);
+                return;
+            }
+        };
+
+        let rows: Vec<_> = files
+            .iter()
+            .skip(view.scroll)
+            .map(|file| {
+                let target = if store::strip_store_dir(&file.target).is_some() {
+                    file.target.as_str().fg(theme.derivation)
+                } else if file.target.starts_with('/') {
+                    file.target.as_str().into()
+                } else {
+                    // sockets, pipes and such
+                    file.target.as_str().fg(theme.accent)
+                };
+                row![file.fd.as_str().dim(), target]
+            })
+            .collect();
+
+        let table = Table::new(rows, constraints![==5, *=1])
+            .header(Row::new(["FD", "Target"]).dim().underlined())
+            .block(block);
+        frame.render_widget(table, area);
+    }
+}
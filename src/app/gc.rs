@@ -0,0 +1,121 @@
+use super::{App, AppEvent, Event};
+use std::process::Stdio;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::mpsc,
+    task::JoinHandle,
+};
+
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    macros::{constraint, line},
+    style::Stylize,
+    widgets::{Block, Clear, Padding, Paragraph},
+};
+
+/// A `nix store gc` started from the UI.
+#[derive(Debug)]
+pub struct GcRun {
+    pub lines: Vec<String>,
+    /// How it ended, `None` while it's running.
+    pub status: Option<String>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for GcRun {
+    fn drop(&mut self) {
+        // kills nix since it's kill_on_drop. it's fine to stop a gc midway
+        self.task.abort();
+    }
+}
+
+impl App {
+    pub(super) fn start_gc(&mut self, max: u64) {
+        self.events.info("Started garbage collection");
+        let mut nix = self.local_nix();
+        let sender = self.sender.clone();
+        let task = tokio::spawn(async move {
+            let child = nix
+                .arg("store")
+                .arg("gc")
+                .arg("--max")
+                .arg(max.to_string())
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn();
+
+            let mut child = match child {
+                Ok(child) => child,
+                Err(err) => {
+                    _ = sender.send(Event::App(AppEvent::GcDone(format!("failed: {err}"))));
+                    return;
+                }
+            };
+
+            let stdout = child.stdout.take().expect("stdout is piped");
+            let stderr = child.stderr.take().expect("stderr is piped");
+            tokio::join!(forward(stdout, &sender), forward(stderr, &sender));
+
+            let status = match child.wait().await {
+                Ok(status) if status.success() => "done".to_string(),
+                Ok(status) => format!("failed: {status}"),
+                Err(err) => format!("failed: {err}"),
+            };
+            _ = sender.send(Event::App(AppEvent::GcDone(status)));
+        });
+
+        self.gc = Some(GcRun {
+            lines: Vec::new(),
+            status: None,
+            task,
+        });
+    }
+
+    pub(super) fn render_gc(&self, frame: &mut Frame, gc: &GcRun) {
+        let theme = &self.theme;
+        let status = match &gc.status {
+            Some(status) => status.as_str().fg(theme.accent),
+            None => "running".fg(theme.danger).slow_blink(),
+        };
+        let close = if gc.status.is_some() {
+            " close"
+        } else {
+            " stop"
+        };
+
+        let block = Block::bordered()
+            .title_top(line!["Garbage collection ".fg(theme.title), status,])
+            .title_bottom(
+                line!["Esc".fg(theme.key), close.fg(theme.hint)].alignment(Alignment::Right),
+            )
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+
+        let area = frame
+            .area()
+            .centered(constraint!(==80%), constraint!(==60%));
+
+        // only the tail fits
+        let height = block.inner(area).height as usize;
+        let lines: Vec<_> = gc.lines[gc.lines.len().saturating_sub(height)..]
+            .iter()
+            .map(|line| line![line.as_str()])
+            .collect();
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+async fn forward(stream: impl AsyncRead + Unpin, sender: &mpsc::UnboundedSender<Event>) {
+    let mut lines = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if sender.send(Event::App(AppEvent::GcOutput(line))).is_err() {
+            break;
+        }
+    }
+}
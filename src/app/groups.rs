@@ -0,0 +1,202 @@
+//! Grouped view of the builds table, where builds of the same package go under one row.
+
+use super::App;
+use crate::{
+    columns::Column,
+    glyphs, ps,
+    stats::{show_bytes, show_cpu, show_duration, show_elapsed, show_rate},
+};
+use ratatui::{
+    layout::Alignment,
+    macros::line,
+    style::Stylize,
+    text::Line,
+    widgets::{Cell, Row},
+};
+use std::collections::HashSet;
+
+/// A row of the builds table.
+#[derive(Debug)]
+pub enum TableRow<'a> {
+    /// A build, `nested` when it's under a group.
+    Build { build: &'a ps::Build, nested: bool },
+    /// Builds with the same package name, collapsed into one row.
+    Group {
+        pname: &'a str,
+        builds: Vec<&'a ps::Build>,
+        expanded: bool,
+    },
+}
+
+/// What a row is about, to find it again after the rows change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowKey {
+    Build(Option<String>, usize),
+    Group(String),
+}
+
+impl TableRow<'_> {
+    pub fn key(&self) -> RowKey {
+        match self {
+            TableRow::Build { build, .. } => RowKey::Build(build.host.clone(), build.nix_pid),
+            TableRow::Group { pname, .. } => RowKey::Group(pname.to_string()),
+        }
+    }
+}
+
+/// Puts builds with the same package name together, in the order their first build is in.
+/// Packages with just one build aren't worth a group.
+pub fn group<'a>(builds: &[&'a ps::Build], expanded: &HashSet<String>) -> Vec<TableRow<'a>> {
+    let mut groups: Vec<(&str, Vec<&ps::Build>)> = vec![];
+    for build in builds {
+        let pname = build.name().0;
+        match groups.iter_mut().find(|(p, _)| *p == pname) {
+            Some((_, group)) => group.push(build),
+            None => groups.push((pname, vec![build])),
+        }
+    }
+
+    let mut rows = vec![];
+    for (pname, builds) in groups {
+        if let [build] = builds[..] {
+            rows.push(TableRow::Build {
+                build,
+                nested: false,
+            });
+            continue;
+        }
+
+        let is_expanded = expanded.contains(pname);
+        rows.push(TableRow::Group {
+            pname,
+            builds: builds.clone(),
+            expanded: is_expanded,
+        });
+        if is_expanded {
+            rows.extend(builds.into_iter().map(|build| TableRow::Build {
+                build,
+                nested: true,
+            }));
+        }
+    }
+    rows
+}
+
+impl App {
+    /// Rows of the builds table, grouped or not.
+    pub(super) fn table_rows(&self) -> Vec<TableRow<'_>> {
+        let builds = self.visible_builds();
+        if self.grouped {
+            group(&builds, &self.expanded_groups)
+        } else {
+            builds
+                .into_iter()
+                .map(|build| TableRow::Build {
+                    build,
+                    nested: false,
+                })
+                .collect()
+        }
+    }
+
+    /// Expands or collapses the group of the selected row. Collapsing from a build in the group
+    /// selects the group again.
+    pub(super) fn expand_group(&mut self, expand: bool) {
+        let rows = self.table_rows();
+        let Some(row) = self.table_state.selected().and_then(|i| rows.get(i)) else {
+            return;
+        };
+
+        let pname = match row {
+            TableRow::Group { pname, .. } => pname.to_string(),
+            TableRow::Build {
+                build,
+                nested: true,
+            } if !expand => build.name().0.to_string(),
+            TableRow::Build { .. } => return,
+        };
+
+        if expand {
+            self.expanded_groups.insert(pname);
+        } else {
+            let index = rows
+                .iter()
+                .position(|row| row.key() == RowKey::Group(pname.clone()));
+            self.expanded_groups.remove(&pname);
+            self.table_state.select(index);
+        }
+    }
+
+    pub(super) fn group_row<'a>(
+        &self,
+        pname: &'a str,
+        builds: &[&ps::Build],
+        expanded: bool,
+    ) -> Row<'a> {
+        let theme = &self.theme;
+        let cpu = builds
+            .iter()
+            .filter_map(|b| self.cpu.build(b))
+            .reduce(|a, b| a + b);
+        let rss = builds.iter().filter_map(|b| b.rss()).reduce(|a, b| a + b);
+        let io = builds
+            .iter()
+            .filter_map(|b| self.io.build(b))
+            .reduce(|a, b| (a.0 + b.0, a.1 + b.1));
+        let net = builds
+            .iter()
+            .filter_map(|b| self.net.build(b))
+            .reduce(|a, b| (a.0 + b.0, a.1 + b.1));
+
+        let cells = self.visible_columns().into_iter().map(|column| {
+            let line = match column {
+                Column::Package => {
+                    let arrow = if expanded {
+                        glyphs::pick("▾ ", "v ")
+                    } else {
+                        glyphs::pick("▸ ", "> ")
+                    };
+                    line![arrow.fg(theme.accent), pname.fg(theme.pname)]
+                }
+                Column::Version => line![format!("{} builds", builds.len()).dim()],
+                Column::Cpu => line![show_cpu(cpu)],
+                Column::CpuTime => line![show_duration(builds.iter().map(|b| b.cpu_time()).sum())],
+                Column::Processes => {
+                    line![
+                        builds
+                            .iter()
+                            .map(|b| b.processes.len())
+                            .sum::<usize>()
+                            .to_string()
+                    ]
+                }
+                Column::Memory => line![show_bytes(rss)],
+                Column::Read => line![show_rate(io.map(|(read, _)| read))],
+                Column::Write => line![show_rate(io.map(|(_, write)| write))],
+                Column::Download => line![show_rate(net.map(|(down, _)| down))],
+                Column::Upload => line![show_rate(net.map(|(_, up)| up))],
+                // the one that has been going for longest
+                Column::Time => {
+                    let started = builds.iter().filter_map(|b| b.started()).min();
+                    line![show_elapsed(started.map(|started| self.now - started))]
+                }
+                Column::Pid
+                | Column::Host
+                | Column::User
+                | Column::BuildUser
+                | Column::Client
+                | Column::Phase
+                | Column::CpuGraph
+                | Column::Eta => Line::default(),
+            };
+
+            if column.right_aligned() {
+                Cell::from(line.alignment(Alignment::Right))
+            } else {
+                Cell::from(line)
+            }
+        });
+
+        Row::new(cells).bold()
+    }
+}
@@ -0,0 +1,116 @@
+use super::{
+    App,
+    keys::{self, Action, BINDINGS},
+};
+use crate::theme::Theme;
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    macros::{constraint, constraints, line, row, text},
+    style::Stylize,
+    widgets::{Block, Clear, Padding, Table},
+};
+
+/// Borders and padding around the table, on each axis.
+const CHROME: u16 = 4;
+const COLUMN_SPACING: u16 = 2;
+
+fn key_labels() -> Vec<String> {
+    BINDINGS
+        .iter()
+        .map(|binding| {
+            let keys: Vec<String> = binding.keys.iter().map(|k| k.label()).collect();
+            keys.join(" ")
+        })
+        .collect()
+}
+
+/// Rows of bindings that fit on a screen this big.
+fn visible_rows(area: Rect) -> usize {
+    (area.height.saturating_sub(CHROME) as usize).min(BINDINGS.len())
+}
+
+impl App {
+    /// Scrolls the help when it doesn't fit, or closes it.
+    pub(super) fn handle_help(&mut self, action: Action) {
+        let height = visible_rows(self.area);
+        let max = BINDINGS.len() - height;
+        let scroll = |delta: isize| self.help_scroll.saturating_add_signed(delta).min(max);
+        self.help_scroll = match action {
+            Action::Up => scroll(-1),
+            Action::Down => scroll(1),
+            Action::PageUp => scroll(-(height as isize)),
+            Action::PageDown => scroll(height as isize),
+            Action::HalfPageUp => scroll(-(height as isize) / 2),
+            Action::HalfPageDown => scroll(height as isize / 2),
+            Action::Top => 0,
+            Action::Bottom => max,
+            Action::ClearSelection => {
+                self.show_help = false;
+                return;
+            }
+            _ => self.help_scroll,
+        };
+    }
+}
+
+/// Renders the keybinding help popup on top of everything else, scrolled down by `scroll` rows
+/// when it's taller than the screen.
+pub fn render(frame: &mut Frame, theme: &Theme, scroll: usize) {
+    let area = frame.area();
+    let labels = key_labels();
+    let visible = visible_rows(area);
+    let scroll = scroll.min(BINDINGS.len() - visible);
+
+    let keys_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u16;
+    let description_width = BINDINGS
+        .iter()
+        .map(|b| b.description.chars().count())
+        .max()
+        .unwrap_or(0) as u16;
+    let width = (keys_width + COLUMN_SPACING + description_width + CHROME).min(area.width);
+    let height = visible as u16 + CHROME;
+
+    let rows: Vec<_> = BINDINGS
+        .iter()
+        .zip(labels)
+        .skip(scroll)
+        .map(|(binding, keys)| {
+            row![
+                text!(keys).alignment(Alignment::Right).fg(theme.key),
+                binding.description,
+            ]
+        })
+        .collect();
+
+    let mut hint = line![];
+    if visible < BINDINGS.len() {
+        let up = keys::label(Action::Up).unwrap_or_default();
+        let down = keys::label(Action::Down).unwrap_or_default();
+        hint.push_span(format!("{up}/{down}").fg(theme.key));
+        hint.push_span(format!(" scroll {}/{}  ", scroll + visible, BINDINGS.len()).fg(theme.hint));
+    }
+    hint.push_span(
+        keys::label(Action::ToggleHelp)
+            .unwrap_or_default()
+            .fg(theme.key),
+    );
+    hint.push_span(" close".fg(theme.hint));
+
+    let block = Block::bordered()
+        .title_top(line!["Help".fg(theme.title)])
+        .title_bottom(hint.alignment(Alignment::Right))
+        .border_set(theme.border_set())
+        .border_style(theme.border)
+        .padding(Padding::uniform(1));
+
+    let table = Table::new(rows, constraints![==keys_width, ==100%])
+        .column_spacing(COLUMN_SPACING)
+        .block(block);
+
+    let area = area.centered(constraint!(==width), constraint!(==height));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(table, area);
+}
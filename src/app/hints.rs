@@ -0,0 +1,106 @@
+use super::{
+    App, Focus,
+    keys::{self, Action},
+    tabs::Tab,
+};
+use crate::signal::Signal;
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::Stylize,
+    text::{Line, Span},
+};
+
+impl App {
+    /// Keys worth knowing about for what's on screen right now, with a short description.
+    fn hints(&self) -> Vec<(Action, &'static str)> {
+        if self.editing_filter {
+            // enter and esc aren't bindings
+            return vec![];
+        }
+
+        if let Some(view) = &self.tree_view {
+            if view.editing_query {
+                return vec![];
+            }
+            return vec![
+                (Action::Down, "select"),
+                (Action::Filter, "search"),
+                (Action::NextMatch, "next match"),
+                (Action::Kill(Signal::Term), "terminate"),
+                (Action::ClearSelection, "close"),
+            ];
+        }
+
+        match (self.tab, self.focus) {
+            (Tab::Builds, Focus::Builds) => {
+                let mut hints = vec![(Action::Down, "select"), (Action::Filter, "filter")];
+                if self.grouped {
+                    hints.push((Action::Right, "expand"));
+                }
+                hints.extend([
+                    (Action::CycleSort, "sort"),
+                    (Action::Kill(Signal::Term), "terminate"),
+                ]);
+                if self.show_details {
+                    hints.extend([
+                        (Action::FocusNext, "next pane"),
+                        (Action::FlipLayout, "change layout"),
+                    ]);
+                } else {
+                    hints.push((Action::ToggleDetails, "show details"));
+                }
+                hints
+            }
+            (Tab::Builds, Focus::Tree) => vec![
+                (Action::Down, "select"),
+                (Action::Left, "collapse"),
+                (Action::Right, "expand"),
+                (Action::ScrollRight, "scroll"),
+                (Action::ToggleWrap, "wrap"),
+                (Action::ViewEnviron, "environment"),
+                (Action::ViewFiles, "open files"),
+                (Action::FocusNext, "next pane"),
+            ],
+            (Tab::Builds, Focus::Log) => vec![
+                (Action::Down, "scroll"),
+                (Action::Bottom, "end"),
+                (Action::FocusNext, "next pane"),
+            ],
+            (Tab::History, _) => vec![
+                (Action::Down, "scroll"),
+                (Action::CycleHistoryReport, "saved builds"),
+            ],
+            (Tab::System, _) => vec![
+                (Action::CollectGarbage, "collect garbage"),
+                (Action::ToggleDisk, "disk usage"),
+            ],
+            (Tab::Logs, _) => vec![(Action::Down, "scroll"), (Action::Bottom, "end")],
+        }
+    }
+
+    /// The bar at the bottom with the keys for the focused pane, and the ones that always work on
+    /// the right.
+    pub(super) fn render_hints(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let line = |hints: Vec<(Action, &'static str)>| {
+            let mut spans = vec![];
+            for (action, description) in hints {
+                if !spans.is_empty() {
+                    spans.push(Span::from("  "));
+                }
+                spans.push(Span::from(keys::label(action).unwrap_or_default()).fg(theme.key));
+                spans.push(Span::from(format!(" {description}")).fg(theme.hint));
+            }
+            Line::from(spans)
+        };
+
+        frame.render_widget(line(self.hints()), rect);
+        let always = vec![
+            (Action::CommandPalette, "commands"),
+            (Action::ToggleHelp, "help"),
+        ];
+        frame.render_widget(line(always).alignment(Alignment::Right), rect);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    archive::{self, Report},
+    history::History,
+    stats::{show_bytes, show_duration},
+    theme::Theme,
+};
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Rect},
+    macros::{constraints, line, row, text},
+    style::Stylize,
+    widgets::{Block, Padding, Row, Table},
+};
+
+/// Renders the finished builds, scrolled down by `scroll` rows.
+pub fn render(frame: &mut Frame, rect: Rect, theme: &Theme, history: &History, scroll: usize) {
+    let rows: Vec<_> = history
+        .finished
+        .iter()
+        .skip(scroll)
+        .map(|finished| {
+            let (pname, version) = finished.build.name();
+            row![
+                pname.fg(theme.pname),
+                version.fg(theme.version),
+                finished.build.host.as_deref().unwrap_or("local"),
+                finished
+                    .ended
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+                    .fg(theme.accent),
+                show_duration(finished.duration()),
+                text!(show_duration(finished.build.cpu_time())).alignment(Alignment::Right),
+                text!(finished.peaks.processes.to_string()).alignment(Alignment::Right),
+                text!(show_bytes(finished.peaks.rss)).alignment(Alignment::Right),
+            ]
+        })
+        .collect();
+
+    let header = Row::new([
+        "Package",
+        "Version",
+        "Host",
+        "Finished at",
+        "Took",
+        "CPU time",
+        "Peak procs",
+        "Peak mem",
+    ])
+    .dim()
+    .underlined();
+
+    let block = Block::bordered()
+        .title_top(line![
+            "Finished builds".fg(theme.title),
+            format!(" ({})", history.finished.len()).dim(),
+        ])
+        .border_set(theme.border_set())
+        .border_style(theme.border)
+        .padding(Padding::horizontal(1));
+
+    let table = Table::new(
+        rows,
+        constraints![==70%, ==30%, ==16, ==19, ==8, ==8, ==10, ==8],
+    )
+    .header(header)
+    .block(block);
+
+    frame.render_widget(table, rect);
+}
+
+/// Renders a report over the builds saved by earlier runs, scrolled down by `scroll` rows.
+pub fn render_report(
+    frame: &mut Frame,
+    rect: Rect,
+    theme: &Theme,
+    report: Report,
+    table: &archive::Table,
+    scroll: usize,
+) {
+    // as wide as the widest cell
+    let widths = table.header.iter().enumerate().map(|(i, header)| {
+        let width = table
+            .rows
+            .iter()
+            .filter_map(|row| row.get(i))
+            .map(|cell| cell.chars().count())
+            .chain([header.len()])
+            .max()
+            .unwrap_or_default();
+        Constraint::Length(width as u16)
+    });
+
+    let rows: Vec<_> = table
+        .rows
+        .iter()
+        .skip(scroll)
+        .map(|cells| {
+            Row::new(cells.iter().enumerate().map(|(i, cell)| match i {
+                // the package or day
+                0 => cell.clone().fg(theme.pname),
+                _ => cell.clone().into(),
+            }))
+        })
+        .collect();
+
+    let block = Block::bordered()
+        .title_top(line![
+            report.title().fg(theme.title),
+            format!(" ({})", table.rows.len()).dim(),
+        ])
+        .border_set(theme.border_set())
+        .border_style(theme.border)
+        .padding(Padding::horizontal(1));
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(table.header.clone()).dim().underlined())
+        .column_spacing(2)
+        .block(block);
+
+    frame.render_widget(table, rect);
+}
@@ -0,0 +1,424 @@
+use super::{sort::SortKey, tabs::Tab};
+use crate::{glyphs, signal::Signal};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Something that can be triggered from the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    FasterRefresh,
+    SlowerRefresh,
+    ToggleFreeze,
+    Refresh,
+    Up,
+    Down,
+    Left,
+    Right,
+    ScrollLeft,
+    ScrollRight,
+    ToggleWrap,
+    Top,
+    Bottom,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    FocusNext,
+    FocusPrevious,
+    ClearSelection,
+    CycleSort,
+    ReverseSort,
+    /// Only in the command palette.
+    SortBy(SortKey),
+    CycleHistoryReport,
+    Filter,
+    ViewDerivation,
+    ViewEnviron,
+    ViewFiles,
+    FullscreenTree,
+    /// Only in the fullscreen tree.
+    NextMatch,
+    PreviousMatch,
+    WhyDepends,
+    ToggleGroups,
+    ColumnMenu,
+    Kill(Signal),
+    SignalMenu,
+    TogglePause,
+    /// Changes the nice value by this much.
+    Renice(i32),
+    Export,
+    ShrinkBuilds,
+    GrowBuilds,
+    FlipLayout,
+    NextTab,
+    PreviousTab,
+    ShowTab(Tab),
+    ToggleDetails,
+    ToggleDisk,
+    CollectGarbage,
+    CommandPalette,
+    ToggleHelp,
+    Quit,
+}
+
+/// A single key combination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    const fn plain(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    const fn char(c: char) -> Self {
+        Self::plain(KeyCode::Char(c))
+    }
+
+    const fn ctrl(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    fn matches(&self, event: &KeyEvent) -> bool {
+        // shift is already baked into the char for stuff like `?` and `+`
+        let modifiers = event.modifiers.difference(KeyModifiers::SHIFT);
+        let code = match event.code {
+            // ctrl-C should still quit with caps lock on
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            code => code,
+        };
+        self.code == code && self.modifiers == modifiers
+    }
+
+    /// Short human-readable name of the key, for hints and the help screen.
+    pub fn label(&self) -> String {
+        let code = match self.code {
+            KeyCode::Up => glyphs::pick("↑", "Up").to_string(),
+            KeyCode::Down => glyphs::pick("↓", "Down").to_string(),
+            KeyCode::Left => glyphs::pick("←", "Left").to_string(),
+            KeyCode::Right => glyphs::pick("→", "Right").to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            code => code.to_string(),
+        };
+
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            format!("Ctrl-{code}")
+        } else {
+            code
+        }
+    }
+}
+
+/// Maps keys to an action. The first key is the "main" one shown in hints.
+#[derive(Debug)]
+pub struct Binding {
+    pub keys: &'static [Key],
+    pub action: Action,
+    pub description: &'static str,
+}
+
+/// The key handling table. Help screen is generated from this, so keep the descriptions short.
+pub const BINDINGS: &[Binding] = &[
+    Binding {
+        keys: &[Key::plain(KeyCode::Up), Key::char('k')],
+        action: Action::Up,
+        description: "Select previous build / scroll up",
+    },
+    Binding {
+        keys: &[Key::plain(KeyCode::Down), Key::char('j')],
+        action: Action::Down,
+        description: "Select next build / scroll down",
+    },
+    Binding {
+        keys: &[Key::plain(KeyCode::Left)],
+        action: Action::Left,
+        description: "Collapse process / group",
+    },
+    Binding {
+        keys: &[Key::plain(KeyCode::Right)],
+        action: Action::Right,
+        description: "Expand process / group",
+    },
+    Binding {
+        keys: &[Key::char('h')],
+        action: Action::ScrollLeft,
+        description: "Scroll commands left (process tree)",
+    },
+    Binding {
+        keys: &[Key::char('l')],
+        action: Action::ScrollRight,
+        description: "Scroll commands right (process tree)",
+    },
+    Binding {
+        keys: &[Key::char('W')],
+        action: Action::ToggleWrap,
+        description: "Wrap long commands (process tree)",
+    },
+    Binding {
+        keys: &[Key::char('g'), Key::plain(KeyCode::Home)],
+        action: Action::Top,
+        description: "Jump to first row (g twice)",
+    },
+    Binding {
+        keys: &[Key::char('G'), Key::plain(KeyCode::End)],
+        action: Action::Bottom,
+        description: "Jump to last row",
+    },
+    Binding {
+        keys: &[Key::plain(KeyCode::PageUp)],
+        action: Action::PageUp,
+        description: "Page up",
+    },
+    Binding {
+        keys: &[Key::plain(KeyCode::PageDown)],
+        action: Action::PageDown,
+        description: "Page down",
+    },
+    Binding {
+        keys: &[Key::ctrl('u')],
+        action: Action::HalfPageUp,
+        description: "Half a page up",
+    },
+    Binding {
+        keys: &[Key::ctrl('d')],
+        action: Action::HalfPageDown,
+        description: "Half a page down",
+    },
+    Binding {
+        keys: &[Key::char('L')],
+        action: Action::FocusNext,
+        description: "Focus next pane",
+    },
+    Binding {
+        keys: &[Key::char('H')],
+        action: Action::FocusPrevious,
+        description: "Focus previous pane",
+    },
+    Binding {
+        keys: &[Key::plain(KeyCode::Tab)],
+        action: Action::NextTab,
+        description: "Next tab",
+    },
+    Binding {
+        keys: &[Key::plain(KeyCode::BackTab)],
+        action: Action::PreviousTab,
+        description: "Previous tab",
+    },
+    Binding {
+        keys: &[Key::char('1')],
+        action: Action::ShowTab(Tab::Builds),
+        description: "Builds tab",
+    },
+    Binding {
+        keys: &[Key::char('2')],
+        action: Action::ShowTab(Tab::History),
+        description: "Finished builds tab",
+    },
+    Binding {
+        keys: &[Key::char('3')],
+        action: Action::ShowTab(Tab::System),
+        description: "System tab",
+    },
+    Binding {
+        keys: &[Key::char('4')],
+        action: Action::ShowTab(Tab::Logs),
+        description: "Event log tab",
+    },
+    Binding {
+        keys: &[Key::plain(KeyCode::Esc)],
+        action: Action::ClearSelection,
+        description: "Clear selection",
+    },
+    Binding {
+        keys: &[Key::char('-')],
+        action: Action::FasterRefresh,
+        description: "Decrease refresh interval",
+    },
+    Binding {
+        keys: &[Key::char('+'), Key::char('=')],
+        action: Action::SlowerRefresh,
+        description: "Increase refresh interval",
+    },
+    Binding {
+        keys: &[Key::char(' ')],
+        action: Action::ToggleFreeze,
+        description: "Pause/resume refreshing",
+    },
+    Binding {
+        keys: &[Key::char('r')],
+        action: Action::Refresh,
+        description: "Refresh now",
+    },
+    Binding {
+        keys: &[Key::char('s')],
+        action: Action::CycleSort,
+        description: "Cycle sort column",
+    },
+    Binding {
+        keys: &[Key::char('S')],
+        action: Action::ReverseSort,
+        description: "Reverse sort order",
+    },
+    Binding {
+        keys: &[Key::char('a')],
+        action: Action::CycleHistoryReport,
+        description: "Switch between this run and saved builds (finished builds tab)",
+    },
+    Binding {
+        keys: &[Key::char('f')],
+        action: Action::Filter,
+        description: "Filter builds by name, host or user",
+    },
+    Binding {
+        keys: &[Key::char('v')],
+        action: Action::ViewDerivation,
+        description: "View derivation of selected build",
+    },
+    Binding {
+        keys: &[Key::char('E')],
+        action: Action::ViewEnviron,
+        description: "View environment of selected process",
+    },
+    Binding {
+        keys: &[Key::char('o')],
+        action: Action::ViewFiles,
+        description: "View open files of selected process",
+    },
+    Binding {
+        keys: &[Key::char('t')],
+        action: Action::FullscreenTree,
+        description: "Process tree on the whole screen",
+    },
+    Binding {
+        keys: &[Key::char('n')],
+        action: Action::NextMatch,
+        description: "Next match in tree search",
+    },
+    Binding {
+        keys: &[Key::char('N')],
+        action: Action::PreviousMatch,
+        description: "Previous match in tree search",
+    },
+    Binding {
+        keys: &[Key::char('w')],
+        action: Action::WhyDepends,
+        description: "Find out why selected build is needed",
+    },
+    Binding {
+        keys: &[Key::char('p')],
+        action: Action::ToggleGroups,
+        description: "Group builds by package",
+    },
+    Binding {
+        keys: &[Key::char('c')],
+        action: Action::ColumnMenu,
+        description: "Choose columns",
+    },
+    Binding {
+        keys: &[Key::char('x'), Key::plain(KeyCode::Delete)],
+        action: Action::Kill(Signal::Term),
+        description: "Terminate selected build/process",
+    },
+    Binding {
+        keys: &[Key::char('X')],
+        action: Action::Kill(Signal::Kill),
+        description: "Kill selected build/process",
+    },
+    Binding {
+        keys: &[Key::char('K')],
+        action: Action::SignalMenu,
+        description: "Choose a signal to send",
+    },
+    Binding {
+        keys: &[Key::char('z')],
+        action: Action::TogglePause,
+        description: "Pause/resume selected build",
+    },
+    Binding {
+        keys: &[Key::char('[')],
+        action: Action::Renice(5),
+        description: "Lower priority of selected build",
+    },
+    Binding {
+        keys: &[Key::char(']')],
+        action: Action::Renice(-5),
+        description: "Raise priority of selected build (root)",
+    },
+    Binding {
+        keys: &[Key::char('e')],
+        action: Action::Export,
+        description: "Export builds to a file",
+    },
+    Binding {
+        keys: &[Key::char('<')],
+        action: Action::ShrinkBuilds,
+        description: "Shrink builds pane",
+    },
+    Binding {
+        keys: &[Key::char('>')],
+        action: Action::GrowBuilds,
+        description: "Grow builds pane",
+    },
+    Binding {
+        keys: &[Key::char('/')],
+        action: Action::FlipLayout,
+        description: "Change layout",
+    },
+    Binding {
+        keys: &[Key::char('D')],
+        action: Action::ToggleDetails,
+        description: "Toggle details pane",
+    },
+    Binding {
+        keys: &[Key::char('d')],
+        action: Action::ToggleDisk,
+        description: "Toggle store disk usage",
+    },
+    Binding {
+        keys: &[Key::char('C')],
+        action: Action::CollectGarbage,
+        description: "Run garbage collection",
+    },
+    Binding {
+        keys: &[Key::ctrl('p')],
+        action: Action::CommandPalette,
+        description: "Search all commands",
+    },
+    Binding {
+        keys: &[Key::char('?')],
+        action: Action::ToggleHelp,
+        description: "Toggle this help",
+    },
+    Binding {
+        keys: &[Key::char('q'), Key::ctrl('c')],
+        action: Action::Quit,
+        description: "Quit",
+    },
+];
+
+/// Looks up the action bound to a key event, if any.
+pub fn action_for(event: &KeyEvent) -> Option<Action> {
+    BINDINGS
+        .iter()
+        .find(|b| b.keys.iter().any(|k| k.matches(event)))
+        .map(|b| b.action)
+}
+
+/// Label of the main key bound to an action, for hints.
+pub fn label(action: Action) -> Option<String> {
+    BINDINGS
+        .iter()
+        .find(|b| b.action == action)
+        .and_then(|b| b.keys.first())
+        .map(Key::label)
+}
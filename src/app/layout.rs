@@ -0,0 +1,212 @@
+use super::{App, downloads, queue};
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    macros::vertical,
+    widgets::{Block, Padding},
+};
+
+/// How wide the terminal has to be for the builds and details to go next to each other by
+/// themselves.
+const SIDE_BY_SIDE_WIDTH: u16 = 200;
+
+/// Where everything goes on screen. Computed in one place so that mouse handling can find out
+/// what was clicked.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Areas {
+    pub tabs: Rect,
+    pub banner: Option<Rect>,
+    /// The key hints at the very bottom, unless turned off.
+    pub hints: Option<Rect>,
+    /// Everything under the tab bar and the banner, for the tabs other than builds.
+    pub content: Rect,
+    /// How busy the machine is, above the builds when watching this one.
+    pub summary: Option<Rect>,
+    pub builds: Rect,
+    /// Under the builds, when a Nix client says it has some queued up.
+    pub queue: Option<Rect>,
+    /// Under the builds, when something is being downloaded.
+    pub downloads: Option<Rect>,
+    /// Under the builds (and downloads), if turned on.
+    pub disk: Option<Rect>,
+    pub details: Rect,
+    pub properties: Rect,
+    /// Properties scrolled off the top.
+    pub properties_offset: usize,
+    pub tree: Rect,
+    /// Processes scrolled off the top, once all the properties are.
+    pub tree_offset: usize,
+    pub log: Rect,
+}
+
+impl App {
+    pub(super) fn areas(&self, area: Rect) -> Areas {
+        let mut areas = Areas::default();
+
+        let [tabs, mut area] = area.layout(&vertical![==1, *=1]);
+        areas.tabs = tabs;
+        if self.show_hints {
+            let [rest, hints] = area.layout(&vertical![*=1, ==1]);
+            areas.hints = Some(hints);
+            area = rest;
+        }
+        if self.refresh_error.is_some() || self.notice.is_some() || !self.collectors.is_empty() {
+            let [banner, rest] = area.layout(&vertical![==1, *=1]);
+            areas.banner = Some(banner);
+            area = rest;
+        }
+        areas.content = area;
+
+        if self.watches_local() {
+            let [summary, rest] = area.layout(&vertical![==1, *=1]);
+            areas.summary = Some(summary);
+            area = rest;
+        }
+
+        // without the details, the builds get everything and the details are nowhere
+        let (builds, details) = if self.show_details {
+            let [builds, details] = area.layout(&Layout::new(
+                self.direction(),
+                [
+                    Constraint::Percentage(self.split),
+                    Constraint::Percentage(100 - self.split),
+                ],
+            ));
+            (builds, details)
+        } else {
+            (area, Rect::default())
+        };
+        areas.builds = builds;
+
+        if self.show_disk {
+            let [builds, disk] = areas.builds.layout(&vertical![*=1, ==3]);
+            areas.builds = builds;
+            areas.disk = Some(disk);
+        }
+
+        if !self.queue.is_empty() {
+            let height = self.queue.waiting.len().min(queue::MAX_SHOWN) as u16 + 2;
+            let [builds, queue] = areas.builds.layout(&vertical![*=1, ==height]);
+            areas.builds = builds;
+            areas.queue = Some(queue);
+        }
+
+        let downloads = self.downloads().len();
+        if downloads > 0 {
+            // borders and header
+            let height = downloads.min(downloads::MAX_SHOWN) as u16 + 3;
+            let [builds, downloads] = areas.builds.layout(&vertical![*=1, ==height]);
+            areas.builds = builds;
+            areas.downloads = Some(downloads);
+        }
+        areas.details = details;
+
+        // has to match the block in render_build_details
+        let inner = Block::bordered()
+            .padding(Padding::uniform(1))
+            .inner(details);
+        let (properties, tree) = self.details_rows(inner.width);
+        let [_, _, log] = inner.layout(&vertical![==properties as u16, *=1, *=1]);
+        areas.log = log;
+
+        // the properties and the tree scroll together in what's left above the log
+        let above = Rect {
+            height: inner.height - log.height,
+            ..inner
+        };
+        let rows = properties + tree.iter().sum::<usize>();
+        let offset = self
+            .details_offset()
+            .min(rows.saturating_sub(above.height as usize));
+        areas.properties_offset = offset.min(properties);
+        // the list only scrolls by whole processes
+        let mut left = offset - areas.properties_offset;
+        for height in tree {
+            if height > left {
+                break;
+            }
+            left -= height;
+            areas.tree_offset += 1;
+        }
+        let shown = (properties - areas.properties_offset) as u16;
+        [areas.properties, areas.tree] = above.layout(&vertical![==shown, *=1]);
+
+        areas
+    }
+
+    /// Which way the builds and details are split. Unless picked, side by side once the terminal
+    /// is wide enough for both (cells are about twice as tall as they're wide), stacked otherwise.
+    pub(super) fn direction(&self) -> Direction {
+        self.direction.unwrap_or(
+            if self.area.width >= SIDE_BY_SIDE_WIDTH && self.area.width >= 3 * self.area.height {
+                Direction::Horizontal
+            } else {
+                Direction::Vertical
+            },
+        )
+    }
+
+    /// Rows the properties of the selected build take (with a spare one under them), and rows of
+    /// each line of its process tree.
+    fn details_rows(&self, width: u16) -> (usize, Vec<usize>) {
+        match self.selected_build() {
+            Some(build) => (
+                self.build_properties(build).len() + 1,
+                self.tree_heights(&self.tree.lines(build), width),
+            ),
+            None => (0, vec![]),
+        }
+    }
+
+    fn details_offset(&self) -> usize {
+        self.details_for
+            .as_ref()
+            .and_then(|build| self.details_scroll.get(build))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn set_details_offset(&mut self, offset: usize) {
+        if let Some(build) = &self.details_for {
+            self.details_scroll.insert(build.clone(), offset);
+        }
+    }
+
+    /// Scrolls the properties and process tree together, when they don't fit.
+    pub(super) fn scroll_details(&mut self, delta: isize) {
+        let areas = self.areas(self.area);
+        let (properties, tree) = self.details_rows(areas.tree.width);
+        let height = (areas.properties.height + areas.tree.height) as usize;
+        let max = (properties + tree.iter().sum::<usize>()).saturating_sub(height);
+        let offset = self.details_offset().min(max);
+        self.set_details_offset(offset.saturating_add_signed(delta).min(max));
+    }
+
+    /// Scrolls the details just enough to show the process under the cursor.
+    pub(super) fn scroll_to_cursor(&mut self) {
+        let Some(build) = self.selected_build() else {
+            return;
+        };
+        let areas = self.areas(self.area);
+        let (properties, tree) = self.details_rows(areas.tree.width);
+        let Some(cursor) = self.tree.cursor_index(&self.tree.lines(build)) else {
+            return;
+        };
+
+        let height = (areas.properties.height + areas.tree.height) as usize;
+        let top = properties + tree[..cursor].iter().sum::<usize>();
+        let bottom = top + tree[cursor];
+        let offset = self.details_offset();
+        let offset = if bottom <= height {
+            // back to the top once everything up to the cursor fits
+            0
+        } else if top < offset {
+            top
+        } else if bottom > offset + height {
+            bottom - height
+        } else {
+            offset
+        };
+        self.set_details_offset(offset);
+    }
+}
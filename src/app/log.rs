@@ -0,0 +1,138 @@
+use super::{AppEvent, Event};
+use crate::store;
+use std::process::Stdio;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    sync::mpsc,
+    task::JoinHandle,
+};
+
+/// How many lines of log to keep around per build.
+const MAX_LINES: usize = 10_000;
+
+/// Streams `nix log --follow` of one derivation into the event loop.
+#[derive(Debug)]
+pub struct BuildLog {
+    pub derivation: String,
+    pub lines: Vec<String>,
+    /// How many lines up from the bottom we're scrolled, 0 means following the tail.
+    pub scroll: usize,
+    task: JoinHandle<()>,
+}
+
+impl BuildLog {
+    /// Starts following the log, with `nix` being a command that runs Nix on the right machine.
+    pub fn follow(
+        derivation: String,
+        mut nix: Command,
+        sender: mpsc::UnboundedSender<Event>,
+    ) -> Self {
+        let path = store::drv_path(&derivation);
+        let drv = derivation.clone();
+        let task = tokio::spawn(async move {
+            let child = nix
+                .arg("log")
+                .arg("--follow")
+                .arg(&path)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn();
+
+            let mut child = match child {
+                Ok(child) => child,
+                Err(err) => {
+                    _ = sender.send(Event::App(AppEvent::Log(drv, format!("error: {err}"))));
+                    return;
+                }
+            };
+
+            let stdout = child.stdout.take().expect("stdout is piped");
+            let stderr = child.stderr.take().expect("stderr is piped");
+            tokio::join!(
+                forward(stdout, &drv, &sender),
+                forward(stderr, &drv, &sender),
+            );
+            _ = child.wait().await;
+        });
+
+        Self {
+            derivation,
+            lines: Vec::new(),
+            scroll: 0,
+            task,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        self.lines.push(strip_ansi(&line));
+        if self.lines.len() > MAX_LINES {
+            self.lines.drain(..self.lines.len() - MAX_LINES);
+        }
+
+        // stay on the same lines when scrolled up
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.lines.len());
+        }
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.lines.len().saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// The lines that fit into a pane of the given height.
+    pub fn window(&self, height: usize) -> &[String] {
+        let end = self.lines.len() - self.scroll.min(self.lines.len());
+        let start = end.saturating_sub(height);
+        &self.lines[start..end]
+    }
+}
+
+impl Drop for BuildLog {
+    fn drop(&mut self) {
+        // kills `nix log` since the child is kill_on_drop
+        self.task.abort();
+    }
+}
+
+async fn forward(
+    stream: impl AsyncRead + Unpin,
+    derivation: &str,
+    sender: &mpsc::UnboundedSender<Event>,
+) {
+    let mut lines = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let event = Event::App(AppEvent::Log(derivation.to_string(), line));
+        if sender.send(event).is_err() {
+            break;
+        }
+    }
+}
+
+/// Removes terminal escape sequences (colors mostly) that builders like to print.
+fn strip_ansi(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            result.push(c);
+            continue;
+        }
+
+        // CSI sequences end with a byte in the @..~ range
+        if chars.next_if_eq(&'[').is_some() {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    result
+}
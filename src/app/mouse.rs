@@ -0,0 +1,153 @@
+use super::{App, Focus, layout::Areas, tabs::Tab};
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
+
+/// Lines scrolled per mouse wheel tick in the log.
+const LOG_SCROLL: usize = 3;
+
+impl App {
+    /// Handles terminal mouse events.
+    pub(super) fn handle_mouse_event(&mut self, event: MouseEvent) {
+        // popups are keyboard only, and there's nothing to click on in the compact list
+        if self.too_small()
+            || self.show_help
+            || self.confirm.is_some()
+            || self.gc.is_some()
+            || self.column_menu.is_some()
+            || self.signal_menu.is_some()
+            || self.derivation_view.is_some()
+            || self.environ_view.is_some()
+            || self.files_view.is_some()
+            || self.tree_view.is_some()
+            || self.editing_filter
+        {
+            return;
+        }
+
+        let areas = self.areas(self.area);
+        let position = Position::new(event.column, event.row);
+
+        if areas.tabs.contains(position) {
+            if event.kind == MouseEventKind::Down(MouseButton::Left)
+                && let Some(tab) = self.tab_at(areas.tabs, position.x)
+            {
+                self.show_tab(tab);
+            }
+            return;
+        }
+
+        // the other tabs only scroll
+        if self.tab != Tab::Builds {
+            match event.kind {
+                MouseEventKind::ScrollUp => self.move_by(-1),
+                MouseEventKind::ScrollDown => self.move_by(1),
+                _ => {}
+            }
+            return;
+        }
+        // the properties aren't a pane, but scroll along with the tree
+        if areas.properties.contains(position) {
+            match event.kind {
+                MouseEventKind::ScrollUp => self.scroll_details(-1),
+                MouseEventKind::ScrollDown => self.scroll_details(1),
+                _ => {}
+            }
+            return;
+        }
+
+        let pane = if areas.builds.contains(position) {
+            Focus::Builds
+        } else if areas.tree.contains(position) {
+            Focus::Tree
+        } else if areas.log.contains(position) && self.log.is_some() {
+            Focus::Log
+        } else {
+            return;
+        };
+
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.focus = pane;
+                match pane {
+                    Focus::Builds => self.click_builds(areas.builds, position),
+                    Focus::Tree => self.click_tree(&areas, position),
+                    Focus::Log => {}
+                }
+            }
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let delta = if event.kind == MouseEventKind::ScrollUp {
+                    -1
+                } else {
+                    1
+                };
+
+                match pane {
+                    Focus::Log => self.move_in(pane, delta * LOG_SCROLL as isize),
+                    _ => self.move_in(pane, delta),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn click_builds(&mut self, rect: Rect, position: Position) {
+        // top border is where the refresh interval is, right-aligned: `- 2000ms +`
+        if position.y == rect.y {
+            if self.frozen {
+                return;
+            }
+            let width = format!(" {}ms ", self.refresh_interval.as_millis()).len() as u16 + 2;
+            let plus = rect.right().saturating_sub(2);
+            let minus = rect.right().saturating_sub(1 + width);
+            if position.x == plus {
+                self.slower_refresh();
+            } else if position.x == minus {
+                self.faster_refresh();
+            }
+            return;
+        }
+
+        // skip the border and the header
+        let Some(row) = position.y.checked_sub(rect.y + 2) else {
+            return;
+        };
+
+        let index = self.table_state.offset() + row as usize;
+        if index < self.table_rows().len() {
+            self.table_state.select(Some(index));
+        }
+    }
+
+    fn click_tree(&mut self, areas: &Areas, position: Position) {
+        let rect = areas.tree;
+        let Some(i) = self.selected_index() else {
+            return;
+        };
+
+        let build = &self.active_builds[i];
+        let lines = self.tree.lines(build);
+
+        // the list scrolls just enough to keep the cursor on the last line, and with wrapped
+        // commands lines can take more than one row
+        let heights = self.tree_heights(&lines, rect.width);
+        let mut offset = areas.tree_offset;
+        if let Some(cursor) = self.tree.cursor_index(&lines)
+            && cursor >= offset
+        {
+            let mut height: usize = heights[offset..=cursor].iter().sum();
+            while height > rect.height as usize && offset < cursor {
+                height -= heights[offset];
+                offset += 1;
+            }
+        }
+
+        let mut row = (position.y - rect.y) as usize;
+        for (line, height) in lines.iter().zip(&heights).skip(offset) {
+            if row < *height {
+                self.tree.cursor = Some(line.pid);
+                return;
+            }
+            row -= height;
+        }
+    }
+}
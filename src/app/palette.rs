@@ -0,0 +1,175 @@
+use super::{
+    App,
+    keys::{Action, BINDINGS},
+};
+use crate::{columns::Column, glyphs};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    macros::{constraint, constraints, line, row, text},
+    style::{Style, Stylize},
+    widgets::{Block, Clear, Padding, Row, Table, TableState},
+};
+
+/// How many matches the palette shows at once.
+const MAX_SHOWN: usize = 15;
+
+/// The command palette, listing every action to be searched by name.
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub query: String,
+    /// Index into the matches.
+    pub cursor: usize,
+}
+
+/// Something the palette can do, with the key that does it too.
+struct Command {
+    name: String,
+    key: Option<String>,
+    action: Action,
+}
+
+/// Everything from the keybindings, plus sorting by each column since those have no keys.
+fn commands() -> Vec<Command> {
+    let bindings = BINDINGS
+        .iter()
+        .filter(|b| b.action != Action::CommandPalette)
+        .map(|b| Command {
+            name: b.description.to_string(),
+            key: b.keys.first().map(|k| k.label()),
+            action: b.action,
+        });
+    let sorts = Column::ALL.into_iter().filter_map(|column| {
+        Some(Command {
+            name: format!("Sort by {}", column.title().to_lowercase()),
+            key: None,
+            action: Action::SortBy(column.sort_key()?),
+        })
+    });
+    bindings.chain(sorts).collect()
+}
+
+/// How well `query` fuzzily matches `text`, higher is better. All of the query's characters have
+/// to be in the text in order, and ones right after each other or at the start of words count
+/// more.
+fn score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|t| *t == c)?;
+        score += match found {
+            0 => 3,
+            _ if !text[found - 1].is_alphanumeric() => 3,
+            _ if previous == Some(found - 1) => 2,
+            _ => 0,
+        };
+        // gaps cost a little, so shorter spreads win
+        score -= (found - position).min(3) as i32;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// The commands matching the query, best first.
+fn matches(query: &str) -> Vec<Command> {
+    let mut scored: Vec<(i32, Command)> = commands()
+        .into_iter()
+        .filter_map(|command| Some((score(query, &command.name)?, command)))
+        .collect();
+    // stable, so equally good ones stay in help order
+    scored.sort_by_key(|(score, _)| -score);
+    scored.into_iter().map(|(_, command)| command).collect()
+}
+
+impl App {
+    /// Handles key events while the command palette is open.
+    pub(super) fn handle_palette(&mut self, key_event: KeyEvent) {
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let count = matches(&palette.query).len();
+
+        match key_event.code {
+            KeyCode::Up => palette.cursor = palette.cursor.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => palette.cursor = palette.cursor.saturating_sub(1),
+            KeyCode::Down => palette.cursor = (palette.cursor + 1).min(count.saturating_sub(1)),
+            KeyCode::Char('n') if ctrl => {
+                palette.cursor = (palette.cursor + 1).min(count.saturating_sub(1));
+            }
+            KeyCode::Char('c') if ctrl => self.palette = None,
+            KeyCode::Char(c) => {
+                palette.query.push(c);
+                palette.cursor = 0;
+            }
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.cursor = 0;
+            }
+            KeyCode::Enter => {
+                let action = matches(&palette.query)
+                    .get(palette.cursor)
+                    .map(|command| command.action);
+                self.palette = None;
+                if let Some(action) = action {
+                    self.perform(action);
+                }
+            }
+            KeyCode::Esc => self.palette = None,
+            _ => {}
+        }
+    }
+
+    pub(super) fn render_palette(&self, frame: &mut Frame, palette: &Palette) {
+        let theme = &self.theme;
+        let matches = matches(&palette.query);
+        let rows: Vec<_> = matches
+            .iter()
+            .map(|command| {
+                row![
+                    command.name.as_str(),
+                    text!(command.key.clone().unwrap_or_default())
+                        .alignment(Alignment::Right)
+                        .fg(theme.key),
+                ]
+            })
+            .collect();
+
+        let shown = rows.len().clamp(1, MAX_SHOWN) as u16;
+        let block = Block::bordered()
+            .title_top(line!["Commands".fg(theme.title)])
+            .title_bottom(line![
+                "enter".fg(theme.key),
+                " run ".fg(theme.hint),
+                "esc".fg(theme.key),
+                " close".fg(theme.hint),
+            ])
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+
+        let header = Row::new([line![
+            "> ".fg(theme.accent),
+            palette.query.as_str(),
+            glyphs::pick("█", "_")
+        ]])
+        .bottom_margin(1);
+        let table = Table::new(rows, constraints![==100%, ==10])
+            .header(header)
+            .block(block)
+            .row_highlight_style(Style::new().bg(theme.highlight));
+        let mut state = TableState::default().with_selected(Some(palette.cursor));
+
+        let area = frame
+            .area()
+            .centered(constraint!(==60), constraint!(==shown + 4));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+}
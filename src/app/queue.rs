@@ -0,0 +1,86 @@
+use super::{App, AppEvent, Event};
+use crate::store::DrvName;
+use std::{path::PathBuf, time::Duration};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader},
+    time,
+};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    macros::line,
+    style::Stylize,
+    widgets::{Block, List, Padding},
+};
+
+/// Most waiting derivations shown at once.
+pub const MAX_SHOWN: usize = 5;
+
+/// How often to check for more lines once we've caught up with the file.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+impl App {
+    /// Tails a file that a Nix client writes its `internal-json` log to, like `tail -f`.
+    pub(super) fn follow_activity(&self, path: PathBuf) {
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let file = match File::open(&path).await {
+                Ok(file) => file,
+                Err(err) => {
+                    let line = format!("can't read {}: {err}", path.display());
+                    _ = sender.send(Event::App(AppEvent::ActivityError(line)));
+                    return;
+                }
+            };
+
+            let mut lines = BufReader::new(file).lines();
+            loop {
+                match lines.next_line().await {
+                    Ok(Some(line)) => {
+                        if sender.send(Event::App(AppEvent::Activity(line))).is_err() {
+                            break;
+                        }
+                    }
+                    // caught up, wait for nix to write more
+                    Ok(None) if !sender.is_closed() => time::sleep(POLL_INTERVAL).await,
+                    _ => break,
+                }
+            }
+        });
+    }
+
+    pub(super) fn render_queue(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let queue = &self.queue;
+
+        let mut title = line!["Waiting".fg(theme.title)];
+        if let Some(progress) = queue.progress {
+            title.push_span(
+                format!(
+                    " ({} queued, {} of {} done)",
+                    progress.queued(),
+                    progress.done,
+                    progress.expected
+                )
+                .dim(),
+            );
+        }
+
+        let items: Vec<_> = queue
+            .waiting
+            .values()
+            .take(MAX_SHOWN)
+            .map(|derivation| line![DrvName::parse(derivation).name.fg(theme.pname)])
+            .collect();
+
+        let block = Block::bordered()
+            .title_top(title)
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+
+        frame.render_widget(List::new(items).block(block), rect);
+    }
+}
@@ -0,0 +1,197 @@
+//! JSON-RPC 2.0 on a unix socket, so scripts and editors can ask the running UI about builds.
+//! Requests and responses are one per line.
+
+use super::{App, AppEvent, Event};
+use crate::{
+    export, ps,
+    signal::{self, Signal},
+    store,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{
+    fs::{self, Permissions},
+    io,
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    slice,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{mpsc, oneshot},
+};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Anything that went wrong doing what was asked.
+const FAILED: i64 = -32000;
+
+#[derive(Debug)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// A request on its way to the event loop, with the channel the answer goes back on.
+#[derive(Debug)]
+pub struct Call {
+    method: String,
+    params: Value,
+    reply: oneshot::Sender<Result<Value, RpcError>>,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Params of the methods about one build.
+#[derive(Deserialize)]
+struct BuildParams {
+    /// With or without the store directory.
+    derivation: String,
+    /// Like `TERM` or `SIGKILL`, for `kill`.
+    #[serde(default)]
+    signal: Option<String>,
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": err.code, "message": err.message },
+        }),
+    }
+}
+
+/// Answers requests on one connection until it's closed.
+async fn handle(stream: UnixStream, sender: mpsc::UnboundedSender<Event>) -> io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let (reply, answer) = oneshot::channel();
+                let call = Call {
+                    method: request.method,
+                    params: request.params,
+                    reply,
+                };
+                if sender.send(Event::App(AppEvent::Rpc(call))).is_err() {
+                    break;
+                }
+                let result = answer
+                    .await
+                    .unwrap_or_else(|_| Err(RpcError::new(FAILED, "ntop is quitting")));
+                response(request.id, result)
+            }
+            Err(err) => response(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, err.to_string())),
+            ),
+        };
+        write.write_all(format!("{response}\n").as_bytes()).await?;
+    }
+    Ok(())
+}
+
+impl App {
+    /// Starts answering requests on a unix socket. A socket left behind by an earlier run is
+    /// replaced.
+    pub(super) fn serve_rpc(&mut self, path: PathBuf) {
+        _ = fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(err) => {
+                self.events
+                    .error(format!("Can't listen on {}: {err}", path.display()));
+                return;
+            }
+        };
+        // `kill` does whatever we're allowed to, so nobody else gets to ask
+        _ = fs::set_permissions(&path, Permissions::from_mode(0o600));
+
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    // nothing to do about broken connections
+                    _ = handle(stream, sender).await;
+                });
+            }
+        });
+    }
+
+    /// Answers a request with what the UI knows right now.
+    pub(super) fn answer(&mut self, call: Call) {
+        let result = self.call(&call.method, call.params);
+        _ = call.reply.send(result);
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "builds" => Ok(export::json(&self.active_builds, &self.cpu)),
+            "build" => {
+                let build = self.rpc_build(params)?.0;
+                let mut builds = export::json(slice::from_ref(build), &self.cpu);
+                Ok(builds[0].take())
+            }
+            "kill" => {
+                let (build, signal) = self.rpc_build(params)?;
+                if build.host.is_some() {
+                    return Err(RpcError::new(FAILED, "the build is on another machine"));
+                }
+                let signal = match signal {
+                    None => Signal::Term,
+                    Some(name) => Signal::from_name(&name).ok_or_else(|| {
+                        RpcError::new(INVALID_PARAMS, format!("unknown signal {name}"))
+                    })?,
+                };
+                // like the kill key, the Nix worker takes the builder down with it
+                let (pid, target) = (build.nix_pid, build.derivation.clone());
+                signal::send(pid, signal).map_err(|err| RpcError::new(FAILED, err.to_string()))?;
+                self.events
+                    .info(format!("Sent {} to {target}", signal.name()));
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("no method {method}, there's builds, build and kill"),
+            )),
+        }
+    }
+
+    /// The active build that the params are about, and the signal if there's one.
+    fn rpc_build(&self, params: Value) -> Result<(&ps::Build, Option<String>), RpcError> {
+        let params: BuildParams = serde_json::from_value(params)
+            .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?;
+        let derivation = store::strip_store_dir(&params.derivation).unwrap_or(&params.derivation);
+        let build = self
+            .active_builds
+            .iter()
+            .find(|b| b.derivation == derivation)
+            .ok_or_else(|| RpcError::new(FAILED, format!("{derivation} isn't being built")))?;
+        Ok((build, params.signal))
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{
+    client::{self, Client},
+    drv,
+    host::HostStats,
+    priority, proc, ps,
+};
+use std::collections::{HashMap, HashSet};
+
+/// What refreshing finds out about this machine besides the builds. Getting it means reading a
+/// good part of `/proc`, so it's done on a blocking thread next to the fetch, not by the UI.
+#[derive(Debug, Default)]
+pub struct LocalScan {
+    pub host: HostStats,
+    pub substitutions: Vec<proc::Substitution>,
+    pub collectors: Vec<usize>,
+    /// Builds handed to remote builders, as far as `/proc` can tell.
+    pub delegated: Vec<ps::Build>,
+    pub drv_files: HashMap<String, Option<drv::Local>>,
+    /// By main PID.
+    pub nice: HashMap<usize, i32>,
+    pub build_users: HashMap<usize, proc::BuildUser>,
+    /// By Nix PID.
+    pub clients: HashMap<usize, Option<Client>>,
+}
+
+/// What has to stay around between scans: counters to work out rates from, and things that
+/// don't change while a build runs.
+#[derive(Debug, Default)]
+pub(super) struct Scanner {
+    host: HostStats,
+    disk_usage: proc::DiskUsage,
+    drv_files: HashMap<String, Option<drv::Local>>,
+    clients: HashMap<usize, Option<Client>>,
+}
+
+impl Scanner {
+    /// Looks at `builds` and the machine they run on. `local` is whether any of them can be
+    /// here, otherwise only the derivations the activity log delegated (`delegated`) are read,
+    /// since those are always on this machine.
+    pub fn scan(&mut self, builds: &[ps::Build], delegated: &[String], local: bool) -> LocalScan {
+        let mut scan = LocalScan::default();
+        if local {
+            self.host.update();
+            scan.host = self.host.clone();
+            scan.substitutions = proc::substitutions(&mut self.disk_usage);
+            scan.collectors = proc::garbage_collectors();
+            scan.delegated = proc::delegated_builds(builds);
+        }
+
+        let here: Vec<&ps::Build> = builds
+            .iter()
+            .filter(|b| local && b.host.is_none())
+            .collect();
+        let derivations: HashSet<&String> = here
+            .iter()
+            .copied()
+            .chain(builds.iter().filter(|b| b.delegated))
+            .chain(&scan.delegated)
+            .map(|b| &b.derivation)
+            .chain(delegated)
+            .collect();
+        self.drv_files
+            .retain(|derivation, _| derivations.contains(derivation));
+        for derivation in derivations {
+            self.drv_files
+                .entry(derivation.clone())
+                .or_insert_with(|| drv::read_local(derivation));
+        }
+        scan.drv_files = self.drv_files.clone();
+
+        scan.nice = here
+            .iter()
+            .filter_map(|b| Some((b.main_pid, priority::nice(b.main_pid)?)))
+            .collect();
+        scan.build_users = here
+            .iter()
+            .filter_map(|b| Some((b.main_pid, proc::BuildUser::of(b.main_pid)?)))
+            .collect();
+
+        let nix_pids: HashSet<usize> = here.iter().map(|b| b.nix_pid).collect();
+        self.clients.retain(|pid, _| nix_pids.contains(pid));
+        for pid in nix_pids {
+            self.clients.entry(pid).or_insert_with(|| client::find(pid));
+        }
+        scan.clients = self.clients.clone();
+        scan
+    }
+}
@@ -0,0 +1,127 @@
+use super::{App, Focus, confirm::Confirm};
+use crate::signal::Signal;
+use crossterm::event::{KeyCode, KeyEvent};
+
+use ratatui::{
+    Frame,
+    macros::{constraint, line},
+    style::{Style, Stylize},
+    widgets::{Block, Clear, List, ListState, Padding},
+};
+
+/// How many recently sent signals go at the top of the menu.
+const RECENT: usize = 3;
+
+/// Picking which signal to send to a build or process.
+#[derive(Debug, Clone)]
+pub struct SignalMenu {
+    pid: usize,
+    /// What is being signaled, passed on to the confirmation.
+    target: String,
+    cursor: usize,
+}
+
+impl App {
+    /// The PID to signal and what it is: the process under the cursor when the tree is focused,
+    /// otherwise the Nix worker of the selected build, which takes the builder down with it.
+    pub(super) fn signal_target(&self) -> Option<(usize, String)> {
+        let build = self.selected_build()?;
+        let cursor = if self.focus == Focus::Tree {
+            self.tree.cursor
+        } else {
+            None
+        };
+        match cursor.and_then(|pid| build.processes.iter().find(|p| p.pid == pid)) {
+            Some(process) => Some((
+                process.pid,
+                format!("{} in {}", process.argv.join(" "), build.derivation),
+            )),
+            None => Some((build.nix_pid, build.derivation.clone())),
+        }
+    }
+
+    pub(super) fn open_signal_menu(&mut self) {
+        self.signal_menu = self.signal_target().map(|(pid, target)| SignalMenu {
+            pid,
+            target,
+            cursor: 0,
+        });
+    }
+
+    /// Recently sent signals first, then the rest in the usual order.
+    fn menu_signals(&self) -> Vec<Signal> {
+        let rest = Signal::ALL
+            .into_iter()
+            .filter(|s| !self.recent_signals.contains(s));
+        self.recent_signals.iter().copied().chain(rest).collect()
+    }
+
+    /// Handles key events while the signal menu is open.
+    pub(super) fn handle_signal_menu(&mut self, key_event: KeyEvent) {
+        let Some(menu) = &mut self.signal_menu else {
+            return;
+        };
+
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => menu.cursor = menu.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                menu.cursor = (menu.cursor + 1).min(Signal::ALL.len() - 1);
+            }
+            KeyCode::Enter => {
+                let Some(menu) = self.signal_menu.take() else {
+                    return;
+                };
+                let signal = self.menu_signals()[menu.cursor];
+                self.recent_signals.retain(|s| *s != signal);
+                self.recent_signals.insert(0, signal);
+                self.recent_signals.truncate(RECENT);
+                self.confirm = Some(Confirm::Signal {
+                    signal,
+                    pid: menu.pid,
+                    target: menu.target,
+                });
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.signal_menu = None,
+            _ => {}
+        }
+    }
+
+    pub(super) fn render_signal_menu(&self, frame: &mut Frame, menu: &SignalMenu) {
+        let theme = &self.theme;
+        let items: Vec<_> = self
+            .menu_signals()
+            .into_iter()
+            .map(|signal| {
+                line![
+                    format!("{:<8} ", signal.name()).fg(theme.danger),
+                    signal.description().fg(theme.hint)
+                ]
+            })
+            .collect();
+
+        let height = items.len() as u16 + 4;
+        let block = Block::bordered()
+            .title_top(line!["Send signal".fg(theme.title)])
+            .title_bottom(line![
+                "enter".fg(theme.key),
+                " choose ".fg(theme.hint),
+                "esc".fg(theme.key),
+                " cancel".fg(theme.hint),
+            ])
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::uniform(1));
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::new().bg(theme.highlight));
+        let mut state = ListState::default().with_selected(Some(menu.cursor));
+
+        let area = frame
+            .area()
+            .centered(constraint!(==32), constraint!(==height));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+}
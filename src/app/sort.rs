@@ -0,0 +1,79 @@
+use crate::{glyphs, ps};
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+/// Column the active builds table is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Pid,
+    Host,
+    #[default]
+    Package,
+    Version,
+    CpuTime,
+    Time,
+}
+
+impl SortKey {
+    /// The next key when cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Pid => SortKey::Host,
+            SortKey::Host => SortKey::Package,
+            SortKey::Package => SortKey::Version,
+            SortKey::Version => SortKey::CpuTime,
+            SortKey::CpuTime => SortKey::Time,
+            SortKey::Time => SortKey::Pid,
+        }
+    }
+
+    pub fn compare(self, a: &ps::Build, b: &ps::Build) -> Ordering {
+        match self {
+            SortKey::Pid => a.main_pid.cmp(&b.main_pid),
+            // local builds first
+            SortKey::Host => a.host.cmp(&b.host),
+            SortKey::Package => a.name().0.cmp(b.name().0),
+            SortKey::Version => a.name().1.cmp(b.name().1),
+            SortKey::CpuTime => a.cpu_time().cmp(&b.cpu_time()),
+            // later start = shorter elapsed time
+            SortKey::Time => b.started().cmp(&a.started()),
+        }
+    }
+}
+
+/// How the builds are sorted, also the `[sort]` section of the config to start with.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sort {
+    pub key: SortKey,
+    pub reversed: bool,
+    /// For builds that are equal by `key`, always in increasing order.
+    pub then: Option<SortKey>,
+}
+
+impl Sort {
+    pub fn apply(&self, builds: &mut [ps::Build]) {
+        builds.sort_by(|a, b| {
+            let ordering = self.key.compare(a, b);
+            let ordering = if self.reversed {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            ordering
+                .then_with(|| self.then.map_or(Ordering::Equal, |key| key.compare(a, b)))
+                // keep it stable across refreshes when the keys are equal
+                .then_with(|| a.derivation.cmp(&b.derivation))
+        });
+    }
+
+    /// Arrow shown next to the column header that is being sorted by.
+    pub fn indicator(&self, key: SortKey) -> &'static str {
+        match (self.key == key, self.reversed) {
+            (false, _) => "",
+            (true, false) => glyphs::pick(" ▲", " ^"),
+            (true, true) => glyphs::pick(" ▼", " v"),
+        }
+    }
+}
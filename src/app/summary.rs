@@ -0,0 +1,132 @@
+use super::{App, AppEvent, Event};
+use crate::{
+    host,
+    stats::{show_bytes, show_cpu, show_duration, show_rate, sparkline},
+};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    macros::line,
+    style::Stylize,
+    text::{Line, Span},
+};
+
+/// A minute of samples at the default refresh interval.
+const CPU_GRAPH_WIDTH: usize = 30;
+
+impl App {
+    /// Whether one of the sources is this machine, so that looking at `/proc` makes sense.
+    pub(super) fn watches_local(&self) -> bool {
+        self.sources.iter().any(|s| s.host().is_none())
+    }
+
+    pub(super) fn lookup_max_jobs(&self) {
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            // not worth bothering anyone about, the header just leaves it out
+            if let Ok(max_jobs) = host::max_jobs().await {
+                _ = sender.send(Event::App(AppEvent::MaxJobs(max_jobs)));
+            }
+        });
+    }
+
+    /// The line above the builds with load, CPU, memory, swap and how many builds there are.
+    pub(super) fn render_summary(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let mut line = line![" "];
+        let mut field = |name: &str, value: Span<'static>| {
+            if line.spans.len() > 1 {
+                line.push_span("  ");
+            }
+            line.push_span(format!("{name} ").dim());
+            line.push_span(value);
+        };
+
+        if let Some([one, five, fifteen]) = self.host.load {
+            field(
+                "Load",
+                format!("{one:.2} {five:.2} {fifteen:.2}").fg(theme.accent),
+            );
+        }
+        if self.host.cpu.is_some() {
+            // the last minute, to tell a busy moment from a saturated machine
+            let graph = sparkline(&self.host.cpu_history(), CPU_GRAPH_WIDTH);
+            let cpu = format!("{} {graph}", show_cpu(self.host.cpu));
+            field("CPU", cpu.fg(theme.cpu));
+        }
+        if let Some((used, total)) = self.host.memory {
+            let mut memory = format!("{}/{}", show_bytes(Some(used)), show_bytes(Some(total)));
+            // only what's measured on this machine, delegated builds count on theirs
+            let builds: u64 = self
+                .active_builds
+                .iter()
+                .filter(|b| b.host.is_none())
+                .filter_map(|b| b.rss())
+                .sum();
+            if builds > 0 {
+                memory.push_str(&format!(" ({} builds)", show_bytes(Some(builds))));
+            }
+            field("Mem", memory.fg(theme.memory));
+        }
+        if let Some((used, total)) = self.host.swap {
+            let swap = format!("{}/{}", show_bytes(Some(used)), show_bytes(Some(total)));
+            // any swapping at all is worth noticing, builds slow to a crawl when it keeps up
+            match self.host.swap_rate.filter(|rate| *rate > 0.0) {
+                Some(rate) => field(
+                    "Swap",
+                    format!("{swap} {}", show_rate(Some(rate))).fg(theme.danger),
+                ),
+                None => field("Swap", swap.fg(theme.memory)),
+            }
+        }
+
+        let local = self
+            .active_builds
+            .iter()
+            .filter(|b| b.host.is_none())
+            .count();
+        let builds = match self.max_jobs {
+            Some(max_jobs) => format!("{local}/{max_jobs} max-jobs"),
+            None => local.to_string(),
+        };
+        field("Builds", builds.fg(theme.accent));
+
+        frame.render_widget(line, rect);
+    }
+
+    /// Everything in the builds table added up, for the bottom of it.
+    pub(super) fn build_totals(&self) -> Line<'static> {
+        let builds = &self.active_builds;
+        if builds.is_empty() {
+            return Line::default();
+        }
+        let processes: usize = builds.iter().map(|b| b.processes.len()).sum();
+        let cpu: f64 = builds.iter().filter_map(|b| self.cpu.build(b)).sum();
+        let memory: u64 = builds.iter().filter_map(|b| b.rss()).sum();
+        let plural = |count: usize, what: &str| {
+            if count == 1 {
+                format!("{count} {what}")
+            } else {
+                format!("{count} {what}es")
+            }
+        };
+
+        let mut line = line![
+            format!(
+                " {} builds, {}, {} CPU, {} memory",
+                builds.len(),
+                plural(processes, "process"),
+                show_cpu(Some(cpu)),
+                show_bytes(Some(memory)),
+            )
+            .dim()
+        ];
+        if let Some(oldest) = builds.iter().filter_map(|b| b.elapsed(self.now)).max() {
+            line.push_span(format!(", oldest {} ", show_duration(oldest)).dim());
+        } else {
+            line.push_span(" ");
+        }
+        line
+    }
+}
@@ -0,0 +1,273 @@
+use super::App;
+use crate::{
+    events::Level,
+    glyphs,
+    stats::{progress_bar, show_cpu},
+};
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    macros::{constraints, line, row, text, vertical},
+    style::{Style, Stylize},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{Axis, Block, Chart, Dataset, GraphType, Padding, Paragraph, Row, Table},
+};
+
+/// How wide the meter of one core on the system tab is at least, with its label.
+const CORE_METER_WIDTH: u16 = 24;
+
+/// Top-level view, picked from the tab bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tab {
+    #[default]
+    Builds,
+    History,
+    System,
+    Logs,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 4] = [Tab::Builds, Tab::History, Tab::System, Tab::Logs];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Tab::Builds => "Builds",
+            Tab::History => "History",
+            Tab::System => "System",
+            Tab::Logs => "Logs",
+        }
+    }
+
+    fn index(self) -> usize {
+        Tab::ALL.iter().position(|t| *t == self).unwrap_or(0)
+    }
+
+    /// The next (or previous) tab, wrapping around.
+    pub fn cycle(self, forward: bool) -> Self {
+        let len = Tab::ALL.len();
+        let index = if forward {
+            self.index() + 1
+        } else {
+            self.index() + len - 1
+        };
+        Tab::ALL[index % len]
+    }
+}
+
+/// Text of a tab in the tab bar, its width is needed for clicking.
+fn label(tab: Tab) -> String {
+    format!(" {} {} ", tab.index() + 1, tab.title())
+}
+
+impl App {
+    pub(super) fn show_tab(&mut self, tab: Tab) {
+        self.tab = tab;
+        if tab == Tab::System {
+            self.watch_disk();
+        }
+    }
+
+    /// The tab under a column of the tab bar.
+    pub(super) fn tab_at(&self, rect: Rect, x: u16) -> Option<Tab> {
+        let mut left = rect.x;
+        for tab in Tab::ALL {
+            let right = left + label(tab).len() as u16;
+            if (left..right).contains(&x) {
+                return Some(tab);
+            }
+            // the divider
+            left = right + 1;
+        }
+        None
+    }
+
+    pub(super) fn render_tabs(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let mut spans = vec![];
+        for tab in Tab::ALL {
+            if !spans.is_empty() {
+                spans.push(Span::from(glyphs::pick("│", "|")).fg(theme.border));
+            }
+            let label = Span::from(label(tab));
+            spans.push(if tab == self.tab {
+                label.fg(theme.title).bg(theme.highlight).bold()
+            } else {
+                label.fg(theme.hint)
+            });
+        }
+        frame.render_widget(Line::from(spans), rect);
+    }
+
+    pub(super) fn render_system(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let downloads = self.downloads().len().clamp(1, super::downloads::MAX_SHOWN) as u16 + 3;
+        let cores = self.core_rows(rect.width);
+        let cores = if cores == 0 { 0 } else { cores + 2 };
+        let disk_io = if self.host.disk_io.is_some() { 3 } else { 0 };
+        let [disk, disk_io, gc, downloads, cores, chart] =
+            rect.layout(&vertical![==3, ==disk_io, ==3, ==downloads, ==cores, *=1]);
+
+        self.render_disk(frame, disk);
+        self.render_disk_io(frame, disk_io);
+
+        let status = if self.collectors.is_empty() {
+            line!["none running".dim()]
+        } else {
+            let pids: Vec<_> = self.collectors.iter().map(|p| p.to_string()).collect();
+            line![
+                "running ".fg(theme.danger).bold(),
+                format!("(PID {}), new builds will wait for it", pids.join(", ")),
+            ]
+        };
+        let block = Block::bordered()
+            .title_top(line!["Garbage collection".fg(theme.title)])
+            .title_bottom(
+                line!["C".fg(theme.key), " collect garbage".fg(theme.hint)]
+                    .alignment(Alignment::Right),
+            )
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+        frame.render_widget(Paragraph::new(status).block(block), gc);
+
+        self.render_downloads(frame, downloads);
+        if !self.host.cores.is_empty() {
+            self.render_cores(frame, cores);
+        }
+        self.render_build_chart(frame, chart);
+    }
+
+    /// How many columns of core meters fit in a pane `width` wide, at least one.
+    fn core_columns(width: u16) -> usize {
+        // borders and padding
+        (width.saturating_sub(4) / CORE_METER_WIDTH).max(1) as usize
+    }
+
+    /// How many rows the core meters need, none when there's nothing to show.
+    fn core_rows(&self, width: u16) -> u16 {
+        self.host.cores.len().div_ceil(Self::core_columns(width)) as u16
+    }
+
+    /// A bar per core, like htop's meters, filled down each column first.
+    fn render_cores(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let cores = &self.host.cores;
+        let columns = Self::core_columns(rect.width);
+        let rows = cores.len().div_ceil(columns);
+        let column_width = (rect.width.saturating_sub(4) as usize / columns).max(1);
+        let label_width = (cores.len() - 1).to_string().len();
+        // the label, a space before the bar, the percentage and a gap before the next column
+        let bar_width = column_width.saturating_sub(label_width + 7).max(1);
+
+        let lines: Vec<_> = (0..rows)
+            .map(|row| {
+                let mut line = line![];
+                for (i, usage) in cores.iter().enumerate().skip(row).step_by(rows) {
+                    line.push_span(format!("{i:>label_width$} ").dim());
+                    let bar = progress_bar(usage.round() as u64, 100, bar_width);
+                    line.push_span(bar.fg(theme.cpu));
+                    line.push_span(format!(" {:>4} ", show_cpu(Some(*usage))));
+                }
+                line
+            })
+            .collect();
+
+        let mut title = line!["CPU cores".fg(theme.title)];
+        let busy = cores.iter().filter(|usage| **usage >= 50.0).count();
+        title.push_span(format!(" {busy} of {} busy", cores.len()).dim());
+        if let Some(max_jobs) = self.max_jobs {
+            title.push_span(format!(", max-jobs {max_jobs}").dim());
+        }
+        let block = Block::bordered()
+            .title_top(title)
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+        frame.render_widget(Paragraph::new(lines).block(block), rect);
+    }
+
+    /// Line chart of how many builds were running, over the configured window.
+    fn render_build_chart(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let counts = &self.build_counts;
+        let now = self.now;
+        let minutes = counts.window.num_seconds() as f64 / 60.0;
+
+        // minutes ago, negative so that now is on the right
+        let points: Vec<(f64, f64)> = counts
+            .samples
+            .iter()
+            .map(|(at, count)| {
+                let ago = (now - *at).num_milliseconds() as f64 / 60_000.0;
+                (-ago, *count as f64)
+            })
+            .collect();
+        let max = counts
+            .samples
+            .iter()
+            .map(|(_, c)| *c)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let dataset = Dataset::default()
+            .graph_type(GraphType::Line)
+            .marker(Marker::Braille)
+            .style(Style::new().fg(theme.accent))
+            .data(&points);
+
+        let x = Axis::default()
+            .bounds([-minutes, 0.0])
+            .labels([format!("-{minutes:.0}m"), "now".to_string()])
+            .style(Style::new().dim());
+        let y = Axis::default()
+            .bounds([0.0, max as f64])
+            .labels(["0".to_string(), max.to_string()])
+            .style(Style::new().dim());
+
+        let block = Block::bordered()
+            .title_top(line!["Active builds".fg(theme.title)])
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+
+        let chart = Chart::new(vec![dataset]).block(block).x_axis(x).y_axis(y);
+        frame.render_widget(chart, rect);
+    }
+
+    pub(super) fn render_logs(&self, frame: &mut Frame, rect: Rect) {
+        let theme = &self.theme;
+        let rows: Vec<_> = self
+            .events
+            .entries
+            .iter()
+            .skip(self.events_scroll)
+            .map(|entry| {
+                let message = match entry.level {
+                    Level::Info => text!(entry.message.as_str()),
+                    Level::Error => text!(entry.message.as_str()).fg(theme.danger),
+                };
+                row![
+                    entry.time.format("%H:%M:%S").to_string().fg(theme.accent),
+                    message,
+                ]
+            })
+            .collect();
+
+        let block = Block::bordered()
+            .title_top(line![
+                "Events".fg(theme.title),
+                format!(" ({})", self.events.entries.len()).dim(),
+            ])
+            .border_set(theme.border_set())
+            .border_style(theme.border)
+            .padding(Padding::horizontal(1));
+
+        let table = Table::new(rows, constraints![==8, *=1])
+            .header(Row::new(["Time", "Event"]).dim().underlined())
+            .block(block);
+        frame.render_widget(table, rect);
+    }
+}
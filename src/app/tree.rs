@@ -0,0 +1,221 @@
+use crate::{glyphs, ps};
+use std::{collections::HashSet, mem};
+
+/// Cursor and collapsed nodes of the process tree of the selected build.
+#[derive(Debug, Default)]
+pub struct TreeState {
+    /// PID of the process under the cursor.
+    pub cursor: Option<usize>,
+    /// PIDs of processes whose children are hidden.
+    pub collapsed: HashSet<usize>,
+    /// How many characters of the commands are scrolled off to the left.
+    pub scroll: usize,
+}
+
+/// A single visible line of the process tree.
+#[derive(Debug)]
+pub struct TreeLine<'a> {
+    pub pid: usize,
+    /// Box drawing stuff before the command.
+    pub prefix: String,
+    /// Same for the lines of the command after the first, when wrapped.
+    pub indent: String,
+    pub argv: Vec<&'a str>,
+    /// How many processes are hidden under this one, if it's collapsed.
+    pub hidden: Option<usize>,
+}
+
+impl TreeLine<'_> {
+    pub fn command(&self) -> String {
+        self.argv.join(" ")
+    }
+}
+
+impl TreeState {
+    /// Flattens the tree into lines, skipping children of collapsed processes.
+    pub fn lines<'a>(&self, build: &'a ps::Build) -> Vec<TreeLine<'a>> {
+        let mut lines = vec![];
+        self.walk(build, build.main_pid, "", "", &mut lines);
+        lines
+    }
+
+    fn walk<'a>(
+        &self,
+        build: &'a ps::Build,
+        pid: usize,
+        first: &str,
+        rest: &str,
+        lines: &mut Vec<TreeLine<'a>>,
+    ) {
+        let Some(process) = build.process(pid) else {
+            return;
+        };
+
+        let collapsed = self.collapsed.contains(&pid);
+        let children = children(build, pid);
+        // keeps the line down to the children going
+        let indent = if collapsed || children.is_empty() {
+            format!("{rest}     ")
+        } else {
+            format!("{rest}{}    ", glyphs::pick("│", "|"))
+        };
+        lines.push(TreeLine {
+            pid,
+            prefix: first.to_string(),
+            indent,
+            argv: process.argv.iter().map(String::as_str).collect(),
+            hidden: collapsed.then(|| descendants(build, pid)),
+        });
+
+        if collapsed {
+            return;
+        }
+
+        for (i, child) in children.iter().enumerate() {
+            let last = i == children.len() - 1;
+            let (first, next) = if last {
+                (
+                    format!("{rest}{}", glyphs::pick("└─── ", "`--- ")),
+                    format!("{rest}     "),
+                )
+            } else {
+                let line = glyphs::pick("│", "|");
+                (
+                    format!("{rest}{}", glyphs::pick("├─── ", "|--- ")),
+                    format!("{rest}{line}    "),
+                )
+            };
+            self.walk(build, child.pid, &first, &next, lines);
+        }
+    }
+
+    /// Index of the cursor in the visible lines. If that process went away (or is hidden), the
+    /// first line is used instead.
+    pub fn cursor_index(&self, lines: &[TreeLine]) -> Option<usize> {
+        let index = self
+            .cursor
+            .and_then(|pid| lines.iter().position(|l| l.pid == pid));
+
+        index.or((!lines.is_empty()).then_some(0))
+    }
+
+    pub fn move_by(&mut self, build: &ps::Build, delta: isize) {
+        let lines = self.lines(build);
+        let Some(index) = self.cursor_index(&lines) else {
+            return;
+        };
+
+        let index = index.saturating_add_signed(delta).min(lines.len() - 1);
+        self.cursor = Some(lines[index].pid);
+    }
+
+    /// Collapses the process under the cursor, or goes to its parent if there's nothing to collapse.
+    pub fn collapse(&mut self, build: &ps::Build) {
+        let Some(pid) = self.cursor else {
+            return;
+        };
+
+        if !children(build, pid).is_empty() && self.collapsed.insert(pid) {
+            return;
+        }
+
+        if pid != build.main_pid
+            && let Some(process) = build.process(pid)
+            && let Some(parent) = process.parent_pid
+            && build.process(parent).is_some()
+        {
+            self.cursor = Some(parent);
+        }
+    }
+
+    /// Scrolls the commands sideways, up to where the longest one ends.
+    pub fn scroll_by(&mut self, build: &ps::Build, delta: isize) {
+        let longest = self
+            .lines(build)
+            .iter()
+            .map(|line| line.command().chars().count())
+            .max()
+            .unwrap_or_default();
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta)
+            .min(longest.saturating_sub(1));
+    }
+
+    pub fn expand(&mut self) {
+        if let Some(pid) = self.cursor {
+            self.collapsed.remove(&pid);
+        }
+    }
+}
+
+/// Splits a command into lines that fit in `first` characters for the first one and `rest` after
+/// that, between arguments unless one doesn't fit on a line by itself.
+pub fn wrap(command: &str, first: usize, rest: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let width = |lines: &Vec<String>| if lines.is_empty() { first } else { rest }.max(1);
+    for word in command.split(' ') {
+        let used = line.chars().count();
+        if used > 0 && used + 1 + word.chars().count() > width(&lines) {
+            lines.push(mem::take(&mut line));
+        } else if used > 0 {
+            line.push(' ');
+        }
+        line.push_str(word);
+
+        // too long even by itself
+        while line.chars().count() > width(&lines) {
+            let tail = line.chars().skip(width(&lines)).collect();
+            line = line.chars().take(width(&lines)).collect();
+            lines.push(mem::replace(&mut line, tail));
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+fn children(build: &ps::Build, pid: usize) -> Vec<&ps::BuildProcess> {
+    build
+        .processes
+        .iter()
+        .filter(|p| p.parent_pid == Some(pid))
+        .collect()
+}
+
+fn descendants(build: &ps::Build, pid: usize) -> usize {
+    children(build, pid)
+        .iter()
+        .map(|child| 1 + descendants(build, child.pid))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_fits_on_one_line() {
+        assert_eq!(wrap("make -j8 all", 20, 20), ["make -j8 all"]);
+    }
+
+    #[test]
+    fn wrap_empty() {
+        assert_eq!(wrap("", 10, 10), [""]);
+    }
+
+    #[test]
+    fn wrap_narrower_after_first_line() {
+        assert_eq!(wrap("aaa bbb ccc ddd", 7, 3), ["aaa bbb", "ccc", "ddd"]);
+    }
+
+    #[test]
+    fn wrap_breaks_long_words() {
+        assert_eq!(wrap("abcdefghij", 4, 3), ["abcd", "efg", "hij"]);
+    }
+
+    #[test]
+    fn wrap_counts_chars_not_bytes() {
+        assert_eq!(wrap("ééééé ü", 3, 4), ["ééé", "éé ü"]);
+    }
+}
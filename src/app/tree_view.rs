@@ -0,0 +1,192 @@
+use super::{
+    App, Focus,
+    keys::{self, Action},
+    tree::TreeLine,
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    macros::line,
+    style::{Style, Stylize},
+    widgets::{Block, Clear, List, ListState, Padding},
+};
+
+/// The process tree of the selected build on the whole screen, for deep builds. The cursor and
+/// collapsed processes are the same ones as in the details pane.
+#[derive(Debug, Default)]
+pub struct TreeView {
+    /// What to look for in the commands, ignoring case.
+    pub query: String,
+    pub editing_query: bool,
+}
+
+impl TreeView {
+    fn matches(&self, line: &TreeLine) -> bool {
+        !self.query.is_empty()
+            && line
+                .command()
+                .to_lowercase()
+                .contains(&self.query.to_lowercase())
+    }
+}
+
+impl App {
+    pub(super) fn open_tree_view(&mut self) {
+        if self.selected_build().is_none() {
+            return;
+        }
+        // so that signals and such go to the process under the cursor
+        self.focus = Focus::Tree;
+        self.tree_view = Some(TreeView::default());
+    }
+
+    pub(super) fn close_tree_view(&mut self) {
+        self.tree_view = None;
+        if !self.show_details {
+            self.focus = Focus::Builds;
+        }
+    }
+
+    /// Typing into the search box of the tree view, which jumps to the first match as it goes.
+    pub(super) fn handle_tree_search(&mut self, key_event: KeyEvent) {
+        let Some(view) = &mut self.tree_view else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c) => view.query.push(c),
+            KeyCode::Backspace => _ = view.query.pop(),
+            KeyCode::Enter => view.editing_query = false,
+            KeyCode::Esc => {
+                view.editing_query = false;
+                view.query.clear();
+                return;
+            }
+            _ => return,
+        }
+        self.find_match(true, false);
+    }
+
+    /// Moves the cursor to the next (or previous) process that matches the search, wrapping
+    /// around. The one under the cursor only counts if it's not `skip_cursor`.
+    fn find_match(&mut self, forward: bool, skip_cursor: bool) {
+        let (Some(view), Some(i)) = (&self.tree_view, self.selected_index()) else {
+            return;
+        };
+        let lines = self.tree.lines(&self.active_builds[i]);
+        let Some(cursor) = self.tree.cursor_index(&lines) else {
+            return;
+        };
+
+        let count = lines.len();
+        let first = usize::from(skip_cursor);
+        let found = (first..first + count)
+            .map(|step| {
+                if forward {
+                    (cursor + step) % count
+                } else {
+                    (cursor + count - step % count) % count
+                }
+            })
+            .find(|i| view.matches(&lines[*i]));
+        if let Some(i) = found {
+            self.tree.cursor = Some(lines[i].pid);
+        }
+    }
+
+    /// Moves around, searches or closes the tree view. Most keys do what they do in the tree pane.
+    pub(super) fn handle_tree_view(&mut self, action: Action) {
+        match action {
+            Action::Filter => {
+                if let Some(view) = &mut self.tree_view {
+                    view.editing_query = true;
+                }
+            }
+            Action::NextMatch => self.find_match(true, true),
+            Action::PreviousMatch => self.find_match(false, true),
+            Action::ClearSelection | Action::FullscreenTree => self.close_tree_view(),
+            Action::Up
+            | Action::Down
+            | Action::Left
+            | Action::Right
+            | Action::ScrollLeft
+            | Action::ScrollRight
+            | Action::ToggleWrap
+            | Action::Top
+            | Action::Bottom
+            | Action::PageUp
+            | Action::PageDown
+            | Action::HalfPageUp
+            | Action::HalfPageDown
+            | Action::Kill(_)
+            | Action::SignalMenu
+            | Action::ViewEnviron
+            | Action::ViewFiles
+            | Action::ToggleHelp => self.perform(action),
+            _ => {}
+        }
+    }
+
+    pub(super) fn render_tree_view(&self, frame: &mut Frame, rect: Rect, view: &TreeView) {
+        let Some(build) = self.selected_build() else {
+            return;
+        };
+        let theme = &self.theme;
+        let lines = self.tree.lines(build);
+
+        let mut title = line![
+            "Processes".fg(theme.title),
+            format!(" of {}", build.drv_name().name).dim(),
+        ];
+        if view.editing_query || !view.query.is_empty() {
+            title.push_span(" search: ".dim());
+            title.push_span(view.query.as_str().fg(theme.accent));
+            if view.editing_query {
+                title.push_span("_".fg(theme.accent).slow_blink());
+            }
+            let matches = lines.iter().filter(|line| view.matches(line)).count();
+            title.push_span(format!(" ({matches} found)").dim());
+        }
+
+        // from the bindings, so they stay right if those change
+        let key = |action| keys::label(action).unwrap_or_default();
+        let block = Block::bordered()
+            .title_top(title)
+            .title_bottom(
+                line![
+                    key(Action::Filter).fg(theme.key),
+                    " search  ".fg(theme.hint),
+                    format!("{}/{}", key(Action::NextMatch), key(Action::PreviousMatch))
+                        .fg(theme.key),
+                    " next/previous  ".fg(theme.hint),
+                    key(Action::ClearSelection).fg(theme.key),
+                    " close".fg(theme.hint),
+                ]
+                .alignment(Alignment::Right),
+            )
+            .border_set(theme.border_set())
+            .border_style(theme.focus)
+            .padding(Padding::horizontal(1));
+
+        let inner = block.inner(rect);
+        let items: Vec<_> = self
+            .tree_items(build, &lines, inner.width)
+            .into_iter()
+            .zip(&lines)
+            .map(|(item, line)| {
+                if view.matches(line) {
+                    item.patch_style(Style::new().bold().underlined())
+                } else {
+                    item
+                }
+            })
+            .collect();
+        let list = List::new(items).highlight_style(Style::new().bg(theme.highlight));
+        let mut state = ListState::default().with_selected(self.tree.cursor_index(&lines));
+
+        frame.render_widget(Clear, rect);
+        frame.render_widget(block, rect);
+        frame.render_stateful_widget(list, inner, &mut state);
+    }
+}
@@ -0,0 +1,352 @@
+//! Every build ntop saw finish, across runs, for looking back at later. Kept as one JSON object
+//! per line in `$XDG_STATE_HOME/ntop/builds.jsonl`, so saving one is just appending to it.
+
+use crate::{
+    history::FinishedBuild,
+    stats::{show_bytes, show_duration},
+    store::DrvName,
+};
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Where ntop keeps things between runs, `$XDG_STATE_HOME/ntop`.
+pub fn state_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(base.join("ntop"))
+}
+
+pub fn path() -> Option<PathBuf> {
+    Some(state_dir()?.join("builds.jsonl"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    pub derivation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Unix time.
+    pub started: i64,
+    /// Unix time ntop noticed it was gone, so up to one refresh late.
+    pub ended: i64,
+    pub cpu_seconds: f64,
+    /// Most processes at once. Builds saved by older versions don't have this or `peak_rss`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_processes: Option<usize>,
+    /// Most memory at once, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss: Option<u64>,
+}
+
+impl Record {
+    pub fn new(finished: &FinishedBuild) -> Self {
+        Self {
+            derivation: finished.build.derivation.clone(),
+            host: finished.build.host.clone(),
+            started: finished
+                .build
+                .started()
+                .unwrap_or(finished.ended)
+                .timestamp(),
+            ended: finished.ended.timestamp(),
+            cpu_seconds: finished.build.cpu_time().num_milliseconds() as f64 / 1000.0,
+            peak_processes: (finished.peaks.processes > 0).then_some(finished.peaks.processes),
+            peak_rss: finished.peaks.rss,
+        }
+    }
+
+    pub fn duration(&self) -> TimeDelta {
+        TimeDelta::try_seconds(self.ended.saturating_sub(self.started)).unwrap_or_default()
+    }
+
+    pub fn cpu_time(&self) -> TimeDelta {
+        TimeDelta::try_milliseconds((self.cpu_seconds * 1000.0) as i64).unwrap_or_default()
+    }
+
+    /// Whether the times could be real. A hand-edited or corrupt line could have anything, and
+    /// adding those up would overflow.
+    fn plausible(&self) -> bool {
+        const TEN_YEARS: i64 = 10 * 365 * 24 * 60 * 60;
+        (0..=TEN_YEARS).contains(&self.ended.saturating_sub(self.started))
+            && (0.0..=TEN_YEARS as f64).contains(&self.cpu_seconds)
+    }
+
+    pub fn ended(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_secs(self.ended).unwrap_or_default()
+    }
+
+    pub fn name(&self) -> (&str, &str) {
+        let name = DrvName::parse(&self.derivation);
+        (name.pname, name.version)
+    }
+}
+
+/// Adds a build to the end of the file.
+pub fn append(record: &Record) -> anyhow::Result<()> {
+    let path = path().context("no home directory to save builds in")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    // one write, so lines from two ntops don't get mixed up
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+/// Reads all saved builds, oldest first. Lines that don't parse or make no sense are skipped.
+pub fn load() -> anyhow::Result<Vec<Record>> {
+    match path() {
+        Some(path) => load_from(&path),
+        None => Ok(vec![]),
+    }
+}
+
+fn load_from(path: &Path) -> anyhow::Result<Vec<Record>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .filter(Record::plausible)
+            .collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(err) => Err(err).with_context(|| format!("reading {}", path.display())),
+    }
+}
+
+/// Ways of looking at the saved builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "tui", derive(clap::ValueEnum))]
+pub enum Report {
+    /// Newest first.
+    Recent,
+    /// Slowest first.
+    Longest,
+    /// How many builds there were each day, and how long they took.
+    Daily,
+    /// Per package: how many builds, and how long they took on average.
+    Packages,
+    /// The most memory first, with CPU time and processes too.
+    Resources,
+}
+
+/// A report laid out as text, for printing or showing in a table.
+#[derive(Debug)]
+pub struct Table {
+    pub header: Vec<&'static str>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Report {
+    pub const ALL: [Report; 5] = [
+        Report::Recent,
+        Report::Longest,
+        Report::Daily,
+        Report::Packages,
+        Report::Resources,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Report::Recent => "All finished builds",
+            Report::Longest => "Longest builds",
+            Report::Daily => "Builds per day",
+            Report::Packages => "Builds per package",
+            Report::Resources => "Hungriest builds",
+        }
+    }
+
+    /// Runs the report, with at most `limit` rows.
+    pub fn table(self, records: &[Record], limit: usize) -> Table {
+        match self {
+            Report::Recent => builds(records.iter().rev(), limit),
+            Report::Longest => {
+                let mut records: Vec<&Record> = records.iter().collect();
+                records.sort_by_key(|r| std::cmp::Reverse(r.duration()));
+                builds(records.into_iter(), limit)
+            }
+            Report::Daily => daily(records, limit),
+            Report::Packages => packages(records, limit),
+            Report::Resources => resources(records, limit),
+        }
+    }
+}
+
+fn builds<'a>(records: impl Iterator<Item = &'a Record>, limit: usize) -> Table {
+    let rows = records
+        .take(limit)
+        .map(|record| {
+            let (pname, version) = record.name();
+            vec![
+                pname.to_string(),
+                version.to_string(),
+                record.host.clone().unwrap_or_else(|| "local".to_string()),
+                record.ended().format("%Y-%m-%d %H:%M").to_string(),
+                show_duration(record.duration()),
+                show_duration(record.cpu_time()),
+            ]
+        })
+        .collect();
+    Table {
+        header: vec![
+            "Package",
+            "Version",
+            "Host",
+            "Finished at",
+            "Took",
+            "CPU time",
+        ],
+        rows,
+    }
+}
+
+fn daily(records: &[Record], limit: usize) -> Table {
+    let mut days: BTreeMap<NaiveDate, (usize, TimeDelta, TimeDelta)> = BTreeMap::new();
+    for record in records {
+        let day = record.ended().date_naive();
+        let (count, total, longest) = days.entry(day).or_default();
+        *count += 1;
+        *total += record.duration();
+        *longest = (*longest).max(record.duration());
+    }
+
+    let rows = days
+        .into_iter()
+        .rev()
+        .take(limit)
+        .map(|(day, (count, total, longest))| {
+            vec![
+                day.to_string(),
+                count.to_string(),
+                show_duration(total),
+                show_duration(longest),
+            ]
+        })
+        .collect();
+    Table {
+        header: vec!["Day", "Builds", "Total time", "Longest"],
+        rows,
+    }
+}
+
+fn packages(records: &[Record], limit: usize) -> Table {
+    let mut packages: BTreeMap<&str, (usize, TimeDelta, TimeDelta)> = BTreeMap::new();
+    for record in records {
+        let (count, total, longest) = packages.entry(record.name().0).or_default();
+        *count += 1;
+        *total += record.duration();
+        *longest = (*longest).max(record.duration());
+    }
+
+    // the ones that cost the most time first
+    let mut packages: Vec<_> = packages.into_iter().collect();
+    packages.sort_by_key(|(_, (_, total, _))| std::cmp::Reverse(*total));
+    let rows = packages
+        .into_iter()
+        .take(limit)
+        .map(|(pname, (count, total, longest))| {
+            vec![
+                pname.to_string(),
+                count.to_string(),
+                show_duration(total / count as i32),
+                show_duration(longest),
+                show_duration(total),
+            ]
+        })
+        .collect();
+    Table {
+        header: vec!["Package", "Builds", "Average", "Longest", "Total time"],
+        rows,
+    }
+}
+
+fn resources(records: &[Record], limit: usize) -> Table {
+    let mut records: Vec<&Record> = records.iter().collect();
+    // CPU time breaks ties, and orders the ones without memory
+    records.sort_by(|a, b| {
+        (b.peak_rss, b.cpu_seconds)
+            .partial_cmp(&(a.peak_rss, a.cpu_seconds))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let rows = records
+        .into_iter()
+        .take(limit)
+        .map(|record| {
+            let (pname, version) = record.name();
+            vec![
+                pname.to_string(),
+                version.to_string(),
+                record.host.clone().unwrap_or_else(|| "local".to_string()),
+                show_duration(record.duration()),
+                show_duration(record.cpu_time()),
+                record
+                    .peak_processes
+                    .map_or_else(|| "-".to_string(), |p| p.to_string()),
+                show_bytes(record.peak_rss),
+            ]
+        })
+        .collect();
+    Table {
+        header: vec![
+            "Package",
+            "Version",
+            "Host",
+            "Took",
+            "CPU time",
+            "Peak procs",
+            "Peak mem",
+        ],
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_skips_broken_lines() {
+        let valid = r#"{"derivation":"hello-2.12.1.drv","started":1700000000,"ended":1700000060,"cpuSeconds":42.5}"#;
+        let lines = [
+            // cut off mid-write
+            r#"{"derivation":"firefox-130.0.drv","started":17000"#,
+            // ended before it started
+            r#"{"derivation":"rustc-1.80.0.drv","started":1700000060,"ended":1700000000,"cpuSeconds":1.0}"#,
+            // more CPU time than fits in a TimeDelta
+            r#"{"derivation":"gcc-13.drv","started":1700000000,"ended":1700000060,"cpuSeconds":1e300}"#,
+            valid,
+        ];
+        let path = env::temp_dir().join(format!("ntop-archive-test-{}.jsonl", std::process::id()));
+        fs::write(&path, lines.join("\n")).unwrap();
+        let records = load_from(&path);
+        _ = fs::remove_file(&path);
+
+        let records = records.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].derivation, "hello-2.12.1.drv");
+        assert_eq!(records[0].duration(), TimeDelta::minutes(1));
+        assert_eq!(records[0].cpu_time(), TimeDelta::milliseconds(42_500));
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let path = env::temp_dir().join("ntop-archive-test-does-not-exist.jsonl");
+        assert!(load_from(&path).unwrap().is_empty());
+    }
+}
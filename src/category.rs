@@ -0,0 +1,124 @@
+//! What kind of program a build process is, so the process tree can color compilers, linkers and
+//! so on differently.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Compiler,
+    Linker,
+    Test,
+    Fetch,
+    Shell,
+}
+
+/// One `[[processes]]` entry of the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub category: Category,
+    /// Program names, like `cc1plus`. A `*` at the end matches anything starting with the rest.
+    pub programs: Vec<String>,
+}
+
+/// The built-in table, checked after the ones from the config.
+const BUILT_IN: &[(Category, &[&str])] = &[
+    (
+        Category::Compiler,
+        &[
+            "cc",
+            "c++",
+            "gcc",
+            "g++",
+            "clang",
+            "clang++",
+            "cc1",
+            "cc1plus",
+            "cc1obj",
+            "rustc",
+            "ghc",
+            "javac",
+            "kotlinc",
+            "scalac",
+            "swiftc",
+            "nvcc",
+            "zig",
+            "ocamlopt",
+            "ocamlc",
+            "ocamlfind",
+            "tsc",
+            "compile",
+            "as",
+        ],
+    ),
+    (
+        Category::Linker,
+        &[
+            "ld", "ld.bfd", "ld.gold", "ld.lld", "lld", "mold", "collect2", "link", "ar", "ranlib",
+        ],
+    ),
+    (
+        Category::Test,
+        &[
+            "pytest",
+            "ctest",
+            "cargo-nextest",
+            "prove",
+            "jest",
+            "vitest",
+            "tox",
+            "nosetests",
+        ],
+    ),
+    (
+        Category::Fetch,
+        &[
+            "curl",
+            "wget",
+            "git",
+            "hg",
+            "svn",
+            "rsync",
+            "nix-prefetch-*",
+        ],
+    ),
+    (Category::Shell, &["bash", "sh", "dash", "zsh", "fish"]),
+];
+
+#[derive(Debug, Default)]
+pub struct Categories {
+    /// The first one that matches a program wins.
+    rules: Vec<Rule>,
+}
+
+impl Categories {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Category of a process, from the file name of the program it runs.
+    pub fn of(&self, program: &str) -> Option<Category> {
+        let program = program.rsplit('/').next().unwrap_or(program);
+
+        let configured = self
+            .rules
+            .iter()
+            .map(|rule| (rule.category, rule.programs.as_slice()))
+            .find(|(_, patterns)| patterns.iter().any(|p| matches(p, program)));
+        if let Some((category, _)) = configured {
+            return Some(category);
+        }
+        BUILT_IN
+            .iter()
+            .find(|(_, patterns)| patterns.iter().any(|p| matches(p, program)))
+            .map(|(category, _)| *category)
+    }
+}
+
+fn matches(pattern: &str, program: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => program.starts_with(prefix),
+        None => program == pattern,
+    }
+}
@@ -0,0 +1,48 @@
+//! Build cgroups, which Nix puts builds in with the `cgroups` experimental feature and
+//! `use-cgroups = true`. They count everything the build ever ran, even processes that already
+//! exited, so they're more accurate than adding up the processes we can see.
+
+use serde::Serialize;
+use std::{fs, path::PathBuf};
+
+/// Where cgroup v2 is mounted. v1 isn't supported by Nix for builds anyway.
+const ROOT: &str = "/sys/fs/cgroup";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    /// CPU time used by the whole build so far, in seconds.
+    pub cpu_seconds: f64,
+    /// Memory charged to the build in bytes, including page cache.
+    pub memory: Option<u64>,
+    /// Number of processes (and threads) in the build.
+    pub pids: Option<u64>,
+}
+
+/// The cgroup directory of a process, if it's in one Nix made for a build.
+fn build_cgroup(pid: usize) -> Option<PathBuf> {
+    let cgroups = fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    // v2 is the line with the empty controller list: `0::/path`
+    let path = cgroups.lines().find_map(|line| line.strip_prefix("0::"))?;
+    // the daemon's own cgroup would count every build and the daemon too
+    path.split('/')
+        .any(|part| part.starts_with("nix-build-uid-"))
+        .then(|| PathBuf::from(format!("{ROOT}{path}")))
+}
+
+/// Reads the cgroup of a build by its main process.
+pub fn stats(main_pid: usize) -> Option<Stats> {
+    let dir = build_cgroup(main_pid)?;
+    let read = |file: &str| fs::read_to_string(dir.join(file)).ok();
+
+    let cpu_stat = read("cpu.stat")?;
+    let usage_usec: u64 = cpu_stat
+        .lines()
+        .find_map(|line| line.strip_prefix("usage_usec ")?.trim().parse().ok())?;
+
+    Some(Stats {
+        cpu_seconds: usage_usec as f64 / 1_000_000.0,
+        memory: read("memory.current").and_then(|s| s.trim().parse().ok()),
+        pids: read("pids.current").and_then(|s| s.trim().parse().ok()),
+    })
+}
@@ -0,0 +1,167 @@
+use crate::{
+    archive::Report,
+    print::Checks,
+    source::{self, DataSource, Local, Replay, Sources},
+    stats::parse_duration,
+};
+use chrono::TimeDelta;
+use clap::{Parser, Subcommand};
+use regex::Regex;
+use std::{net::SocketAddr, path::PathBuf};
+
+/// Btop for Nix.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Watch builds on another machine over SSH instead of this one. Can be given multiple
+    /// times, use `local` to include this machine as well.
+    #[arg(long = "host", value_name = "HOST")]
+    pub hosts: Vec<String>,
+
+    /// Store for Nix to use instead of the default one, passed on as `--store`. Stores on
+    /// another machine, like `ssh-ng://builder`, have their builds shown as remote.
+    #[arg(long, value_name = "URI", conflicts_with = "replay")]
+    pub store: Option<String>,
+
+    /// Only watch builds whose derivation matches this regex, like `linux|firmware`. The rest
+    /// are dropped before ntop looks any closer at them, so they don't cost anything.
+    #[arg(long, value_name = "REGEX")]
+    pub filter: Option<Regex>,
+
+    /// Play back `nix ps --json` outputs saved to a file, one per refresh, instead of watching
+    /// real builds.
+    #[arg(long, value_name = "FILE", conflicts_with = "hosts")]
+    pub replay: Option<PathBuf>,
+
+    /// Nix client log to read queued builds from, written with `--log-format internal-json`.
+    #[arg(long, value_name = "FILE")]
+    pub activity: Option<PathBuf>,
+
+    /// Answer JSON-RPC requests about the builds on this unix socket while the UI runs.
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Draw the UI with plain ASCII, for terminals and fonts that mangle box drawing characters.
+    #[arg(long)]
+    pub ascii: bool,
+
+    /// Print the active builds once and exit, instead of starting the UI.
+    #[arg(long)]
+    pub once: bool,
+
+    /// Print a line when a build starts or finishes instead of starting the UI, for screen
+    /// readers.
+    #[arg(long, conflicts_with_all = ["once", "headless"])]
+    pub accessible: bool,
+
+    /// Print the active builds as a table every refresh instead of starting the UI, one below
+    /// the other, for CI logs and serial consoles.
+    #[arg(long, conflicts_with_all = ["once", "headless", "accessible"])]
+    pub plain: bool,
+
+    /// Seconds between refreshes with --plain, --accessible and `ntop wait`.
+    #[arg(long, value_name = "SECONDS", default_value_t = 2)]
+    pub interval: u64,
+
+    /// Print JSON instead of a table.
+    #[arg(long, requires = "once")]
+    pub json: bool,
+
+    /// With --once, exit with an error if a build has been running longer than this, like `1h`
+    /// or `1h 30m`.
+    #[arg(long, value_name = "DURATION", requires = "once", value_parser = parse_duration)]
+    pub fail_if_longer_than: Option<TimeDelta>,
+
+    /// With --once, exit with an error if a build uses no CPU and does no I/O for this long,
+    /// `1m` if not given. ntop watches for that long before printing anything.
+    #[arg(
+        long,
+        value_name = "DURATION",
+        requires = "once",
+        num_args = 0..=1,
+        default_missing_value = "1m",
+        value_parser = parse_duration
+    )]
+    pub fail_if_stuck: Option<TimeDelta>,
+
+    /// File to export builds to, as JSON or CSV depending on the extension. With --once, the
+    /// builds are written there instead of printed. In the UI, press `e` to export.
+    #[arg(long, short, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Write debug logs to this file, since the UI has the terminal. `NTOP_LOG` picks what's
+    /// logged, like `RUST_LOG` does elsewhere.
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Serve Prometheus metrics on this address, like `127.0.0.1:9184`.
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_listen: Option<SocketAddr>,
+
+    /// Don't start the UI, only serve metrics.
+    #[arg(long, requires = "metrics_listen")]
+    pub headless: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Show builds from earlier runs of ntop, saved in `$XDG_STATE_HOME/ntop/builds.jsonl`.
+    History {
+        /// What to show.
+        #[arg(value_enum, default_value = "recent")]
+        report: Report,
+
+        /// Show at most this many rows.
+        #[arg(long, short = 'n', default_value_t = 20)]
+        limit: usize,
+
+        /// Print the saved builds as JSON instead of a table, ignoring the report.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Wait until the builds whose derivation contains PATTERN are done, or for one to start
+    /// and finish if none is running. Exits with an error if one of them failed.
+    Wait {
+        /// Part of the derivation, like `hello` or its whole path.
+        pattern: String,
+    },
+
+    /// Serve a web page with the active builds, plus their JSON at `/api/builds` and metrics at
+    /// `/metrics`, instead of starting the UI.
+    Serve {
+        /// Address to listen on.
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+    },
+}
+
+impl Cli {
+    pub fn checks(&self) -> Checks {
+        Checks {
+            longer_than: self.fail_if_longer_than,
+            stuck_for: self.fail_if_stuck,
+        }
+    }
+
+    pub fn sources(&self) -> anyhow::Result<Sources> {
+        let sources: Vec<Box<dyn DataSource>> = if let Some(path) = &self.replay {
+            vec![Box::new(Replay::open(path, self.filter.clone())?)]
+        } else if self.hosts.is_empty() {
+            vec![Box::new(Local {
+                store: self.store.clone(),
+                filter: self.filter.clone(),
+            })]
+        } else {
+            let store = self.store.as_deref();
+            self.hosts
+                .iter()
+                .map(|h| source::from_host(h, store, self.filter.as_ref()))
+                .collect()
+        };
+        Ok(sources.into())
+    }
+}
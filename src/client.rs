@@ -0,0 +1,191 @@
+//! Finding out which client command a build was started by, like `nixos-rebuild switch` or
+//! `nix build .#foo`, and whose it is.
+
+use crate::proc;
+use std::{collections::HashMap, path::PathBuf, process::Command};
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub pid: usize,
+    pub user: Option<String>,
+    pub argv: Vec<String>,
+    pub cwd: Option<PathBuf>,
+}
+
+impl Client {
+    /// The flake reference the client was given, if it looks like it was given one.
+    pub fn flake(&self) -> Option<&str> {
+        let mut args = self.argv.iter().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--flake" {
+                return args.next().map(String::as_str);
+            }
+            if arg.contains('#') && !arg.starts_with('-') {
+                return Some(arg);
+            }
+        }
+        None
+    }
+
+    /// What the client was asked to build, as an installable `nix why-depends` understands.
+    /// Only works for flakes, since that's what can be evaluated again without guessing.
+    pub fn target(&self) -> Option<String> {
+        let flake = self.flake()?;
+        let program = program(&self.argv)?.trim_end_matches("-wrapped");
+        let (reference, name) = flake.split_once('#').unwrap_or((flake, ""));
+        let reference = if reference.is_empty() { "." } else { reference };
+        // they default to the host name
+        let name = match name {
+            "" => proc::hostname()?,
+            name => name.to_string(),
+        };
+
+        // the rebuild tools take a configuration name, not an attribute
+        Some(match program {
+            "nixos-rebuild" | "nixos-rebuild-ng" => {
+                format!("{reference}#nixosConfigurations.{name}.config.system.build.toplevel")
+            }
+            "darwin-rebuild" => format!("{reference}#darwinConfigurations.{name}.system"),
+            "home-manager" => {
+                format!("{reference}#homeConfigurations.{name}.activationPackage")
+            }
+            _ => flake.to_string(),
+        })
+    }
+
+    /// The command without the interpreter and the path to the program, for showing.
+    pub fn command(&self) -> String {
+        let mut argv = self.argv.as_slice();
+        if let Some(first) = argv.first()
+            && INTERPRETERS.contains(&file_name(first))
+            && let Some(script) = argv.iter().position(|a| !a.starts_with('-') && a != first)
+        {
+            argv = &argv[script..];
+        }
+
+        let Some((program, args)) = argv.split_first() else {
+            return String::new();
+        };
+        let mut command = file_name(program).to_string();
+        for arg in args {
+            command.push(' ');
+            command.push_str(arg);
+        }
+        command
+    }
+}
+
+/// Commands that start builds, directly or by running one of the others.
+const CLIENTS: &[&str] = &[
+    "nix",
+    "nix-build",
+    "nix-shell",
+    "nix-env",
+    "nix-store",
+    "nix-instantiate",
+    "nixos-rebuild",
+    "nixos-rebuild-ng",
+    "darwin-rebuild",
+    "home-manager",
+    "nh",
+    "colmena",
+    "deploy",
+];
+
+/// Interpreters that wrapper scripts like `nixos-rebuild` run under.
+const INTERPRETERS: &[&str] = &["bash", "sh", "python3", "python", "perl"];
+
+/// Name of the program a process is running, looking through interpreters.
+fn program(argv: &[String]) -> Option<&str> {
+    let program = file_name(argv.first()?);
+    if INTERPRETERS.contains(&program) {
+        // skip interpreter flags like `bash -e script`
+        let script = argv.iter().skip(1).find(|a| !a.starts_with('-'))?;
+        Some(file_name(script))
+    } else {
+        Some(program)
+    }
+}
+
+/// The last path component, without the dot nixpkgs wrappers put in front.
+fn file_name(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.trim_start_matches('.')
+}
+
+fn is_client(pid: usize) -> bool {
+    proc::argv(pid)
+        .as_deref()
+        .and_then(program)
+        .is_some_and(|p| CLIENTS.contains(&p.trim_end_matches("-wrapped")))
+}
+
+/// Goes up from a client to the outermost client, so that `nix build` run by `nixos-rebuild`
+/// shows up as `nixos-rebuild`.
+fn outermost(pid: usize) -> usize {
+    let mut pid = pid;
+    while let Some(parent) = proc::parent(pid)
+        && parent > 1
+        && is_client(parent)
+    {
+        pid = parent;
+    }
+    pid
+}
+
+/// Finds the client of the Nix process in charge of a build. Without a daemon that's one of its
+/// ancestors (or itself), with one it's on the other end of the daemon socket.
+pub fn find(nix_pid: usize) -> Option<Client> {
+    let mut pid = nix_pid;
+    let client = loop {
+        let argv = proc::argv(pid)?;
+        if program(&argv) == Some("nix-daemon") {
+            break pid_in_title(&argv).or_else(|| peer(pid))?;
+        }
+        if is_client(pid) {
+            break pid;
+        }
+        pid = proc::parent(pid).filter(|p| *p > 1)?;
+    };
+
+    let pid = outermost(client);
+    Some(Client {
+        pid,
+        user: proc::uid(pid).and_then(proc::user_name),
+        argv: proc::argv(pid)?,
+        cwd: proc::cwd(pid),
+    })
+}
+
+/// Daemon workers write the PID of their client over their first argument (as much of it as
+/// fits), so there's no need to be root to find it. It's only trusted if it's a client.
+fn pid_in_title(argv: &[String]) -> Option<usize> {
+    let pid = argv.get(1)?.parse().ok()?;
+    is_client(pid).then_some(pid)
+}
+
+/// The process on the other end of a daemon worker's socket, from `ss`, since the kernel only
+/// tells about socket peers over netlink. Needs root to see other users' processes.
+fn peer(daemon: usize) -> Option<usize> {
+    let output = Command::new("ss").arg("-xpn").output().ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    // inode -> (peer inode, pids)
+    let mut sockets: HashMap<&str, (&str, Vec<usize>)> = HashMap::new();
+    for line in output.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [_, _, _, _, _, inode, _, peer, ..] = fields[..] else {
+            continue;
+        };
+        let pids = fields[8..]
+            .iter()
+            .flat_map(|f| f.split(','))
+            .filter_map(|f| f.strip_prefix("pid=")?.parse().ok())
+            .collect();
+        sockets.insert(inode, (peer, pids));
+    }
+
+    let (peer, _) = sockets.values().find(|(_, pids)| pids.contains(&daemon))?;
+    let (_, pids) = sockets.get(peer)?;
+    pids.first().copied()
+}
@@ -0,0 +1,139 @@
+//! Columns of the builds table, picked and ordered in the config.
+
+use ratatui::layout::Constraint;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Pid,
+    /// Only shown when watching more than one machine.
+    Host,
+    /// Who started the build.
+    User,
+    Client,
+    Package,
+    Version,
+    /// The `nixbld` user (or auto-allocated UID) the build runs as.
+    #[serde(rename = "builduser")]
+    BuildUser,
+    /// Which stdenv phase it looks like the build is in.
+    Phase,
+    Cpu,
+    /// CPU time used so far, which stays put while a build waits on something.
+    #[serde(rename = "cputime")]
+    CpuTime,
+    /// CPU usage over the last few minutes.
+    #[serde(rename = "cpugraph")]
+    CpuGraph,
+    /// How many processes are running in the build right now.
+    Processes,
+    Memory,
+    /// Bytes read from storage per second.
+    Read,
+    /// Bytes written to storage per second.
+    Write,
+    /// Bytes received over the network per second, for sandboxed builds.
+    Download,
+    /// Bytes sent over the network per second.
+    Upload,
+    Time,
+    /// How much longer the build should take, from how long it took before.
+    Eta,
+}
+
+impl Column {
+    pub const ALL: [Column; 19] = [
+        Column::Pid,
+        Column::Host,
+        Column::User,
+        Column::Client,
+        Column::Package,
+        Column::Version,
+        Column::BuildUser,
+        Column::Phase,
+        Column::Cpu,
+        Column::CpuTime,
+        Column::CpuGraph,
+        Column::Processes,
+        Column::Memory,
+        Column::Read,
+        Column::Write,
+        Column::Download,
+        Column::Upload,
+        Column::Time,
+        Column::Eta,
+    ];
+
+    /// What's there unless the config says otherwise.
+    pub const DEFAULT: [Column; 9] = [
+        Column::Pid,
+        Column::Host,
+        Column::Package,
+        Column::Version,
+        Column::Phase,
+        Column::Cpu,
+        Column::CpuTime,
+        Column::Memory,
+        Column::Time,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Pid => "PID",
+            Column::Host => "Host",
+            Column::User => "User",
+            Column::Client => "Client",
+            Column::Package => "Package",
+            Column::Version => "Version",
+            Column::BuildUser => "Build user",
+            Column::Phase => "Phase",
+            Column::Cpu => "CPU",
+            Column::CpuTime => "CPU time",
+            Column::CpuGraph => "CPU history",
+            Column::Processes => "Procs",
+            Column::Memory => "Mem",
+            Column::Read => "Read",
+            Column::Write => "Write",
+            Column::Download => "Down",
+            Column::Upload => "Up",
+            Column::Time => "Time",
+            Column::Eta => "ETA",
+        }
+    }
+
+    /// Width when the config doesn't set one. Package and version share what's left.
+    pub fn default_width(self) -> Constraint {
+        match self {
+            Column::Pid => Constraint::Length(9),
+            Column::Host => Constraint::Length(16),
+            Column::User | Column::BuildUser => Constraint::Length(10),
+            Column::Client => Constraint::Length(24),
+            Column::Package => Constraint::Fill(4),
+            Column::Version => Constraint::Fill(1),
+            Column::Phase => Constraint::Length(9),
+            Column::Cpu | Column::Memory | Column::Processes => Constraint::Length(6),
+            Column::CpuTime | Column::Time | Column::Eta => Constraint::Length(10),
+            Column::Read | Column::Write | Column::Download | Column::Upload => {
+                Constraint::Length(8)
+            }
+            Column::CpuGraph => Constraint::Length(20),
+        }
+    }
+
+    /// Numbers go on the right.
+    pub fn right_aligned(self) -> bool {
+        matches!(
+            self,
+            Column::Pid
+                | Column::Cpu
+                | Column::CpuTime
+                | Column::Processes
+                | Column::Memory
+                | Column::Read
+                | Column::Write
+                | Column::Download
+                | Column::Upload
+        )
+    }
+}
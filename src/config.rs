@@ -0,0 +1,128 @@
+use crate::{
+    alerts::Rule,
+    app::sort::Sort,
+    category,
+    columns::Column,
+    notify::Notifications,
+    theme::{Colors, Theme},
+};
+use anyhow::Context;
+use serde::Deserialize;
+use std::{collections::HashMap, env, fs, io, path::PathBuf};
+
+/// Contents of `$XDG_CONFIG_HOME/ntop/config.toml`. Everything is optional.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Percentage of the screen taken by the builds table.
+    pub split: u16,
+    /// Whether the details go under the builds or next to them.
+    pub direction: LayoutDirection,
+    /// Name of the built-in theme to use.
+    pub theme: String,
+    /// Overrides for individual colors of the theme.
+    pub colors: Colors,
+    /// Whether to show the Nix store disk usage panel on startup.
+    pub disk: bool,
+    /// Whether to show the details of the selected build next to the builds table.
+    pub details: bool,
+    /// Whether to start with builds of the same package grouped together.
+    pub grouped: bool,
+    /// How the builds are sorted at first.
+    pub sort: Sort,
+    /// Columns of the builds table, in order.
+    pub columns: Vec<Column>,
+    /// Widths of columns in characters, for the ones that shouldn't use the default.
+    pub column_widths: HashMap<Column, u16>,
+    /// How many bytes garbage collection started from the UI frees at most.
+    pub gc_max: u64,
+    /// Minutes of build counts shown in the chart on the system tab.
+    pub chart_minutes: u32,
+    /// Refreshes in a row without CPU or I/O before a build is highlighted as idle, twice that
+    /// and it's stuck. 0 turns it off.
+    pub stuck_after: u32,
+    /// How long builds may take, the first rule that matches a build applies.
+    pub alerts: Vec<Rule>,
+    /// How many times slower to refresh while the terminal isn't focused, 1 to not slow down.
+    pub unfocused_slowdown: u32,
+    pub notifications: Notifications,
+    /// Extra programs to color in the process tree, checked before the built-in ones.
+    pub processes: Vec<category::Rule>,
+    /// Derivations to leave out, by name with `*` matching anything.
+    pub exclude: Vec<String>,
+    /// Whether to leave out fixed-output derivations, the fetches.
+    pub exclude_fetches: bool,
+    /// Whether to show the bar with key hints at the bottom.
+    pub hints: bool,
+    /// Whether to draw with plain ASCII instead of box drawing characters and arrows.
+    pub ascii: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutDirection {
+    /// Next to each other on wide terminals, otherwise stacked.
+    #[default]
+    Auto,
+    /// Builds above the details.
+    Vertical,
+    /// Builds left of the details.
+    Horizontal,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            split: 40,
+            direction: LayoutDirection::Auto,
+            theme: "dark".to_string(),
+            colors: Colors::default(),
+            disk: false,
+            details: true,
+            grouped: false,
+            sort: Sort::default(),
+            columns: Column::DEFAULT.to_vec(),
+            column_widths: HashMap::new(),
+            gc_max: 10 * 1024 * 1024 * 1024,
+            chart_minutes: 30,
+            stuck_after: 30,
+            alerts: vec![],
+            unfocused_slowdown: 10,
+            notifications: Notifications::default(),
+            processes: vec![],
+            exclude: vec![],
+            exclude_fetches: false,
+            hints: true,
+            ascii: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("ntop").join("config.toml"))
+    }
+
+    pub fn theme(&self) -> anyhow::Result<Theme> {
+        Theme::new(&self.theme, &self.colors)
+    }
+
+    /// Loads the config file, or the defaults if there isn't one.
+    pub fn load() -> anyhow::Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+        };
+
+        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+}
@@ -0,0 +1,109 @@
+//! Disk usage of the Nix store, since running out of space mid-build is no fun.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    ffi::CString,
+    fs, io,
+    os::unix::fs::MetadataExt,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// How far back the growth rate looks.
+const GROWTH_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Space on the filesystem the store is on, in bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Filesystem {
+    pub total: u64,
+    pub used: u64,
+    /// What's left for unprivileged users, which is what the builds see.
+    pub available: u64,
+}
+
+impl Filesystem {
+    pub fn of(path: &str) -> io::Result<Self> {
+        let path = CString::new(path)?;
+        // SAFETY: statvfs only writes to the struct we give it
+        let stat = unsafe {
+            let mut stat = std::mem::zeroed::<libc::statvfs>();
+            if libc::statvfs(path.as_ptr(), &mut stat) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            stat
+        };
+
+        let block = stat.f_frsize as u64;
+        let total = stat.f_blocks as u64 * block;
+        Ok(Self {
+            total,
+            used: total - stat.f_bfree as u64 * block,
+            available: stat.f_bavail as u64 * block,
+        })
+    }
+}
+
+/// Size of everything under a path, counting hard links once, like `du`. Slow on big stores, so
+/// don't call it on the event loop.
+pub fn size(path: &Path) -> u64 {
+    let mut seen = HashSet::new();
+    size_inner(path, &mut seen)
+}
+
+fn size_inner(path: &Path, seen: &mut HashSet<(u64, u64)>) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    // the store is deduplicated with hard links a lot
+    if metadata.nlink() > 1 && !seen.insert((metadata.dev(), metadata.ino())) {
+        return 0;
+    }
+
+    let own = metadata.blocks() * 512;
+    if !metadata.is_dir() {
+        return own;
+    }
+
+    let children: u64 = fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| size_inner(&entry.path(), seen))
+                .sum()
+        })
+        .unwrap_or(0);
+    own + children
+}
+
+/// What the disk panel shows.
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    pub filesystem: Option<Filesystem>,
+    /// Size of the store itself, `None` until the first time it's been added up.
+    pub store: Option<u64>,
+    /// Used space on the filesystem over time, for the growth rate.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl DiskUsage {
+    pub fn update(&mut self, filesystem: Filesystem) {
+        let now = Instant::now();
+        self.filesystem = Some(filesystem);
+        self.samples.push_back((now, filesystem.used));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > GROWTH_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// How fast the used space grows, in bytes per hour. Negative after garbage collection.
+    pub fn growth(&self) -> Option<f64> {
+        let (first_at, first) = self.samples.front()?;
+        let (last_at, last) = self.samples.back()?;
+        let elapsed = last_at.duration_since(*first_at).as_secs_f64();
+        (elapsed > 0.0).then(|| (*last as f64 - *first as f64) / elapsed * 3600.0)
+    }
+}
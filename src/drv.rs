@@ -0,0 +1,411 @@
+//! Derivation metadata from `nix derivation show`, for the stuff `nix ps` doesn't tell.
+
+use crate::{proc, store};
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    process::Stdio,
+};
+use tokio::process::Command;
+
+#[derive(Debug, Clone)]
+pub struct Info {
+    pub system: String,
+    pub builder: String,
+    pub args: Vec<String>,
+    /// Output names and their store paths. Content-addressed outputs don't have a path yet.
+    pub outputs: Vec<(String, Option<String>)>,
+    pub pname: Option<String>,
+    pub version: Option<String>,
+}
+
+/// How far along an output of a running build is, with how many bytes are there so far.
+#[derive(Debug, Clone, Copy)]
+pub enum OutputState {
+    /// Nothing written yet.
+    Missing,
+    /// Being written in the build's sandbox, it's moved to the store when the build is done.
+    Sandbox(u64),
+    /// At its final path: done, or being written by a build without a sandbox.
+    Store(u64),
+}
+
+/// Looks for an output of a derivation on this machine. Nix versions that keep the sandbox next
+/// to the derivation have it at `<drv>.chroot/root`, which only root can look into.
+pub fn output_state(derivation: &str, path: &str, sizes: &mut proc::DiskUsage) -> OutputState {
+    let path = Path::new(path);
+    if fs::symlink_metadata(path).is_ok() {
+        return OutputState::Store(sizes.get(path));
+    }
+
+    let sandbox = Path::new(store::store_dir())
+        .join(format!("{derivation}.chroot/root"))
+        .join(path.strip_prefix("/").unwrap_or(path));
+    if fs::symlink_metadata(&sandbox).is_ok() {
+        return OutputState::Sandbox(sizes.get(&sandbox));
+    }
+    OutputState::Missing
+}
+
+/// What's needed from a derivation without asking Nix, read straight from its file.
+#[derive(Debug, Clone)]
+pub struct Local {
+    pub system: String,
+    /// Store paths of the outputs, empty for content-addressed ones.
+    pub outputs: Vec<String>,
+    /// Whether the output hash is known up front, which is what fetchers do.
+    pub fixed_output: bool,
+}
+
+/// Reads a derivation from the local store. Way quicker than `nix derivation show`, but only
+/// works for derivations on this machine.
+pub fn read_local(derivation: &str) -> Option<Local> {
+    parse_local(&fs::read_to_string(store::drv_path(derivation)).ok()?)
+}
+
+fn parse_local(text: &str) -> Option<Local> {
+    // Derive(outputs, inputDrvs, inputSrcs, system, builder, args, env)
+    let ATerm::List(fields) = ATerm::parse(text.strip_prefix("Derive")?)?.0 else {
+        return None;
+    };
+    let [ATerm::List(outputs), _, _, ATerm::Str(system), ..] = &fields[..] else {
+        return None;
+    };
+
+    // each output is (name, path, hash algorithm, hash)
+    let fixed_output = outputs.iter().any(|output| match output {
+        ATerm::List(output) => matches!(output.get(3), Some(ATerm::Str(hash)) if !hash.is_empty()),
+        _ => false,
+    });
+    let outputs = outputs
+        .iter()
+        .filter_map(|output| match output {
+            ATerm::List(output) => match output.get(1) {
+                Some(ATerm::Str(path)) if !path.is_empty() => Some(path.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    Some(Local {
+        system: system.clone(),
+        outputs,
+        fixed_output,
+    })
+}
+
+/// The bits of the ATerm format derivations are stored in. Lists and tuples are the same here.
+enum ATerm {
+    Str(String),
+    List(Vec<ATerm>),
+}
+
+impl Info {
+    /// The builder with its arguments, like a shell would take it.
+    pub fn command(&self) -> String {
+        let mut command = self.builder.clone();
+        for arg in &self.args {
+            command.push(' ');
+            // `-c` scripts and such, on one line
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                command.push_str(&format!("'{}'", arg.replace('\n', "\\n")));
+            } else {
+                command.push_str(arg);
+            }
+        }
+        command
+    }
+}
+
+impl ATerm {
+    /// Parses one term from the start of the text, returning it and the rest.
+    fn parse(text: &str) -> Option<(Self, &str)> {
+        let mut chars = text.char_indices();
+        match chars.next()? {
+            (_, '"') => {
+                let mut value = String::new();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '"' => return Some((ATerm::Str(value), &text[i + 1..])),
+                        '\\' => match chars.next()?.1 {
+                            'n' => value.push('\n'),
+                            'r' => value.push('\r'),
+                            't' => value.push('\t'),
+                            c => value.push(c),
+                        },
+                        c => value.push(c),
+                    }
+                }
+                None
+            }
+            (_, open @ ('[' | '(')) => {
+                let close = if open == '[' { ']' } else { ')' };
+                let mut items = vec![];
+                let mut rest = &text[1..];
+                loop {
+                    if let Some(rest) = rest.strip_prefix(close) {
+                        return Some((ATerm::List(items), rest));
+                    }
+                    let (item, after) = ATerm::parse(rest)?;
+                    items.push(item);
+                    rest = after.strip_prefix(',').unwrap_or(after);
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawDerivation {
+    system: String,
+    builder: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    outputs: BTreeMap<String, RawOutput>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct RawOutput {
+    path: Option<String>,
+}
+
+/// Runs `nix derivation show` on a derivation, with `nix` being a command that runs Nix on the
+/// right machine.
+pub async fn show(nix: Command, derivation: &str) -> anyhow::Result<Info> {
+    let json = run(nix, derivation).await?;
+    parse(&json).context("failed to parse nix derivation show output")
+}
+
+/// Like [`show`], but everything in the derivation, as lines of text to look at.
+pub async fn show_all(nix: Command, derivation: &str) -> anyhow::Result<Vec<String>> {
+    let json = run(nix, derivation).await?;
+    let derivation = only_derivation(serde_json::from_slice(&json)?)
+        .context("failed to parse nix derivation show output")?;
+    Ok(format(&derivation))
+}
+
+async fn run(mut nix: Command, derivation: &str) -> anyhow::Result<Vec<u8>> {
+    nix.arg("derivation")
+        .arg("show")
+        .arg(store::drv_path(derivation))
+        .stdin(Stdio::null());
+    tracing::debug!(command = ?nix.as_std(), "running nix derivation show");
+    let cmd = nix.output().await.context("failed to run nix")?;
+
+    if !cmd.status.success() {
+        let stderr = String::from_utf8_lossy(&cmd.stderr);
+        anyhow::bail!(
+            "nix derivation show exited with {}: {}",
+            cmd.status,
+            stderr.trim()
+        );
+    }
+
+    Ok(cmd.stdout)
+}
+
+/// Digs the one derivation we asked for out of the output.
+fn only_derivation(mut value: Value) -> anyhow::Result<Value> {
+    // newer versions wrap it in {"derivations": ..., "version": ...}
+    if let Some(derivations) = value.get_mut("derivations") {
+        value = derivations.take();
+    }
+
+    // keyed by the derivation path, and we only asked for one
+    let Value::Object(derivations) = value else {
+        anyhow::bail!("expected an object");
+    };
+    let Some((_, derivation)) = derivations.into_iter().next() else {
+        anyhow::bail!("no derivations in output");
+    };
+    Ok(derivation)
+}
+
+fn parse(json: &[u8]) -> anyhow::Result<Info> {
+    let derivation = only_derivation(serde_json::from_slice(json)?)?;
+    let mut raw: RawDerivation = serde_json::from_value(derivation)?;
+    let mut outputs: Vec<_> = raw.outputs.into_iter().collect();
+    // in the order the derivation lists them (`out` usually first), not alphabetically
+    if let Some(order) = raw.env.get("outputs") {
+        let order: Vec<&str> = order.split_whitespace().collect();
+        outputs
+            .sort_by_key(|(name, _)| order.iter().position(|o| o == name).unwrap_or(order.len()));
+    }
+    let outputs = outputs
+        .into_iter()
+        .map(|(name, output)| {
+            // newer versions leave out the store dir
+            let path = output.path.map(|path| store::drv_path(&path));
+            (name, path)
+        })
+        .collect();
+
+    Ok(Info {
+        system: raw.system,
+        builder: raw.builder,
+        args: raw.args,
+        outputs,
+        pname: raw.env.remove("pname"),
+        version: raw.env.remove("version"),
+    })
+}
+
+/// Lays out a derivation as an indented list. Multi-line values (like builder scripts) get their
+/// own lines.
+fn format(derivation: &Value) -> Vec<String> {
+    let mut lines = vec![];
+    let string = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    };
+    let mut section = |title: &str, entries: Vec<(Option<String>, String)>| {
+        if entries.is_empty() {
+            return;
+        }
+        lines.push(format!("{title}:"));
+        for (key, value) in entries {
+            let mut value_lines = value.lines();
+            let first = value_lines.next().unwrap_or_default();
+            match key {
+                Some(key) => lines.push(format!("  {key} = {first}")),
+                None => lines.push(format!("  {first}")),
+            }
+            lines.extend(value_lines.map(|line| format!("      {line}")));
+        }
+        lines.push(String::new());
+    };
+
+    let field = |name: &str| derivation.get(name).map(string).unwrap_or_default();
+    section(
+        "Derivation",
+        vec![
+            (Some("system".to_string()), field("system")),
+            (Some("builder".to_string()), field("builder")),
+        ],
+    );
+
+    let array = |value: Option<&Value>| -> Vec<(Option<String>, String)> {
+        match value {
+            Some(Value::Array(items)) => items.iter().map(|v| (None, string(v))).collect(),
+            // input derivations are keyed by path
+            Some(Value::Object(items)) => items.keys().map(|k| (None, k.clone())).collect(),
+            _ => vec![],
+        }
+    };
+    section("Arguments", array(derivation.get("args")));
+
+    let outputs = match derivation.get("outputs") {
+        Some(Value::Object(outputs)) => outputs
+            .iter()
+            .map(|(name, output)| {
+                let path = output.get("path").map(string).unwrap_or_default();
+                (Some(name.clone()), path)
+            })
+            .collect(),
+        _ => vec![],
+    };
+    section("Outputs", outputs);
+
+    // newer versions put both under "inputs"
+    let inputs = derivation.get("inputs");
+    let drvs = derivation.get("inputDrvs").or_else(|| inputs?.get("drvs"));
+    let srcs = derivation.get("inputSrcs").or_else(|| inputs?.get("srcs"));
+    section("Input derivations", array(drvs));
+    section("Input sources", array(srcs));
+
+    let env = match derivation.get("env") {
+        Some(Value::Object(env)) => env
+            .iter()
+            .map(|(name, value)| (Some(name.clone()), string(value)))
+            .collect(),
+        _ => vec![],
+    };
+    section("Environment", env);
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local() {
+        let text = concat!(
+            r#"Derive([("out","/nix/store/aaa-hello-2.12.1","","")],"#,
+            r#"[("/nix/store/bbb-bash.drv",["out"])],["/nix/store/ccc-builder.sh"],"#,
+            r#""x86_64-linux","/nix/store/ddd-bash/bin/bash",["-e","/nix/store/ccc-builder.sh"],"#,
+            r#"[("name","hello"),("script","echo \"hi\"\n\\done")])"#,
+        );
+        let drv = parse_local(text).unwrap();
+        assert_eq!(drv.system, "x86_64-linux");
+        assert_eq!(drv.outputs, ["/nix/store/aaa-hello-2.12.1"]);
+        assert!(!drv.fixed_output);
+    }
+
+    #[test]
+    fn local_fixed_and_content_addressed() {
+        let fixed = r#"Derive([("out","/nix/store/eee-src.tar.gz","sha256","abcd")],[],[],"x86_64-linux","builtin:fetchurl",[],[])"#;
+        assert!(parse_local(fixed).unwrap().fixed_output);
+        let floating =
+            r#"Derive([("out","","r:sha256","")],[],[],"aarch64-linux","/bin/sh",[],[])"#;
+        let drv = parse_local(floating).unwrap();
+        assert!(drv.outputs.is_empty());
+        assert!(!drv.fixed_output);
+    }
+
+    #[test]
+    fn local_garbage() {
+        assert!(parse_local("").is_none());
+        assert!(parse_local("Derive([(\"out\"").is_none());
+        assert!(parse_local(r#"Derive(["unterminated)"#).is_none());
+        assert!(parse_local(r#"Derive([],[],[])"#).is_none());
+    }
+
+    #[test]
+    fn aterm_escapes() {
+        let (ATerm::Str(value), rest) = ATerm::parse(r#""a\"b\\c\n\td",rest"#).unwrap() else {
+            panic!("not a string");
+        };
+        assert_eq!(value, "a\"b\\c\n\td");
+        assert_eq!(rest, ",rest");
+    }
+
+    #[test]
+    fn show_output() {
+        let json = br#"{"/nix/store/x-hello.drv": {
+            "system": "x86_64-linux", "builder": "/bin/sh", "args": ["-e", "b.sh"],
+            "outputs": {"dev": {"path": "/nix/store/y-hello-dev"}, "out": {"path": "/nix/store/z-hello"}},
+            "env": {"outputs": "out dev", "pname": "hello", "version": "2.12.1"}
+        }}"#;
+        let info = parse(json).unwrap();
+        assert_eq!(info.command(), "/bin/sh -e b.sh");
+        let names: Vec<&str> = info.outputs.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["out", "dev"]);
+        assert_eq!(info.outputs[0].1.as_deref(), Some("/nix/store/z-hello"));
+        assert_eq!(info.pname.as_deref(), Some("hello"));
+        assert_eq!(info.version.as_deref(), Some("2.12.1"));
+    }
+
+    #[test]
+    fn show_output_wrapped() {
+        // newer versions: wrapped, with paths relative to the store dir
+        let json = br#"{"version": 3, "derivations": {"x-hello.drv": {
+            "system": "x86_64-linux", "builder": "/bin/sh",
+            "outputs": {"out": {"path": "z-hello"}, "lib": {}}, "env": {}
+        }}}"#;
+        let info = parse(json).unwrap();
+        assert!(info.args.is_empty());
+        let out = format!("{}/z-hello", store::store_dir());
+        assert!(info.outputs.contains(&("out".to_string(), Some(out))));
+        assert!(info.outputs.contains(&("lib".to_string(), None)));
+    }
+}
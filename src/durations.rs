@@ -0,0 +1,126 @@
+//! How long builds of each package took before, kept across runs, to tell how long the running
+//! ones usually take.
+
+use crate::archive;
+use anyhow::Context;
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// How many durations to keep per package, older ones are dropped.
+const MAX_SAMPLES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Key {
+    pub pname: String,
+    pub version: String,
+    pub system: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    key: Key,
+    /// Oldest first.
+    seconds: Vec<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Durations {
+    /// Where they're saved, `None` to not save them at all.
+    path: Option<PathBuf>,
+    samples: HashMap<Key, Vec<i64>>,
+}
+
+impl Durations {
+    /// `$XDG_STATE_HOME/ntop/durations.json`.
+    pub fn path() -> Option<PathBuf> {
+        Some(archive::state_dir()?.join("durations.json"))
+    }
+
+    /// Loads the saved durations, if there are any.
+    pub fn load() -> anyhow::Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let samples = match fs::read(&path) {
+            Ok(json) => serde_json::from_slice::<Vec<Entry>>(&json)
+                .with_context(|| format!("parsing {}", path.display()))?
+                .into_iter()
+                .map(|entry| (entry.key, entry.seconds))
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+        };
+        Ok(Self {
+            path: Some(path),
+            samples,
+        })
+    }
+
+    /// Remembers how long a build took and saves right away, so nothing is lost when ntop gets
+    /// killed.
+    pub fn record(&mut self, key: Key, duration: TimeDelta) -> anyhow::Result<()> {
+        let samples = self.samples.entry(key).or_default();
+        samples.push(duration.num_seconds());
+        if samples.len() > MAX_SAMPLES {
+            samples.remove(0);
+        }
+
+        match &self.path {
+            Some(path) => self.save(path),
+            None => Ok(()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut entries: Vec<Entry> = self
+            .samples
+            .iter()
+            .map(|(key, seconds)| Entry {
+                key: key.clone(),
+                seconds: seconds.clone(),
+            })
+            .collect();
+        // keeps the file diffable
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        // write and rename, so another ntop never reads half a file
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec(&entries)?)
+            .with_context(|| format!("writing {}", temporary.display()))?;
+        fs::rename(&temporary, path).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// How long a build usually takes (the median of the last few) and out of how many. Without
+    /// a system, any system will do.
+    pub fn usual(
+        &self,
+        pname: &str,
+        version: &str,
+        system: Option<&str>,
+    ) -> Option<(TimeDelta, usize)> {
+        let samples = self
+            .samples
+            .iter()
+            .find(|(key, _)| {
+                key.pname == pname
+                    && key.version == version
+                    && system.is_none_or(|system| key.system == system)
+            })?
+            .1;
+
+        let mut sorted = samples.clone();
+        sorted.sort();
+        let median = *sorted.get(sorted.len() / 2)?;
+        Some((TimeDelta::seconds(median), samples.len()))
+    }
+}
@@ -0,0 +1,33 @@
+//! Filling in what `nix ps` leaves out (memory, I/O and so on) for builds on this machine, from
+//! whatever the OS has to offer.
+
+use crate::ps;
+
+#[cfg(target_os = "macos")]
+mod darwin;
+#[cfg(target_os = "linux")]
+mod linux;
+
+/// Adds details about local processes to builds.
+pub trait Enricher: Send + Sync {
+    fn enrich(&self, build: &mut ps::Build);
+}
+
+/// The enricher for the OS we're running on.
+pub fn local() -> &'static dyn Enricher {
+    #[cfg(target_os = "linux")]
+    return &linux::Procfs;
+    #[cfg(target_os = "macos")]
+    return &darwin::Libproc;
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    return &Nothing;
+}
+
+/// For everything else, builds are shown with just what `nix ps` says.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+struct Nothing;
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+impl Enricher for Nothing {
+    fn enrich(&self, _build: &mut ps::Build) {}
+}
@@ -0,0 +1,85 @@
+//! There's no `/proc` on macOS, so this asks the kernel through libproc instead.
+
+use super::Enricher;
+use crate::ps;
+use std::{mem, sync::OnceLock};
+
+/// Memory, CPU times, I/O and start times from libproc.
+#[derive(Debug)]
+pub struct Libproc;
+
+impl Enricher for Libproc {
+    fn enrich(&self, build: &mut ps::Build) {
+        if build.start_time.is_none() {
+            build.start_time = start_time(build.main_pid);
+        }
+
+        for process in &mut build.processes {
+            let Some(usage) = rusage(process.pid) else {
+                continue;
+            };
+            // whatever nix said is as good, but it might not have said anything
+            process.utime.get_or_insert(seconds(usage.ri_user_time));
+            process.stime.get_or_insert(seconds(usage.ri_system_time));
+            process.rss = Some(usage.ri_resident_size);
+            process.io = Some(ps::Io {
+                read_bytes: usage.ri_diskio_bytesread,
+                write_bytes: usage.ri_diskio_byteswritten,
+            });
+        }
+    }
+}
+
+fn rusage(pid: usize) -> Option<libc::rusage_info_v2> {
+    // SAFETY: all zeroes is a valid rusage_info_v2, it's just numbers
+    let mut usage: libc::rusage_info_v2 = unsafe { mem::zeroed() };
+    // SAFETY: the buffer is big enough for the V2 flavor
+    let result = unsafe {
+        libc::proc_pid_rusage(
+            pid as libc::c_int,
+            libc::RUSAGE_INFO_V2,
+            (&raw mut usage).cast(),
+        )
+    };
+    (result == 0).then_some(usage)
+}
+
+/// When the process started, in seconds since the epoch.
+fn start_time(pid: usize) -> Option<f64> {
+    let size = mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+    // SAFETY: same as above
+    let mut info: libc::proc_bsdinfo = unsafe { mem::zeroed() };
+    // SAFETY: proc_pidinfo writes at most `size` bytes
+    let written = unsafe {
+        libc::proc_pidinfo(
+            pid as libc::c_int,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            (&raw mut info).cast(),
+            size,
+        )
+    };
+    (written == size)
+        .then(|| info.pbi_start_tvsec as f64 + info.pbi_start_tvusec as f64 / 1_000_000.0)
+}
+
+/// Converts CPU times from rusage to seconds. They're in Mach time units, which only happen to
+/// be nanoseconds on Intel.
+fn seconds(time: u64) -> f64 {
+    static TIMEBASE: OnceLock<(u32, u32)> = OnceLock::new();
+    let (numer, denom) = *TIMEBASE.get_or_init(timebase);
+    time as f64 * numer as f64 / denom as f64 / 1_000_000_000.0
+}
+
+// the replacement is in the mach2 crate, not worth a dependency for one call
+#[allow(deprecated)]
+fn timebase() -> (u32, u32) {
+    let mut info = libc::mach_timebase_info { numer: 0, denom: 0 };
+    // SAFETY: only writes to the struct we give it
+    let result = unsafe { libc::mach_timebase_info(&mut info) };
+    if result == 0 && info.denom != 0 {
+        (info.numer, info.denom)
+    } else {
+        (1, 1)
+    }
+}
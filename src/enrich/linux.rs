@@ -0,0 +1,17 @@
+use super::Enricher;
+use crate::{cgroup, proc, ps};
+
+/// Memory, I/O and network from `/proc`, plus the build's cgroup if it has one.
+#[derive(Debug)]
+pub struct Procfs;
+
+impl Enricher for Procfs {
+    fn enrich(&self, build: &mut ps::Build) {
+        build.cgroup = cgroup::stats(build.main_pid);
+        build.net = proc::net(build.main_pid);
+        for process in &mut build.processes {
+            process.rss = proc::rss(process.pid);
+            process.io = proc::io(process.pid);
+        }
+    }
+}
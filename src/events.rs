@@ -0,0 +1,45 @@
+//! Things that happened while we were watching, for the Logs tab.
+
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+/// How many entries to remember.
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub time: DateTime<Utc>,
+    pub level: Level,
+    pub message: String,
+}
+
+/// Entries, newest first.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    pub entries: VecDeque<Entry>,
+}
+
+impl EventLog {
+    pub fn push(&mut self, level: Level, message: impl Into<String>) {
+        self.entries.push_front(Entry {
+            time: Utc::now(),
+            level,
+            message: message.into(),
+        });
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Level::Info, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Level::Error, message);
+    }
+}
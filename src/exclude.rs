@@ -0,0 +1,71 @@
+//! Derivations that aren't worth showing, like the sources and patches that a big rebuild
+//! fetches by the hundred and that are gone a second later.
+
+use crate::{drv, ps};
+
+#[derive(Debug, Default)]
+pub struct Exclude {
+    /// Derivation names without the hash and `.drv`, with `*` matching anything.
+    patterns: Vec<String>,
+    /// Whether to leave out fixed-output derivations too, which are nearly all fetches.
+    fetches: bool,
+}
+
+impl Exclude {
+    pub fn new(patterns: Vec<String>, fetches: bool) -> Self {
+        Self { patterns, fetches }
+    }
+
+    /// Whether to leave a build out. Only derivations on this machine can be told to be
+    /// fixed-output, from their file in `drv`.
+    pub fn excludes(&self, build: &ps::Build, drv: Option<&drv::Local>) -> bool {
+        let name = build.drv_name().name;
+        self.patterns.iter().any(|pattern| matches(pattern, name))
+            || (self.fetches && drv.is_some_and(|drv| drv.fixed_output))
+    }
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let Some(last) = parts.next_back() else {
+        // no `*` at all
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_star() {
+        assert!(matches("hello-2.12.1", "hello-2.12.1"));
+        assert!(!matches("hello", "hello-2.12.1"));
+    }
+
+    #[test]
+    fn stars() {
+        assert!(matches("*", "anything"));
+        assert!(matches("*-source", "linux-6.6-source"));
+        assert!(matches("python3*-tests", "python3.12-foo-tests"));
+        assert!(matches("a*b*c", "abc"));
+        assert!(!matches("*-source", "source"));
+    }
+
+    #[test]
+    fn prefix_and_suffix_dont_overlap() {
+        assert!(!matches("ab*ba", "aba"));
+        assert!(matches("ab*ba", "abba"));
+    }
+}
@@ -0,0 +1,159 @@
+//! Dumping builds along with the stats we computed about them into files.
+
+use crate::{archive, ps, stats::CpuUsage};
+use anyhow::Context;
+use chrono::Utc;
+use serde::Serialize;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// Guesses the format from the file extension, JSON unless it's `.csv`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::Json,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Process<'a> {
+    #[serde(flatten)]
+    process: &'a ps::BuildProcess,
+    cpu_percent: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Build<'a> {
+    derivation: &'a str,
+    pname: &'a str,
+    version: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<&'a str>,
+    main_pid: usize,
+    nix_pid: usize,
+    start_time: Option<f64>,
+    elapsed_seconds: Option<i64>,
+    cpu_percent: Option<f64>,
+    rss: Option<u64>,
+    processes: Vec<Process<'a>>,
+}
+
+/// The builds as they're written to JSON files, for sending them elsewhere.
+pub fn json(builds: &[ps::Build], cpu: &CpuUsage) -> serde_json::Value {
+    serde_json::to_value(builds_with_stats(builds, cpu)).unwrap_or_default()
+}
+
+fn builds_with_stats<'a>(builds: &'a [ps::Build], cpu: &CpuUsage) -> Vec<Build<'a>> {
+    builds
+        .iter()
+        .map(|build| Build {
+            derivation: &build.derivation,
+            pname: build.name().0,
+            version: build.name().1,
+            host: build.host.as_deref(),
+            main_pid: build.main_pid,
+            nix_pid: build.nix_pid,
+            start_time: build.start_time,
+            elapsed_seconds: build.elapsed(Utc::now()).map(|e| e.num_seconds()),
+            cpu_percent: cpu.build(build),
+            rss: build.rss(),
+            processes: build
+                .processes
+                .iter()
+                .map(|process| Process {
+                    process,
+                    cpu_percent: cpu.process(build, process.pid),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Serializes builds to a string in the given format.
+pub fn serialize(builds: &[ps::Build], cpu: &CpuUsage, format: Format) -> anyhow::Result<String> {
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(&builds_with_stats(
+            builds, cpu,
+        ))?),
+        Format::Csv => Ok(csv(builds, cpu)),
+    }
+}
+
+/// One row per process, with the build columns repeated.
+fn csv(builds: &[ps::Build], cpu: &CpuUsage) -> String {
+    let mut output = String::from(
+        "derivation,host,main_pid,nix_pid,start_time,pid,parent_pid,utime,stime,cpu_percent,rss,argv\n",
+    );
+
+    for build in builds {
+        for process in &build.processes {
+            let cpu = cpu.process(build, process.pid).map(|c| format!("{c:.1}"));
+            let rss = process.rss.map(|r| r.to_string());
+            let optional = |value: Option<String>| value.unwrap_or_default();
+            _ = writeln!(
+                output,
+                "{},{},{},{},{},{},{},{},{},{},{},{}",
+                csv_field(&build.derivation),
+                csv_field(build.host.as_deref().unwrap_or_default()),
+                build.main_pid,
+                build.nix_pid,
+                optional(build.start_time.map(|t| t.to_string())),
+                process.pid,
+                optional(process.parent_pid.map(|p| p.to_string())),
+                optional(process.utime.map(|t| t.to_string())),
+                optional(process.stime.map(|t| t.to_string())),
+                cpu.unwrap_or_default(),
+                rss.unwrap_or_default(),
+                csv_field(&process.argv.join(" ")),
+            );
+        }
+    }
+    output
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes builds to a file, picking the format from its extension.
+pub fn write(path: &Path, builds: &[ps::Build], cpu: &CpuUsage) -> anyhow::Result<()> {
+    let data = serialize(builds, cpu, Format::from_path(path))?;
+    fs::write(path, data).with_context(|| format!("writing {}", path.display()))
+}
+
+/// Where to export to when no path was given.
+pub fn default_path() -> String {
+    format!("ntop-{}.json", Utc::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Writes builds to a new JSON file in `$XDG_STATE_HOME/ntop` (or the current directory if
+/// there's no such thing), for looking at what a long-running ntop sees. Returns where it went.
+pub fn snapshot(builds: &[ps::Build], cpu: &CpuUsage) -> anyhow::Result<PathBuf> {
+    let name = format!("snapshot-{}.json", Utc::now().format("%Y%m%d-%H%M%S"));
+    let path = match archive::state_dir() {
+        Some(dir) => {
+            fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+            dir.join(name)
+        }
+        None => PathBuf::from(name),
+    };
+    write(&path, builds, cpu)?;
+    Ok(path)
+}
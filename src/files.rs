@@ -0,0 +1,131 @@
+//! Open files and sockets of a process, like `lsof -p`. Sockets are looked up in the process's
+//! own `/proc/<pid>/net`, since sandboxed builds have their own network namespace.
+
+use crate::glyphs;
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+#[derive(Debug, Clone)]
+pub struct OpenFile {
+    /// File descriptor number, or `cwd`.
+    pub fd: String,
+    /// Path, or what kind of socket/pipe it is.
+    pub target: String,
+}
+
+/// Working directory and file descriptors of a process, in fd order.
+pub fn open_files(pid: usize) -> io::Result<Vec<OpenFile>> {
+    let mut fds: Vec<(u32, String)> = fs::read_dir(format!("/proc/{pid}/fd"))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let fd = entry.file_name().to_str()?.parse().ok()?;
+            let target = fs::read_link(entry.path()).ok()?;
+            Some((fd, target.to_string_lossy().into_owned()))
+        })
+        .collect();
+    fds.sort();
+
+    let sockets = sockets(pid);
+    let mut files = vec![];
+    if let Ok(cwd) = fs::read_link(format!("/proc/{pid}/cwd")) {
+        files.push(OpenFile {
+            fd: "cwd".to_string(),
+            target: cwd.to_string_lossy().into_owned(),
+        });
+    }
+    for (fd, target) in fds {
+        let socket = target
+            .strip_prefix("socket:[")
+            .and_then(|s| s.strip_suffix(']'))
+            .and_then(|inode| sockets.get(inode));
+        files.push(OpenFile {
+            fd: fd.to_string(),
+            target: socket.cloned().unwrap_or(target),
+        });
+    }
+    Ok(files)
+}
+
+/// Descriptions of the sockets in the network namespace of a process, by inode.
+fn sockets(pid: usize) -> HashMap<String, String> {
+    let mut sockets = HashMap::new();
+    for protocol in ["tcp", "tcp6", "udp", "udp6"] {
+        let Ok(table) = fs::read_to_string(format!("/proc/{pid}/net/{protocol}")) else {
+            continue;
+        };
+        for line in table.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (Some(local), Some(remote), Some(state), Some(inode)) =
+                (fields.get(1), fields.get(2), fields.get(3), fields.get(9))
+            else {
+                continue;
+            };
+            let (Some(local), Some(remote)) = (address(local), address(remote)) else {
+                continue;
+            };
+
+            let mut description =
+                format!("{protocol} {local} {} {remote}", glyphs::pick("→", "->"));
+            if protocol.starts_with("tcp") {
+                description.push_str(&format!(" ({})", tcp_state(state)));
+            }
+            sockets.insert(inode.to_string(), description);
+        }
+    }
+
+    if let Ok(table) = fs::read_to_string(format!("/proc/{pid}/net/unix")) {
+        for line in table.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some(inode) = fields.get(6) else {
+                continue;
+            };
+            let description = match fields.get(7) {
+                Some(path) => format!("unix {path}"),
+                None => "unix".to_string(),
+            };
+            sockets.insert(inode.to_string(), description);
+        }
+    }
+    sockets
+}
+
+/// Turns `0100007F:0050` into `127.0.0.1:80`. The address is in host byte order, in 32 bit
+/// words for IPv6.
+fn address(hex: &str) -> Option<String> {
+    let (ip, port) = hex.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    match ip.len() {
+        8 => {
+            let ip = Ipv4Addr::from(u32::from_str_radix(ip, 16).ok()?.swap_bytes());
+            Some(format!("{ip}:{port}"))
+        }
+        32 => {
+            let mut bytes = [0; 16];
+            for (i, word) in bytes.chunks_mut(4).enumerate() {
+                let value = u32::from_str_radix(&ip[i * 8..(i + 1) * 8], 16).ok()?;
+                word.copy_from_slice(&value.swap_bytes().to_be_bytes());
+            }
+            Some(format!("[{}]:{port}", Ipv6Addr::from(bytes)))
+        }
+        _ => None,
+    }
+}
+
+fn tcp_state(hex: &str) -> &'static str {
+    match hex {
+        "01" => "established",
+        "02" => "syn sent",
+        "03" => "syn received",
+        "04" | "05" => "fin wait",
+        "06" => "time wait",
+        "07" => "closed",
+        "08" => "close wait",
+        "09" => "last ack",
+        "0A" => "listening",
+        "0B" => "closing",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,20 @@
+//! Box drawing, arrows and such look great until a serial console or a Windows font gets hold of
+//! them, so they can all be swapped for plain ASCII.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Sticks to ASCII from now on.
+pub fn use_ascii() {
+    ASCII.store(true, Ordering::Relaxed);
+}
+
+pub fn ascii() -> bool {
+    ASCII.load(Ordering::Relaxed)
+}
+
+/// `fancy`, or `plain` when sticking to ASCII.
+pub fn pick<'a>(fancy: &'a str, plain: &'a str) -> &'a str {
+    if ascii() { plain } else { fancy }
+}
@@ -0,0 +1,103 @@
+//! Builds that went away while we were watching.
+
+use crate::ps;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::{HashMap, VecDeque};
+
+/// How many finished builds to remember.
+const MAX_FINISHED: usize = 1000;
+
+/// The most a build had at once, over all the snapshots we got of it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Peaks {
+    pub processes: usize,
+    /// Bytes, `None` if the memory was never known.
+    pub rss: Option<u64>,
+}
+
+impl Peaks {
+    fn update(&mut self, build: &ps::Build) {
+        self.processes = self.processes.max(build.processes.len());
+        self.rss = self.rss.max(build.rss());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FinishedBuild {
+    /// The last snapshot we got of the build.
+    pub build: ps::Build,
+    /// When we noticed it was gone, so up to one refresh interval late.
+    pub ended: DateTime<Utc>,
+    pub peaks: Peaks,
+}
+
+impl FinishedBuild {
+    pub fn duration(&self) -> TimeDelta {
+        self.build.elapsed(self.ended).unwrap_or_default()
+    }
+}
+
+/// Finished builds, newest first.
+#[derive(Debug, Default)]
+pub struct History {
+    pub finished: VecDeque<FinishedBuild>,
+    /// Of the builds that are still running.
+    peaks: HashMap<(Option<String>, usize), Peaks>,
+}
+
+impl History {
+    /// Records the builds that are in the previous snapshot but not in the current one, and
+    /// returns how many there were.
+    pub fn update(&mut self, previous: &[ps::Build], current: &[ps::Build]) -> usize {
+        let now = Utc::now();
+        for build in current {
+            let key = (build.host.clone(), build.nix_pid);
+            self.peaks.entry(key).or_default().update(build);
+        }
+
+        let mut count = 0;
+        for build in previous {
+            if !current.iter().any(|b| b.key() == build.key()) {
+                let key = (build.host.clone(), build.nix_pid);
+                let mut peaks = self.peaks.remove(&key).unwrap_or_default();
+                peaks.update(build);
+                self.finished.push_front(FinishedBuild {
+                    build: build.clone(),
+                    ended: now,
+                    peaks,
+                });
+                count += 1;
+            }
+        }
+        self.finished.truncate(MAX_FINISHED);
+        count.min(MAX_FINISHED)
+    }
+}
+
+/// How many builds were running over time, for the chart.
+#[derive(Debug)]
+pub struct BuildCounts {
+    /// Oldest first.
+    pub samples: VecDeque<(DateTime<Utc>, usize)>,
+    /// How far back to remember.
+    pub window: TimeDelta,
+}
+
+impl BuildCounts {
+    pub fn new(window: TimeDelta) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            window,
+        }
+    }
+
+    pub fn push(&mut self, count: usize) {
+        let now = Utc::now();
+        self.samples.push_back((now, count));
+        while let Some((at, _)) = self.samples.front()
+            && now - *at > self.window
+        {
+            self.samples.pop_front();
+        }
+    }
+}
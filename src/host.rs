@@ -0,0 +1,419 @@
+//! How busy this machine is overall, to compare with what the builds are doing.
+
+use anyhow::Context;
+use std::{
+    collections::VecDeque,
+    fs, thread,
+    time::{Duration, Instant},
+};
+use tokio::process::Command;
+
+/// CPU time counters of all CPUs together or of one core, in clock ticks.
+#[derive(Debug, Clone, Copy, Default)]
+struct CpuTimes {
+    busy: u64,
+    total: u64,
+}
+
+impl CpuTimes {
+    /// How much was busy between `previous` and this, 0-100.
+    fn usage_since(self, previous: Self) -> Option<f64> {
+        if self.total <= previous.total {
+            return None;
+        }
+        let busy = self.busy.saturating_sub(previous.busy) as f64;
+        Some(busy / (self.total - previous.total) as f64 * 100.0)
+    }
+}
+
+/// The counters of all CPUs and of each core, from `/proc/stat`.
+#[cfg(target_os = "linux")]
+fn cpu_times() -> Option<(CpuTimes, Vec<CpuTimes>)> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let parse = |fields: &str| -> Option<CpuTimes> {
+        let fields: Vec<u64> = fields
+            .split_whitespace()
+            .filter_map(|f| f.parse().ok())
+            .collect();
+        // idle and iowait are the 4th and 5th
+        let idle = fields.get(3)? + fields.get(4).unwrap_or(&0);
+        let total = fields.iter().sum();
+        Some(CpuTimes {
+            busy: total - idle,
+            total,
+        })
+    };
+
+    let mut lines = stat.lines();
+    let all = parse(lines.next()?.strip_prefix("cpu ")?)?;
+    // `cpu0 ...`, `cpu1 ...` and so on, right after the total
+    let cores = lines
+        .map_while(|line| {
+            let (name, fields) = line.split_once(' ')?;
+            name.strip_prefix("cpu")?.parse::<usize>().ok()?;
+            parse(fields)
+        })
+        .collect();
+    Some((all, cores))
+}
+
+/// The counters of each core from the Mach host, added up for all of them.
+#[cfg(target_os = "macos")]
+// the replacements are in the mach2 crate, not worth a dependency for a couple of calls
+#[allow(deprecated)]
+fn cpu_times() -> Option<(CpuTimes, Vec<CpuTimes>)> {
+    let mut count: libc::natural_t = 0;
+    let mut info: libc::processor_info_array_t = std::ptr::null_mut();
+    let mut info_count: libc::mach_msg_type_number_t = 0;
+    // SAFETY: only writes to the variables we give it, the array is ours to deallocate
+    let result = unsafe {
+        libc::host_processor_info(
+            libc::mach_host_self(),
+            libc::PROCESSOR_CPU_LOAD_INFO,
+            &mut count,
+            &mut info,
+            &mut info_count,
+        )
+    };
+    if result != libc::KERN_SUCCESS || info.is_null() {
+        return None;
+    }
+
+    // CPU_STATE_MAX ticks per core: user, system, idle and nice
+    let states = libc::CPU_STATE_MAX as usize;
+    // SAFETY: the kernel gave us `info_count` integers
+    let ticks = unsafe { std::slice::from_raw_parts(info, info_count as usize) };
+    let cores: Vec<CpuTimes> = ticks
+        .chunks_exact(states)
+        .take(count as usize)
+        .map(|core| {
+            let total = core.iter().map(|t| *t as u32 as u64).sum();
+            let idle = core[libc::CPU_STATE_IDLE as usize] as u32 as u64;
+            CpuTimes {
+                busy: total - idle,
+                total,
+            }
+        })
+        .collect();
+    // SAFETY: the array came from host_processor_info and isn't used after this
+    unsafe {
+        libc::vm_deallocate(
+            libc::mach_task_self(),
+            info as libc::vm_address_t,
+            info_count as libc::vm_size_t * std::mem::size_of::<libc::integer_t>(),
+        );
+    }
+
+    let all = cores
+        .iter()
+        .fold(CpuTimes::default(), |all, core| CpuTimes {
+            busy: all.busy + core.busy,
+            total: all.total + core.total,
+        });
+    Some((all, cores))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn cpu_times() -> Option<(CpuTimes, Vec<CpuTimes>)> {
+    None
+}
+
+/// Used and total bytes of memory or swap.
+type Usage = (u64, u64);
+
+/// Reads and writes of the disk the store is on, or of all disks when that can't be told (on
+/// btrfs for one). Network filesystems aren't disks, so there's nothing for them.
+#[derive(Debug, Clone)]
+pub struct DiskIo {
+    /// The store's disk, `None` for all of them.
+    pub device: Option<String>,
+    /// Bytes per second.
+    pub read: f64,
+    pub write: f64,
+    /// How much of the time the disk was doing something, 0-100. For all disks it's the busiest.
+    pub busy: f64,
+}
+
+#[cfg(target_os = "linux")]
+use diskstats::DiskCounters;
+
+/// There's no `/proc/diskstats` anywhere else.
+#[cfg(not(target_os = "linux"))]
+#[derive(Debug, Clone, Default)]
+struct DiskCounters;
+
+#[cfg(not(target_os = "linux"))]
+impl DiskCounters {
+    fn update(&mut self, _now: Instant) -> Option<DiskIo> {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod diskstats {
+    use super::DiskIo;
+    use crate::store;
+    use std::{fs, os::unix::fs::MetadataExt, path::Path, time::Instant};
+
+    /// Counters of a block device from `/proc/diskstats`.
+    #[derive(Debug, Clone)]
+    struct DiskTimes {
+        name: String,
+        /// Major and minor number.
+        device: (u64, u64),
+        /// A real disk rather than a partition, loop device or such, which would count twice.
+        physical: bool,
+        read: u64,
+        written: u64,
+        /// Milliseconds spent doing I/O.
+        busy: u64,
+    }
+
+    fn read() -> Vec<DiskTimes> {
+        let Ok(diskstats) = fs::read_to_string("/proc/diskstats") else {
+            return vec![];
+        };
+        diskstats
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let number = |i: usize| -> Option<u64> { fields.get(i)?.parse().ok() };
+                let name = fields.get(2)?.to_string();
+                // sectors are always 512 bytes here, whatever the disk says
+                Some(DiskTimes {
+                    physical: Path::new("/sys/block").join(&name).join("device").exists(),
+                    name,
+                    device: (number(0)?, number(1)?),
+                    read: number(5)? * 512,
+                    written: number(9)? * 512,
+                    busy: number(12)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Major and minor number of the device the store is on.
+    fn store_device() -> Option<(u64, u64)> {
+        let dev = fs::metadata(store::store_dir()).ok()?.dev();
+        Some((libc::major(dev).into(), libc::minor(dev).into()))
+    }
+
+    fn between(before: &DiskTimes, after: &DiskTimes, seconds: f64) -> DiskIo {
+        DiskIo {
+            device: Some(after.name.clone()),
+            read: after.read.saturating_sub(before.read) as f64 / seconds,
+            write: after.written.saturating_sub(before.written) as f64 / seconds,
+            busy: (after.busy.saturating_sub(before.busy) as f64 / seconds / 10.0).min(100.0),
+        }
+    }
+
+    /// The counters from the last update, to work out rates from.
+    #[derive(Debug, Clone, Default)]
+    pub struct DiskCounters {
+        previous: Option<(Instant, Vec<DiskTimes>)>,
+    }
+
+    impl DiskCounters {
+        /// The I/O of the store's disk since the last update, or of all disks added up.
+        pub fn update(&mut self, now: Instant) -> Option<DiskIo> {
+            let disks = read();
+            let io = match &self.previous {
+                Some((then, previous)) if now > *then => {
+                    disk_io(previous, &disks, (now - *then).as_secs_f64())
+                }
+                _ => None,
+            };
+            self.previous = Some((now, disks));
+            io
+        }
+    }
+
+    fn disk_io(before: &[DiskTimes], after: &[DiskTimes], seconds: f64) -> Option<DiskIo> {
+        let rates: Vec<(&DiskTimes, DiskIo)> = after
+            .iter()
+            .filter_map(|disk| {
+                let previous = before.iter().find(|p| p.device == disk.device)?;
+                Some((disk, between(previous, disk, seconds)))
+            })
+            .collect();
+
+        let store = store_device();
+        if let Some((_, io)) = rates.iter().find(|(disk, _)| Some(disk.device) == store) {
+            return Some(io.clone());
+        }
+
+        rates
+            .into_iter()
+            .filter(|(disk, _)| disk.physical)
+            .map(|(_, io)| io)
+            .reduce(|all, io| DiskIo {
+                read: all.read + io.read,
+                write: all.write + io.write,
+                busy: all.busy.max(io.busy),
+                ..all
+            })
+            .map(|all| DiskIo {
+                device: None,
+                ..all
+            })
+    }
+}
+
+/// How far back the graphs go.
+const HISTORY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Adds a sample to a graph and forgets the ones that are too old.
+fn record(history: &mut VecDeque<(Instant, f64)>, now: Instant, value: Option<f64>) {
+    if let Some(value) = value {
+        history.push_back((now, value));
+    }
+    while let Some((at, _)) = history.front()
+        && now - *at > HISTORY_WINDOW
+    {
+        history.pop_front();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HostStats {
+    /// 1, 5 and 15 minute load averages.
+    pub load: Option<[f64; 3]>,
+    /// How much of all CPUs was used since the last update, 0-100.
+    pub cpu: Option<f64>,
+    /// Like `cpu`, for each core.
+    pub cores: Vec<f64>,
+    /// Used and total memory in bytes. Used doesn't count caches.
+    pub memory: Option<Usage>,
+    /// Used and total swap in bytes, `None` without any swap.
+    pub swap: Option<Usage>,
+    /// Bytes swapped in and out per second since the last update, which is what makes a machine
+    /// crawl rather than how much swap is used.
+    pub swap_rate: Option<f64>,
+    /// Since the last update, `None` without `/proc/diskstats`.
+    pub disk_io: Option<DiskIo>,
+    /// CPU usage over the last minute, oldest first.
+    cpu_history: VecDeque<(Instant, f64)>,
+    /// Like `cpu_history`, for how busy the disk was.
+    disk_history: VecDeque<(Instant, f64)>,
+    previous: Option<(CpuTimes, Vec<CpuTimes>)>,
+    previous_swapped: Option<(Instant, u64)>,
+    disks: DiskCounters,
+}
+
+impl HostStats {
+    /// Reads everything again. Only CPU usage works on macOS, and nothing elsewhere.
+    pub fn update(&mut self) {
+        self.load = load();
+        (self.memory, self.swap) = memory();
+
+        let swapped = swapped().map(|bytes| (Instant::now(), bytes));
+        self.swap_rate = match (self.previous_swapped, swapped) {
+            (Some((then, before)), Some((now, after))) if now > then => {
+                Some(after.saturating_sub(before) as f64 / (now - then).as_secs_f64())
+            }
+            _ => None,
+        };
+        self.previous_swapped = swapped;
+
+        let current = cpu_times();
+        (self.cpu, self.cores) = match (&self.previous, &current) {
+            (Some((previous, previous_cores)), Some((current, cores))) => (
+                current.usage_since(*previous),
+                // cores going offline leave a gap, so just wait for the next update then
+                if cores.len() == previous_cores.len() {
+                    cores
+                        .iter()
+                        .zip(previous_cores)
+                        .map(|(core, previous)| core.usage_since(*previous).unwrap_or(0.0))
+                        .collect()
+                } else {
+                    vec![]
+                },
+            ),
+            _ => (None, vec![]),
+        };
+        self.previous = current;
+
+        let now = Instant::now();
+        self.disk_io = self.disks.update(now);
+
+        record(&mut self.cpu_history, now, self.cpu);
+        let busy = self.disk_io.as_ref().map(|io| io.busy);
+        record(&mut self.disk_history, now, busy);
+    }
+
+    /// The samples of the last minute, for a sparkline.
+    pub fn cpu_history(&self) -> VecDeque<f64> {
+        self.cpu_history.iter().map(|(_, cpu)| *cpu).collect()
+    }
+
+    /// Like [`Self::cpu_history`], for how busy the disk was.
+    pub fn disk_history(&self) -> VecDeque<f64> {
+        self.disk_history.iter().map(|(_, busy)| *busy).collect()
+    }
+}
+
+fn load() -> Option<[f64; 3]> {
+    let loadavg = fs::read_to_string("/proc/loadavg").ok()?;
+    let mut fields = loadavg.split_whitespace().map(|f| f.parse().ok());
+    Some([fields.next()??, fields.next()??, fields.next()??])
+}
+
+/// Used and total memory, and the same for swap if there is any.
+fn memory() -> (Option<Usage>, Option<Usage>) {
+    let Ok(meminfo) = fs::read_to_string("/proc/meminfo") else {
+        return (None, None);
+    };
+    let field = |key: &str| -> Option<u64> {
+        let line = meminfo.lines().find(|l| l.starts_with(key))?;
+        let kb: u64 = line[key.len()..]
+            .trim()
+            .strip_suffix(" kB")?
+            .trim()
+            .parse()
+            .ok()?;
+        Some(kb * 1024)
+    };
+
+    let memory = field("MemTotal:")
+        .zip(field("MemAvailable:"))
+        .map(|(total, available)| (total - available, total));
+    let swap = field("SwapTotal:")
+        .zip(field("SwapFree:"))
+        .filter(|(total, _)| *total > 0)
+        .map(|(total, free)| (total - free, total));
+    (memory, swap)
+}
+
+/// Bytes swapped in and out since boot.
+fn swapped() -> Option<u64> {
+    let vmstat = fs::read_to_string("/proc/vmstat").ok()?;
+    let pages: u64 = vmstat
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(key, _)| matches!(*key, "pswpin" | "pswpout"))
+        .filter_map(|(_, value)| value.parse::<u64>().ok())
+        .sum();
+    // SAFETY: sysconf has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    Some(pages * page_size)
+}
+
+/// The `max-jobs` setting of the local Nix, with `auto` turned into the number of CPUs like Nix
+/// does.
+pub async fn max_jobs() -> anyhow::Result<usize> {
+    let output = Command::new("nix")
+        .args(["config", "show", "max-jobs"])
+        .output()
+        .await
+        .context("running nix config show")?;
+    anyhow::ensure!(output.status.success(), "nix config show failed");
+
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if value == "auto" {
+        return Ok(thread::available_parallelism().map_or(1, |n| n.get()));
+    }
+    value
+        .parse()
+        .with_context(|| format!("max-jobs is {value:?}"))
+}
@@ -0,0 +1,140 @@
+//! Just enough HTTP/1.1 to answer GET requests from Prometheus and browsers.
+
+use std::{future::Future, io, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    time,
+};
+
+/// Most bytes of request line and headers read, anything bigger isn't from a browser.
+const MAX_HEAD: u64 = 16 * 1024;
+const MAX_HEADERS: usize = 100;
+/// Longest a connection gets to send its request and get the answer, so slow clients can't
+/// pile up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: String) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body,
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self {
+            status: 404,
+            content_type: "text/plain",
+            body: "not found\n".to_string(),
+        }
+    }
+
+    pub fn error(err: anyhow::Error) -> Self {
+        Self {
+            status: 500,
+            content_type: "text/plain",
+            body: format!("{err:#}\n"),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Accepts connections forever, answering each request with the handler.
+pub async fn serve<H, F>(listener: TcpListener, handler: H) -> io::Result<()>
+where
+    H: Fn(Request) -> F + Clone + Send + 'static,
+    F: Future<Output = Response> + Send,
+{
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            // nothing to do about broken or slow connections
+            _ = time::timeout(REQUEST_TIMEOUT, handle(stream, handler)).await;
+        });
+    }
+}
+
+async fn handle<H, F>(stream: TcpStream, handler: H) -> io::Result<()>
+where
+    H: Fn(Request) -> F,
+    F: Future<Output = Response>,
+{
+    let mut stream = BufReader::new(stream);
+    let mut head = (&mut stream).take(MAX_HEAD);
+
+    let mut line = String::new();
+    head.read_line(&mut line).await?;
+    // cut off by the limit
+    if !line.ends_with('\n') {
+        return Ok(());
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(());
+    };
+
+    let request = Request {
+        method: method.to_string(),
+        path: target.split('?').next().unwrap_or(target).to_string(),
+    };
+
+    // don't care about headers, but they have to be read
+    let mut headers = 0;
+    loop {
+        let mut header = String::new();
+        if head.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS || !header.ends_with('\n') {
+            return Ok(());
+        }
+    }
+
+    let response = if request.method == "GET" {
+        handler(request).await
+    } else {
+        Response {
+            status: 405,
+            content_type: "text/plain",
+            body: "only GET is supported\n".to_string(),
+        }
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len(),
+    );
+
+    let stream = stream.get_mut();
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,53 @@
+//! What ntop knows about Nix builds, without the UI: getting builds from `nix ps` or `/proc`
+//! ([`source`], [`ps`], [`proc`]), and the numbers worked out from them. Status bars and bots can
+//! depend on this with `default-features = false` to leave out the terminal stuff.
+
+pub mod activity;
+pub mod alerts;
+pub mod archive;
+pub mod category;
+pub mod cgroup;
+pub mod client;
+pub mod disk;
+pub mod drv;
+pub mod durations;
+pub mod enrich;
+pub mod events;
+pub mod exclude;
+pub mod export;
+pub mod files;
+pub mod glyphs;
+pub mod history;
+pub mod host;
+pub mod notify;
+pub mod phase;
+pub mod priority;
+pub mod proc;
+pub mod ps;
+pub mod signal;
+pub mod source;
+pub mod stats;
+pub mod store;
+pub mod stuck;
+pub mod watch;
+pub mod why;
+
+// the UI, and the modes of the command line that print or serve things instead
+#[cfg(feature = "tui")]
+pub mod app;
+#[cfg(feature = "tui")]
+pub mod cli;
+#[cfg(feature = "tui")]
+pub mod columns;
+#[cfg(feature = "tui")]
+pub mod config;
+#[cfg(feature = "tui")]
+pub mod http;
+#[cfg(feature = "tui")]
+pub mod metrics;
+#[cfg(feature = "tui")]
+pub mod print;
+#[cfg(feature = "tui")]
+pub mod theme;
+#[cfg(feature = "tui")]
+pub mod web;
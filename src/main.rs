@@ -1,12 +1,132 @@
-use crate::app::App;
+use anyhow::Context;
+use clap::Parser;
+use crossterm::event::{
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+};
+use ntop::{
+    app::App,
+    cli::{Cli, Command},
+    config::Config,
+    durations::Durations,
+    glyphs, metrics, print, ps, web,
+};
+use std::{fs::File, io, panic, path::Path, sync::Mutex, time::Duration};
+use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
 
-pub mod app;
-pub mod ps;
+/// Sends logs to a file, everything of ntop at debug level unless `NTOP_LOG` says otherwise.
+fn init_logging(path: &Path) -> anyhow::Result<()> {
+    let file = File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    let filter =
+        EnvFilter::try_from_env("NTOP_LOG").unwrap_or_else(|_| EnvFilter::new("ntop=debug"));
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_env_filter(filter)
+        .init();
+    Ok(())
+}
+
+/// Puts the terminal back the way it was. Safe to call more than once.
+fn restore_terminal() {
+    _ = crossterm::execute!(io::stdout(), DisableMouseCapture, DisableFocusChange);
+    ratatui::restore();
+}
+
+/// Restores the terminal before a panic message is printed, so it's readable and the shell
+/// isn't left in raw mode on the alternate screen.
+fn install_panic_hook() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        tracing::error!("{info}");
+        restore_terminal();
+        hook(info);
+    }));
+}
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    if let Some(path) = &cli.log_file {
+        init_logging(path)?;
+    }
+    tracing::debug!(?cli, "starting");
+    if let Some(Command::History {
+        report,
+        limit,
+        json,
+    }) = cli.command
+    {
+        return print::history(report, limit, json);
+    }
+
+    let sources = cli.sources()?;
+    let interval = Duration::from_secs(cli.interval.max(1));
+    // every mode but the UI, which does this itself with what it knows. `--once` too, it can be
+    // waiting for builds to look stuck
+    let ui = !(cli.headless || cli.plain || cli.accessible || cli.once || cli.command.is_some());
+    if !ui {
+        print::snapshot_on_sigusr1(sources.clone());
+    }
+    if let Some(Command::Wait { pattern }) = &cli.command {
+        return print::wait(&sources, pattern, interval).await;
+    }
+    if let Some(Command::Serve { listen }) = cli.command {
+        let listener = TcpListener::bind(listen)
+            .await
+            .with_context(|| format!("binding {listen}"))?;
+        let builds = ps::Cached::new(sources, interval);
+        return Ok(web::serve(listener, builds).await?);
+    }
+    if cli.once {
+        let checks = cli.checks();
+        return print::once(&sources, cli.json, cli.output.as_deref(), &checks).await;
+    }
+
+    if let Some(addr) = cli.metrics_listen {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("binding {addr}"))?;
+
+        let builds = ps::Cached::new(sources.clone(), interval);
+        if cli.headless {
+            return Ok(metrics::serve(listener, builds).await?);
+        }
+        tokio::spawn(metrics::serve(listener, builds));
+    }
+
+    if cli.accessible {
+        return print::accessible(&sources, interval).await;
+    }
+    if cli.plain {
+        return print::plain(&sources, interval).await;
+    }
+
+    let config = Config::load()?;
+    if cli.ascii || config.ascii {
+        glyphs::use_ascii();
+    }
+    let theme = config.theme()?;
+    let mut app = App::new(config, theme);
+    app.output = cli.output;
+    app.sources = sources;
+    app.activity = cli.activity;
+    app.socket = cli.socket;
+    match Durations::load() {
+        Ok(durations) => app.durations = durations,
+        Err(err) => app.events.error(format!("{err:#}")),
+    }
+
     let terminal = ratatui::init();
-    let result = App::new().run(terminal).await;
-    ratatui::restore();
+    install_panic_hook();
+    // errors too, the terminal has to be restored before they're printed
+    let result = match crossterm::execute!(io::stdout(), EnableMouseCapture, EnableFocusChange) {
+        Ok(()) => app.run(terminal).await,
+        Err(err) => Err(err.into()),
+    };
+    restore_terminal();
     result
 }
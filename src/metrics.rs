@@ -0,0 +1,108 @@
+//! Prometheus metrics about active builds.
+
+use crate::{
+    http::{self, Request, Response},
+    ps,
+};
+use chrono::Utc;
+use std::{fmt::Write, sync::Arc};
+use tokio::net::TcpListener;
+
+/// Name, type, help text, and how to get the value from a build.
+type Family = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&ps::Build) -> Option<f64>,
+);
+
+/// Renders builds in the Prometheus text exposition format.
+pub fn render(builds: &[ps::Build]) -> String {
+    let mut output = String::new();
+
+    _ = writeln!(
+        output,
+        "# HELP ntop_active_builds Number of builds in progress."
+    );
+    _ = writeln!(output, "# TYPE ntop_active_builds gauge");
+    _ = writeln!(output, "ntop_active_builds {}", builds.len());
+
+    let families: [Family; 4] = [
+        (
+            "ntop_build_elapsed_seconds",
+            "gauge",
+            "Seconds since the build started.",
+            |b| Some(b.elapsed(Utc::now())?.num_milliseconds() as f64 / 1000.0),
+        ),
+        (
+            "ntop_build_processes",
+            "gauge",
+            "Number of processes in the build.",
+            |b| Some(b.processes.len() as f64),
+        ),
+        // not a counter: without a cgroup it's only the processes still around, so it drops
+        // when one exits
+        (
+            "ntop_build_cpu_seconds",
+            "gauge",
+            "CPU time used by the build, from its cgroup or else its live processes.",
+            |b| Some(b.cpu_time().num_milliseconds() as f64 / 1000.0),
+        ),
+        (
+            "ntop_build_resident_memory_bytes",
+            "gauge",
+            "Resident memory of the build's processes.",
+            |b| b.rss().map(|rss| rss as f64),
+        ),
+    ];
+
+    for (name, kind, help, value) in families {
+        _ = writeln!(output, "# HELP {name} {help}");
+        _ = writeln!(output, "# TYPE {name} {kind}");
+        for build in builds {
+            if let Some(value) = value(build) {
+                _ = writeln!(output, "{name}{{{}}} {value}", labels(build));
+            }
+        }
+    }
+
+    output
+}
+
+fn labels(build: &ps::Build) -> String {
+    let (pname, version) = build.name();
+    let mut labels = format!(
+        "derivation=\"{}\",pname=\"{}\",version=\"{}\"",
+        escape(&build.derivation),
+        escape(pname),
+        escape(version),
+    );
+    if let Some(host) = &build.host {
+        _ = write!(labels, ",host=\"{}\"", escape(host));
+    }
+    labels
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub(crate) async fn handle(request: Request, builds: Arc<ps::Cached>) -> Response {
+    match request.path.as_str() {
+        "/metrics" => match builds.get().await {
+            Ok((_, builds)) => Response::ok("text/plain; version=0.0.4", render(&builds)),
+            Err(err) => Response::error(err),
+        },
+        _ => Response::not_found(),
+    }
+}
+
+/// Serves `/metrics` forever. Scrapes get the builds from the last `nix ps`, if it's recent
+/// enough.
+pub async fn serve(listener: TcpListener, builds: ps::Cached) -> std::io::Result<()> {
+    let builds = Arc::new(builds);
+    http::serve(listener, move |request| handle(request, builds.clone())).await
+}
@@ -0,0 +1,66 @@
+//! Desktop notifications about finished builds, and whatever else needs one.
+
+use crate::{history::FinishedBuild, stats::show_duration};
+use chrono::TimeDelta;
+use serde::Deserialize;
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// The `[notifications]` section of the config.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Notifications {
+    pub enabled: bool,
+    /// Only notify about builds that took at least this many minutes.
+    pub min_minutes: u32,
+}
+
+impl Notifications {
+    /// Sends a notification about a build going away, if it's worth one.
+    pub fn finished(&self, finished: &FinishedBuild) {
+        if !self.enabled || finished.duration() < TimeDelta::minutes(self.min_minutes.into()) {
+            return;
+        }
+
+        let (pname, version) = finished.build.name();
+        let mut body = format!(
+            "{pname} {version} took {}",
+            show_duration(finished.duration())
+        );
+        if let Some(host) = &finished.build.host {
+            body.push_str(&format!(" on {host}"));
+        }
+        send("Build finished", &body);
+    }
+}
+
+/// Shows a notification with whatever the platform has, in the background. Errors are ignored,
+/// there's nowhere to show them and it's not worth interrupting anything over.
+pub fn send(title: &str, body: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title),
+        ));
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.arg("--app-name=ntop").arg(title).arg(body);
+        command
+    };
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    tokio::spawn(async move {
+        _ = command.status().await;
+    });
+}
+
+fn applescript_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,151 @@
+//! Guessing which phase of the stdenv a build is in, from what it's running or from its log.
+
+use crate::ps;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Unpack,
+    Patch,
+    Configure,
+    Build,
+    Check,
+    Install,
+    Fixup,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Unpack => "unpack",
+            Phase::Patch => "patch",
+            Phase::Configure => "configure",
+            Phase::Build => "build",
+            Phase::Check => "check",
+            Phase::Install => "install",
+            Phase::Fixup => "fixup",
+        }
+    }
+
+    /// From the stdenv's phase function names, like `buildPhase`. Custom phases don't count.
+    pub fn from_function(name: &str) -> Option<Self> {
+        Some(match name.strip_suffix("Phase")? {
+            "unpack" => Phase::Unpack,
+            "patch" => Phase::Patch,
+            "configure" => Phase::Configure,
+            "build" => Phase::Build,
+            "check" | "installCheck" => Phase::Check,
+            "install" => Phase::Install,
+            "fixup" | "distribution" => Phase::Fixup,
+            _ => return None,
+        })
+    }
+}
+
+/// The phase from the last `Running phase: ...` line the stdenv printed.
+pub fn from_log(lines: &[String]) -> Option<Phase> {
+    lines.iter().rev().find_map(|line| {
+        let name = line.trim().strip_prefix("Running phase: ")?;
+        Phase::from_function(name.trim())
+    })
+}
+
+/// Which phase wins when programs of several are running. Compilers run during configure, check
+/// and install too, so build goes last.
+const PRIORITY: [Phase; 7] = [
+    Phase::Check,
+    Phase::Install,
+    Phase::Fixup,
+    Phase::Configure,
+    Phase::Patch,
+    Phase::Unpack,
+    Phase::Build,
+];
+
+/// Interpreters whose script is the interesting part, like `bash ./configure`.
+const INTERPRETERS: &[&str] = &["bash", "sh", "python", "python3", "perl"];
+
+/// Guesses the phase from the programs running in the build.
+pub fn detect(build: &ps::Build) -> Option<Phase> {
+    let phases: Vec<Phase> = build
+        .processes
+        .iter()
+        .filter_map(|process| from_argv(&process.argv))
+        .collect();
+    PRIORITY.into_iter().find(|phase| phases.contains(phase))
+}
+
+fn from_argv(argv: &[String]) -> Option<Phase> {
+    let file_name = |arg: &String| arg.rsplit('/').next().unwrap_or(arg).to_string();
+    let mut program = file_name(argv.first()?);
+    let mut args = &argv[1..];
+    if INTERPRETERS.contains(&program.as_str())
+        && let Some(script) = args.iter().position(|a| !a.starts_with('-'))
+    {
+        program = file_name(&args[script]);
+        args = &args[script + 1..];
+    }
+    let has = |arg: &str| args.iter().any(|a| a == arg);
+
+    Some(match program.as_str() {
+        "tar" | "unzip" | "7z" => Phase::Unpack,
+        "patch" => Phase::Patch,
+        "configure" | "cmake" | "autoreconf" | "autoconf" | "automake" => Phase::Configure,
+        "meson" if has("setup") => Phase::Configure,
+        "pytest" | "ctest" | "nextest" => Phase::Check,
+        "make" | "ninja" | "meson" if has("check") || has("test") => Phase::Check,
+        "make" | "ninja" | "meson" if has("install") => Phase::Install,
+        "cargo" | "go" | "dune" if has("test") => Phase::Check,
+        "patchelf" | "strip" | "patch-shebangs" => Phase::Fixup,
+        "make" | "ninja" | "meson" | "cargo" | "go" | "dune" | "cc" | "c++" | "gcc" | "g++"
+        | "clang" | "clang++" | "rustc" | "ghc" | "javac" | "ld" => Phase::Build,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(args: &[&str]) -> Option<Phase> {
+        from_argv(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn programs() {
+        assert_eq!(phase(&["tar", "xf", "src.tar.gz"]), Some(Phase::Unpack));
+        assert_eq!(
+            phase(&["/nix/store/x-gcc/bin/gcc", "-c", "a.c"]),
+            Some(Phase::Build)
+        );
+        assert_eq!(phase(&["meson", "setup", "build"]), Some(Phase::Configure));
+        assert_eq!(
+            phase(&["patchelf", "--shrink-rpath", "a"]),
+            Some(Phase::Fixup)
+        );
+        assert_eq!(phase(&["sleep", "10"]), None);
+        assert_eq!(phase(&[]), None);
+    }
+
+    #[test]
+    fn arguments_decide() {
+        assert_eq!(phase(&["make", "-j8"]), Some(Phase::Build));
+        assert_eq!(phase(&["make", "check"]), Some(Phase::Check));
+        assert_eq!(phase(&["make", "install"]), Some(Phase::Install));
+        assert_eq!(phase(&["cargo", "test"]), Some(Phase::Check));
+        assert_eq!(phase(&["cargo", "build"]), Some(Phase::Build));
+    }
+
+    #[test]
+    fn scripts_run_by_interpreters() {
+        let configure = [
+            "/nix/store/x-bash/bin/bash",
+            "-e",
+            "./configure",
+            "--prefix=/x",
+        ];
+        assert_eq!(phase(&configure), Some(Phase::Configure));
+        assert_eq!(phase(&["python3", "-m", "pytest"]), Some(Phase::Check));
+        // the shell on its own says nothing
+        assert_eq!(phase(&["bash", "-e"]), None);
+    }
+}
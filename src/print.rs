@@ -0,0 +1,388 @@
+//! Plain text output for when the full-screen UI isn't wanted.
+
+use crate::{
+    archive::{self, Report},
+    drv, export, ps,
+    source::Sources,
+    stats::{CpuUsage, show_bytes, show_duration, show_elapsed},
+    store,
+};
+use chrono::{TimeDelta, Utc};
+use std::{collections::HashMap, fmt::Write, fs, path::Path, time::Duration};
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    time::{self, MissedTickBehavior},
+};
+
+/// Formats builds as a plain text table, like `ps` would. The host column is only there when
+/// some builds are on other machines.
+pub fn table(builds: &[ps::Build]) -> String {
+    let hosts = builds.iter().any(|b| b.host.is_some());
+    let rows: Vec<[String; 6]> = builds
+        .iter()
+        .map(|build| {
+            let (pname, version) = build.name();
+            [
+                build.main_pid.to_string(),
+                build.host.clone().unwrap_or_else(|| "local".to_string()),
+                pname.to_string(),
+                version.to_string(),
+                show_bytes(build.rss()),
+                show_elapsed(build.elapsed(Utc::now())),
+            ]
+        })
+        .collect();
+
+    let header = ["PID", "HOST", "PACKAGE", "VERSION", "MEM", "TIME"].map(String::from);
+    let mut widths = [0; 6];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        // numbers on the right, text on the left
+        let [pid, host, pname, version, mem, time] = row;
+        let [w0, w1, w2, w3, w4, _] = widths;
+        if hosts {
+            _ = write!(output, "{pid:>w0$}  {host:<w1$}  ");
+        } else {
+            _ = write!(output, "{pid:>w0$}  ");
+        }
+        _ = writeln!(output, "{pname:<w2$}  {version:<w3$}  {mem:>w4$}  {time}");
+    }
+    output
+}
+
+/// What makes `--once` fail, so that it can be used as a monitoring probe.
+#[derive(Debug, Default)]
+pub struct Checks {
+    /// Builds running for longer than this are a problem.
+    pub longer_than: Option<TimeDelta>,
+    /// Builds that used no CPU and did no I/O for this long are a problem. Takes that long to
+    /// find out.
+    pub stuck_for: Option<TimeDelta>,
+}
+
+impl Checks {
+    /// Waits as long as needed, then returns the builds as they are now and what's wrong with
+    /// them.
+    async fn run(&self, sources: &Sources) -> anyhow::Result<(ps::Output, Vec<String>)> {
+        let mut builds = ps::get_merged(sources).await?;
+        let mut problems = vec![];
+
+        if let Some(stuck_for) = self.stuck_for {
+            let before = builds;
+            time::sleep(stuck_for.to_std().unwrap_or_default()).await;
+            builds = ps::get_merged(sources).await?;
+            // the local side of a delegated build is just ssh waiting, which says nothing
+            for build in builds.iter().filter(|b| !b.delegated) {
+                let Some(previous) = before.iter().find(|b| b.key() == build.key()) else {
+                    continue;
+                };
+                if previous.cpu_time() == build.cpu_time() && previous.io() == build.io() {
+                    problems.push(format!(
+                        "{} used no CPU and did no I/O for {}",
+                        describe(build),
+                        show_duration(stuck_for)
+                    ));
+                }
+            }
+        }
+
+        if let Some(longer_than) = self.longer_than {
+            let now = Utc::now();
+            for build in &builds {
+                if let Some(elapsed) = build.elapsed(now).filter(|e| *e > longer_than) {
+                    problems.push(format!(
+                        "{} has been building for {}, longer than {}",
+                        describe(build),
+                        show_duration(elapsed),
+                        show_duration(longer_than)
+                    ));
+                }
+            }
+        }
+        Ok((builds, problems))
+    }
+}
+
+/// Prints the active builds once, for `--once`. Fails after printing them if they don't pass the
+/// checks.
+pub async fn once(
+    sources: &Sources,
+    json: bool,
+    output: Option<&Path>,
+    checks: &Checks,
+) -> anyhow::Result<()> {
+    let (mut builds, problems) = checks.run(sources).await?;
+    builds.sort_by(|a, b| a.derivation.cmp(&b.derivation));
+
+    if let Some(path) = output {
+        // only one snapshot, so no CPU usage
+        export::write(path, &builds, &CpuUsage::default())?;
+    } else if json {
+        println!("{}", serde_json::to_string_pretty(&builds)?);
+    } else if builds.is_empty() {
+        println!("No active builds");
+    } else {
+        print!("{}", table(&builds));
+    }
+
+    for problem in &problems {
+        eprintln!("{problem}");
+    }
+    match problems.len() {
+        0 => Ok(()),
+        1 => anyhow::bail!("1 build has a problem"),
+        n => anyhow::bail!("{n} builds have problems"),
+    }
+}
+
+/// Prints the active builds every `interval`, for `--plain`. Each table goes below the last one
+/// instead of redrawing, so it works in CI logs and on serial consoles. Runs until interrupted.
+pub async fn plain(sources: &Sources, interval: Duration) -> anyhow::Result<()> {
+    let mut ticks = time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let time = Utc::now().format("%H:%M:%S UTC");
+        match ps::get_merged(sources).await {
+            Ok(mut builds) => {
+                builds.sort_by(|a, b| a.derivation.cmp(&b.derivation));
+                match builds.len() {
+                    0 => println!("{time}, no active builds"),
+                    1 => println!("{time}, 1 active build"),
+                    n => println!("{time}, {n} active builds"),
+                }
+                if !builds.is_empty() {
+                    print!("{}", table(&builds));
+                }
+            }
+            Err(err) => println!("{time}, couldn't get builds: {err:#}"),
+        }
+        println!();
+    }
+}
+
+/// Name of a build for a sentence, like `hello 2.12.1 on builder`.
+fn describe(build: &ps::Build) -> String {
+    let (pname, version) = build.name();
+    let mut name = pname.to_string();
+    if !version.is_empty() {
+        _ = write!(name, " {version}");
+    }
+    if let Some(host) = &build.host {
+        _ = write!(name, " on {host}");
+    }
+    name
+}
+
+/// Prints a line whenever a build starts or finishes, for `--accessible`. Nothing is ever
+/// redrawn, so it works with screen readers. Runs until interrupted.
+pub async fn accessible(sources: &Sources, interval: Duration) -> anyhow::Result<()> {
+    let mut known: HashMap<(Option<String>, usize), ps::Build> = HashMap::new();
+    let mut first = true;
+    let mut failing = false;
+    let mut ticks = time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let builds = match ps::get_merged(sources).await {
+            Ok(builds) => builds,
+            Err(err) => {
+                // once, not every refresh
+                if !failing {
+                    println!("Couldn't get builds: {err:#}");
+                }
+                failing = true;
+                continue;
+            }
+        };
+        if failing {
+            println!("Getting builds works again");
+            failing = false;
+        }
+
+        let now = Utc::now();
+        let mut current = HashMap::new();
+        for build in builds {
+            let key = (build.host.clone(), build.nix_pid);
+            if first {
+                let elapsed = show_elapsed(build.elapsed(now));
+                println!("{} is building, for {elapsed} so far", describe(&build));
+            } else if !known.contains_key(&key) {
+                println!("{} started", describe(&build));
+            }
+            current.insert(key, build);
+        }
+        for (key, build) in &known {
+            if !current.contains_key(key) {
+                let elapsed = show_elapsed(build.elapsed(now));
+                println!("{} finished after {elapsed}", describe(build));
+            }
+        }
+        if first && current.is_empty() {
+            println!("No active builds");
+        }
+
+        known = current;
+        first = false;
+    }
+}
+
+/// Whether all outputs of a derivation on this machine are in the store, `None` if there's no
+/// telling.
+fn outputs_exist(derivation: &str) -> Option<bool> {
+    let local = drv::read_local(derivation)?;
+    if local.outputs.is_empty() {
+        return None;
+    }
+    Some(
+        local
+            .outputs
+            .iter()
+            .all(|path| fs::symlink_metadata(path).is_ok()),
+    )
+}
+
+/// Waits until the builds whose derivation contains `pattern` are done, for `ntop wait`. If none
+/// is running, it waits for one to start, unless `pattern` is a derivation that's already built.
+/// What's going on is printed to stderr, so that scripts can keep stdout to themselves. Fails if
+/// a build on this machine finished without its outputs, which is what a failed build looks like
+/// from the outside.
+pub async fn wait(sources: &Sources, pattern: &str, interval: Duration) -> anyhow::Result<()> {
+    let pattern = store::strip_store_dir(pattern).unwrap_or(pattern);
+    let mut known: HashMap<(Option<String>, usize), ps::Build> = HashMap::new();
+    let mut first = true;
+    let mut failing = false;
+    let mut failed = 0;
+    let mut ticks = time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let builds = match ps::get_merged(sources).await {
+            Ok(builds) => builds,
+            Err(err) => {
+                if !failing {
+                    eprintln!("Couldn't get builds: {err:#}");
+                }
+                failing = true;
+                continue;
+            }
+        };
+        failing = false;
+
+        let now = Utc::now();
+        let mut current = HashMap::new();
+        for build in builds
+            .into_iter()
+            .filter(|b| b.derivation.contains(pattern))
+        {
+            let key = (build.host.clone(), build.nix_pid);
+            if !known.contains_key(&key) {
+                let elapsed = show_elapsed(build.elapsed(now));
+                eprintln!("{} is building, for {elapsed} so far", describe(&build));
+            }
+            current.insert(key, build);
+        }
+
+        for (key, build) in &known {
+            if current.contains_key(key) {
+                continue;
+            }
+            let elapsed = show_elapsed(build.elapsed(now));
+            // the outputs can take a moment to show up after the builder is gone
+            let mut worked = outputs_exist(&build.derivation);
+            if build.host.is_none() && worked == Some(false) {
+                time::sleep(Duration::from_secs(1)).await;
+                worked = outputs_exist(&build.derivation);
+            }
+            if build.host.is_some() || worked != Some(false) {
+                eprintln!("{} finished after {elapsed}", describe(build));
+            } else {
+                eprintln!("{} failed after {elapsed}", describe(build));
+                failed += 1;
+            }
+        }
+
+        if current.is_empty() {
+            if !known.is_empty() {
+                break;
+            }
+            if first {
+                if pattern.ends_with(".drv") && outputs_exist(pattern) == Some(true) {
+                    eprintln!("{pattern} is built already");
+                    return Ok(());
+                }
+                eprintln!("Waiting for a build of {pattern} to start");
+            }
+        }
+        known = current;
+        first = false;
+    }
+
+    match failed {
+        0 => Ok(()),
+        1 => anyhow::bail!("the build failed"),
+        n => anyhow::bail!("{n} of the builds failed"),
+    }
+}
+
+/// Lays out a report as plain text, columns separated by two spaces.
+pub fn report(table: &archive::Table) -> String {
+    let header: Vec<String> = table.header.iter().map(|h| h.to_uppercase()).collect();
+    let mut widths = vec![0; header.len()];
+    for row in std::iter::once(&header).chain(&table.rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    for row in std::iter::once(&header).chain(&table.rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        _ = writeln!(output, "{}", line.join("  ").trim_end());
+    }
+    output
+}
+
+/// Prints builds from earlier runs, for `ntop history`.
+pub fn history(report: Report, limit: usize, json: bool) -> anyhow::Result<()> {
+    let records = archive::load()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+    } else if records.is_empty() {
+        println!("No builds saved yet");
+    } else {
+        print!("{}", self::report(&report.table(&records, limit)));
+    }
+    Ok(())
+}
+
+/// Saves an [`export::snapshot`] of what the sources say on SIGUSR1, for the modes without the
+/// UI. Where it went is printed to stderr, which is usually the journal on a server.
+pub fn snapshot_on_sigusr1(sources: Sources) {
+    let Ok(mut usr1) = signal(SignalKind::user_defined1()) else {
+        tracing::warn!("can't listen for SIGUSR1");
+        return;
+    };
+    tokio::spawn(async move {
+        while usr1.recv().await.is_some() {
+            // only one look, so no CPU usage
+            let result = ps::get_merged(&sources)
+                .await
+                .and_then(|builds| export::snapshot(&builds, &CpuUsage::default()));
+            match result {
+                Ok(path) => eprintln!("Saved the builds to {}", path.display()),
+                Err(err) => eprintln!("Couldn't save the builds: {err:#}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,47 @@
+//! CPU priority (nice value) of builds. Linux derives I/O priority from it unless it was set
+//! separately, so this deprioritizes disk access as well.
+
+use crate::ps;
+use std::{fs, io};
+
+pub const MIN_NICE: i32 = -20;
+pub const MAX_NICE: i32 = 19;
+
+/// Nice value of a process, from `/proc/<pid>/stat`.
+pub fn nice(pid: usize) -> Option<i32> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // the command name is in parens and can contain anything, so skip past the last one
+    let (_, rest) = stat.rsplit_once(')')?;
+    // field 19 in proc(5), minus the two we skipped
+    rest.split_whitespace().nth(16)?.parse().ok()
+}
+
+pub fn set_nice(pid: usize, nice: i32) -> io::Result<()> {
+    let pid = libc::id_t::try_from(pid)
+        .ok()
+        .filter(|pid| *pid > 0)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid pid"))?;
+
+    // SAFETY: setpriority(2) doesn't touch our memory
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Changes the nice value of every process in a build by `delta`, starting from the one of the
+/// main process, and returns the new one. Lowering it needs root. Keeps going on failure like
+/// [`crate::signal::send_all`], but returns the first error.
+pub fn renice_all(build: &ps::Build, delta: i32) -> io::Result<i32> {
+    let current = nice(build.main_pid).unwrap_or(0);
+    let new = (current + delta).clamp(MIN_NICE, MAX_NICE);
+
+    let mut result = Ok(new);
+    for process in &build.processes {
+        if let Err(err) = set_nice(process.pid, new) {
+            result = result.and(Err(err));
+        }
+    }
+    result
+}
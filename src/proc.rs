@@ -0,0 +1,625 @@
+//! Readers for Linux's `/proc`. Per-process stuff returns `None` when the file isn't there
+//! (process exited, or not on Linux at all).
+
+use crate::{cgroup, ps, store};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Reads a `Key:   value` field out of `/proc/<pid>/status`.
+fn status_field(pid: usize, key: &str) -> Option<String> {
+    let status = fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    status.lines().find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        (k == key).then(|| v.trim().to_string())
+    })
+}
+
+/// Resident memory of a process in bytes.
+pub fn rss(pid: usize) -> Option<u64> {
+    let value = status_field(pid, "VmRSS")?;
+    let kb: u64 = value.strip_suffix(" kB")?.trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Bytes a process read from and wrote to storage. Only readable for our own processes or as
+/// root.
+pub fn io(pid: usize) -> Option<ps::Io> {
+    let io = fs::read_to_string(format!("/proc/{pid}/io")).ok()?;
+    let field = |key: &str| -> Option<u64> {
+        io.lines()
+            .find_map(|line| line.strip_prefix(key)?.trim().parse().ok())
+    };
+    Some(ps::Io {
+        read_bytes: field("read_bytes:")?,
+        write_bytes: field("write_bytes:")?,
+    })
+}
+
+/// Network traffic of a process's network namespace, if it isn't ours. Builds in the sandbox
+/// get one of their own, but fixed-output ones (fetchers) and unsandboxed builds use the
+/// machine's, which can't be told apart from everything else. Needs root to see the namespace.
+pub fn net(pid: usize) -> Option<ps::Net> {
+    let namespace = fs::read_link(format!("/proc/{pid}/ns/net")).ok()?;
+    if fs::read_link("/proc/self/ns/net").ok()? == namespace {
+        return None;
+    }
+
+    // two header lines, then `iface: rx_bytes rx_packets ... (8 rx fields) tx_bytes ...`
+    let dev = fs::read_to_string(format!("/proc/{pid}/net/dev")).ok()?;
+    let mut net = ps::Net::default();
+    for line in dev.lines().skip(2) {
+        let Some((iface, counters)) = line.split_once(':') else {
+            continue;
+        };
+        if iface.trim() == "lo" {
+            continue;
+        }
+        let counters: Vec<u64> = counters
+            .split_whitespace()
+            .filter_map(|c| c.parse().ok())
+            .collect();
+        net.received_bytes += counters.first().copied().unwrap_or_default();
+        net.sent_bytes += counters.get(8).copied().unwrap_or_default();
+    }
+    Some(net)
+}
+
+/// First UID used by `auto-allocate-uids`, which doesn't need `nixbld` users to exist.
+const FIRST_AUTO_UID: u32 = 872415232;
+
+/// The bits of a process we need to put builds together.
+#[derive(Debug)]
+struct Process {
+    pid: usize,
+    parent_pid: usize,
+    uid: u32,
+    /// CPU seconds.
+    utime: f64,
+    stime: f64,
+    /// Clock ticks since boot.
+    start_ticks: u64,
+    argv: Vec<String>,
+}
+
+impl Process {
+    fn read(pid: usize) -> Option<Self> {
+        let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // the command name is in parens and can contain anything, so skip past the last one
+        let (_, rest) = stat.rsplit_once(')')?;
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        let ticks = clock_ticks();
+
+        Some(Self {
+            pid,
+            // field numbers are from proc(5), minus the two we skipped
+            parent_pid: fields.get(1)?.parse().ok()?,
+            uid: uid(pid)?,
+            utime: fields.get(11)?.parse::<f64>().ok()? / ticks,
+            stime: fields.get(12)?.parse::<f64>().ok()? / ticks,
+            start_ticks: fields.get(19)?.parse().ok()?,
+            argv: argv(pid)?,
+        })
+    }
+
+    fn program(&self) -> Option<&str> {
+        self.argv.first()?.rsplit('/').next()
+    }
+
+    fn to_build_process(&self) -> ps::BuildProcess {
+        ps::BuildProcess {
+            argv: self.argv.clone(),
+            parent_pid: Some(self.parent_pid),
+            pid: self.pid,
+            stime: Some(self.stime),
+            utime: Some(self.utime),
+            rss: rss(self.pid),
+            io: io(self.pid),
+        }
+    }
+}
+
+fn all_processes() -> io::Result<Vec<Process>> {
+    Ok(fs::read_dir("/proc")?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .filter_map(Process::read)
+        .collect())
+}
+
+/// A process and everything under it, parents before children.
+fn subtree(processes: &[Process], pid: usize) -> Vec<&Process> {
+    let mut tree: Vec<&Process> = processes.iter().filter(|p| p.pid == pid).collect();
+    let mut i = 0;
+    while let Some(parent) = tree.get(i).map(|p| p.pid) {
+        tree.extend(processes.iter().filter(|p| p.parent_pid == parent));
+        i += 1;
+    }
+    tree
+}
+
+fn clock_ticks() -> f64 {
+    // SAFETY: sysconf has no preconditions
+    unsafe { libc::sysconf(libc::_SC_CLK_TCK) as f64 }
+}
+
+/// Boot time as a unix timestamp.
+fn boot_time() -> Option<u64> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// UIDs of the `nixbld*` users from `/etc/passwd`.
+fn build_users() -> HashSet<u32> {
+    let passwd = fs::read_to_string("/etc/passwd").unwrap_or_default();
+    passwd
+        .lines()
+        .filter(|line| line.starts_with("nixbld"))
+        .filter_map(|line| line.split(':').nth(2)?.parse().ok())
+        .collect()
+}
+
+/// Guesses the derivation from the `out` variable of the builder, which is only readable as
+/// root (or the build user). The hash is the one of the output and not of the derivation, so it's
+/// only good for showing the name.
+fn derivation(pid: usize) -> String {
+    let environ = fs::read(format!("/proc/{pid}/environ")).unwrap_or_default();
+    let out = environ.split(|b| *b == 0).find_map(|var| {
+        let path = var.strip_prefix(b"out=")?;
+        path.strip_prefix(store::store_dir().as_bytes())?
+            .strip_prefix(b"/")
+    });
+
+    match out {
+        Some(out) => format!("{}.drv", String::from_utf8_lossy(out)),
+        None => format!("{}-unknown.drv", "0".repeat(32)),
+    }
+}
+
+/// Puts builds together from processes running as build users, for when `nix ps` isn't
+/// available. A build is a tree of processes whose root is a build user process started by a
+/// process that isn't one (the Nix daemon, or Nix itself for single-user installs).
+pub fn builds() -> io::Result<ps::Output> {
+    let users = build_users();
+    let is_builder = |uid: u32| users.contains(&uid) || uid >= FIRST_AUTO_UID;
+
+    let processes = all_processes()?;
+    let by_pid: HashMap<usize, &Process> = processes.iter().map(|p| (p.pid, p)).collect();
+    let boot_time = boot_time().unwrap_or_default() as f64;
+    let ticks = clock_ticks();
+
+    let builds = processes
+        .iter()
+        .filter(|p| is_builder(p.uid))
+        .filter(|p| {
+            by_pid
+                .get(&p.parent_pid)
+                .is_none_or(|parent| !is_builder(parent.uid))
+        })
+        .map(|main| ps::Build {
+            derivation: derivation(main.pid),
+            main_pid: main.pid,
+            nix_pid: main.parent_pid,
+            // everything under the main process, whatever user it runs as
+            processes: subtree(&processes, main.pid)
+                .into_iter()
+                .map(Process::to_build_process)
+                .collect(),
+            start_time: Some(boot_time + main.start_ticks as f64 / ticks),
+            host: None,
+            cgroup: cgroup::stats(main.pid),
+            net: net(main.pid),
+            delegated: false,
+        })
+        .collect();
+
+    Ok(builds)
+}
+
+/// A store path that Nix is writing to outside of a build, which is pretty much always a
+/// substitution (download from a binary cache).
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    /// The Nix process doing it, usually a daemon worker.
+    pub pid: usize,
+    pub path: String,
+    /// How much of the path is on disk so far, unpacked.
+    pub bytes: u64,
+}
+
+/// Programs that substitute paths themselves, either the daemon or single-user installs.
+const NIX_PROGRAMS: &[&str] = &["nix-daemon", "nix", "nix-store", "nix-build", "nix-shell"];
+
+/// Finds store paths being written by Nix processes, from their open files. Only works as root,
+/// since the daemon is root and nobody else can look at its file descriptors.
+pub fn substitutions(sizes: &mut DiskUsage) -> Vec<Substitution> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return vec![];
+    };
+
+    let mut substitutions: Vec<Substitution> = vec![];
+    for pid in entries.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok()) {
+        // only the program name, no need for the rest of Process
+        let argv = argv(pid).unwrap_or_default();
+        let program = argv.first().and_then(|arg| arg.rsplit('/').next());
+        if !program.is_some_and(|p| NIX_PROGRAMS.contains(&p)) {
+            continue;
+        }
+
+        for path in store_paths_written(pid) {
+            if substitutions.iter().all(|s| s.path != path) {
+                let bytes = sizes.get(Path::new(&path));
+                substitutions.push(Substitution { pid, path, bytes });
+            }
+        }
+    }
+
+    substitutions.sort_by(|a, b| a.path.cmp(&b.path));
+    substitutions
+}
+
+/// Store paths a process has open files in, skipping the stuff Nix keeps open for other reasons.
+fn store_paths_written(pid: usize) -> Vec<String> {
+    let Ok(fds) = fs::read_dir(format!("/proc/{pid}/fd")) else {
+        return vec![];
+    };
+
+    let mut paths = vec![];
+    for fd in fds.filter_map(Result::ok) {
+        let Ok(target) = fs::read_link(fd.path()) else {
+            continue;
+        };
+        let Some(rest) = target.to_str().and_then(store::strip_store_dir) else {
+            continue;
+        };
+
+        let name = rest.split('/').next().unwrap_or_default();
+        // lock files, derivations being read, and hard link dedup stuff
+        if name.ends_with(".lock") || name.ends_with(".drv") || name.starts_with('.') {
+            continue;
+        }
+
+        let path = store::drv_path(name);
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// How long the size of a path is reused before walking it again.
+const DISK_USAGE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Sizes from `disk_usage`, since walking a big output or unpacked download every refresh adds
+/// up to a lot of I/O.
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    sizes: HashMap<PathBuf, (Instant, u64)>,
+}
+
+impl DiskUsage {
+    /// Bytes in a file or directory tree, at most a few seconds old.
+    pub fn get(&mut self, path: &Path) -> u64 {
+        let now = Instant::now();
+        if let Some((at, bytes)) = self.sizes.get(path)
+            && now.duration_since(*at) < DISK_USAGE_INTERVAL
+        {
+            return *bytes;
+        }
+
+        let bytes = disk_usage(path);
+        // paths that aren't asked about anymore would stay forever otherwise
+        self.sizes
+            .retain(|_, (at, _)| now.duration_since(*at) < DISK_USAGE_INTERVAL * 12);
+        self.sizes.insert(path.to_path_buf(), (now, bytes));
+        bytes
+    }
+}
+
+/// Bytes in a file or directory tree, 0 if it isn't there.
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// PIDs of garbage collections that are running. Builds can't start while they do, which looks a
+/// lot like everything being stuck.
+pub fn garbage_collectors() -> Vec<usize> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return vec![];
+    };
+
+    let mut pids: Vec<usize> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .filter(|pid| argv(*pid).is_some_and(|argv| is_gc(&argv)))
+        .collect();
+    pids.sort();
+    pids
+}
+
+fn is_gc(argv: &[String]) -> bool {
+    let Some((program, args)) = argv.split_first() else {
+        return false;
+    };
+    let has = |arg: &str| args.iter().any(|a| a == arg);
+    match program.rsplit('/').next() {
+        Some("nix-collect-garbage") => true,
+        Some("nix-store") => has("--gc") || has("--delete"),
+        Some("nix") => has("store") && (has("gc") || has("delete")),
+        _ => false,
+    }
+}
+
+/// Command line of a process.
+pub fn argv(pid: usize) -> Option<Vec<String>> {
+    let cmdline = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    Some(
+        cmdline
+            .split(|b| *b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect(),
+    )
+}
+
+/// Working directory of a process, needed to make sense of relative paths it was given.
+pub fn cwd(pid: usize) -> Option<PathBuf> {
+    fs::read_link(format!("/proc/{pid}/cwd")).ok()
+}
+
+pub fn parent(pid: usize) -> Option<usize> {
+    status_field(pid, "PPid")?.parse().ok()
+}
+
+/// Real UID of a process.
+pub fn uid(pid: usize) -> Option<u32> {
+    status_field(pid, "Uid")?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// The user a build runs as.
+#[derive(Debug, Clone)]
+pub struct BuildUser {
+    pub uid: u32,
+    /// `None` for UIDs from `auto-allocate-uids`, they aren't in `/etc/passwd`.
+    pub name: Option<String>,
+}
+
+impl BuildUser {
+    pub fn of(pid: usize) -> Option<Self> {
+        let uid = uid(pid)?;
+        Some(Self {
+            uid,
+            name: user_name(uid),
+        })
+    }
+
+    pub fn is_auto(&self) -> bool {
+        self.uid >= FIRST_AUTO_UID
+    }
+
+    /// The name, or the UID if there's none.
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.uid.to_string())
+    }
+}
+
+pub fn hostname() -> Option<String> {
+    let name = fs::read_to_string("/proc/sys/kernel/hostname").ok()?;
+    Some(name.trim().to_string())
+}
+
+/// Looks up a user name in `/etc/passwd`.
+pub fn user_name(uid: u32) -> Option<String> {
+    let passwd = fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)?.parse() == Ok(uid)).then(|| name.to_string())
+    })
+}
+
+/// Environment of a process, sorted by name. Only readable for our own processes or as root, so
+/// this keeps the error around to show.
+pub fn environ(pid: usize) -> io::Result<Vec<(String, String)>> {
+    let environ = fs::read(format!("/proc/{pid}/environ"))?;
+    let mut vars: Vec<_> = environ
+        .split(|b| *b == 0)
+        .filter(|var| !var.is_empty())
+        .map(|var| {
+            let var = String::from_utf8_lossy(var);
+            match var.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => (var.into_owned(), String::new()),
+            }
+        })
+        .collect();
+    vars.sort();
+    Ok(vars)
+}
+
+/// Finds builds that were handed off to remote builders. The derivations come from the log files
+/// the Nix process running the build hooks has open (so this needs root for the daemon), and the
+/// machines from the `ssh` under each hook. `known` are the builds we already know about, builds
+/// running here have their log files open too.
+pub fn delegated_builds(known: &[ps::Build]) -> Vec<ps::Build> {
+    let Ok(processes) = all_processes() else {
+        return vec![];
+    };
+    let boot_time = boot_time().unwrap_or_default() as f64;
+    let ticks = clock_ticks();
+
+    // only hooks that are connected to a machine have taken a build, there's also an idle one
+    let mut hooks: HashMap<usize, Vec<(&Process, String)>> = HashMap::new();
+    for hook in processes.iter().filter(|p| is_build_hook(&p.argv)) {
+        let host = subtree(&processes, hook.pid)
+            .into_iter()
+            .filter(|p| p.program() == Some("ssh"))
+            .find_map(|p| ssh_host(&p.argv));
+        if let Some(host) = host {
+            hooks.entry(hook.parent_pid).or_default().push((hook, host));
+        }
+    }
+
+    let mut builds = vec![];
+    for (nix_pid, mut hooks) in hooks {
+        // Nix only starts a new hook once the previous one took a build, and opens the log right
+        // after, so both are in the same order
+        hooks.sort_by_key(|(hook, _)| hook.start_ticks);
+        let logs = build_logs(nix_pid)
+            .into_iter()
+            .filter(|(derivation, _)| !known.iter().any(|b| &b.derivation == derivation));
+
+        for ((hook, host), (derivation, opened)) in hooks.into_iter().zip(logs) {
+            builds.push(ps::Build {
+                derivation,
+                main_pid: hook.pid,
+                nix_pid: hook.pid,
+                processes: subtree(&processes, hook.pid)
+                    .into_iter()
+                    .map(Process::to_build_process)
+                    .collect(),
+                start_time: Some(opened.unwrap_or(boot_time + hook.start_ticks as f64 / ticks)),
+                host: Some(host),
+                cgroup: None,
+                net: None,
+                delegated: true,
+            });
+        }
+    }
+    builds
+}
+
+fn is_build_hook(argv: &[String]) -> bool {
+    match argv.first().and_then(|arg| arg.rsplit('/').next()) {
+        Some("nix") => argv.get(1).is_some_and(|arg| arg == "__build-remote"),
+        // before it was folded into `nix`
+        Some("build-remote") => true,
+        _ => false,
+    }
+}
+
+/// `ssh` options that take a value.
+const SSH_OPTIONS_WITH_VALUE: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+/// The machine an `ssh` command line connects to, without the user.
+fn ssh_host(argv: &[String]) -> Option<String> {
+    let mut args = argv.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(flags) = arg.strip_prefix('-') else {
+            return Some(arg.rsplit('@').next()?.to_string());
+        };
+        // like `-i key`, but not `-ikey` or `-oPort=22`
+        if flags
+            .char_indices()
+            .find(|(_, c)| SSH_OPTIONS_WITH_VALUE.contains(*c))
+            .is_some_and(|(i, _)| i == flags.len() - 1)
+        {
+            args.next();
+        }
+    }
+    None
+}
+
+/// Derivations a Nix process has build logs open for, oldest log first, with when the log was
+/// created (if the filesystem knows).
+fn build_logs(pid: usize) -> Vec<(String, Option<f64>)> {
+    let Ok(fds) = fs::read_dir(format!("/proc/{pid}/fd")) else {
+        return vec![];
+    };
+
+    let mut logs = vec![];
+    for fd in fds.filter_map(Result::ok) {
+        let Ok(target) = fs::read_link(fd.path()) else {
+            continue;
+        };
+        // like `<log dir>/ab/cdef...-name.drv.bz2`, where `abcdef...` is the hash
+        let Ok(rest) = target.strip_prefix(store::drv_log_dir()) else {
+            continue;
+        };
+        let Some(name) = rest.to_str().map(|r| r.replace('/', "")) else {
+            continue;
+        };
+        let name = name.strip_suffix(".bz2").unwrap_or(&name);
+        if !name.ends_with(".drv") {
+            continue;
+        }
+
+        let created = fs::metadata(&target)
+            .and_then(|m| m.created())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|t| t.as_secs_f64());
+        let number: usize = fd
+            .file_name()
+            .to_str()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0);
+        logs.push((name.to_string(), created, number));
+    }
+
+    // fd numbers get reused, so they're only a fallback for when there's no creation time
+    logs.sort_by(|a, b| {
+        a.1.partial_cmp(&b.1)
+            .unwrap_or(Ordering::Equal)
+            .then(a.2.cmp(&b.2))
+    });
+    logs.into_iter()
+        .map(|(name, created, _)| (name, created))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn ssh_host_skips_option_values() {
+        let ssh = argv(&["ssh", "-i", "key", "-p", "2222", "builder@b1", "nix-daemon"]);
+        assert_eq!(ssh_host(&ssh).as_deref(), Some("b1"));
+        let ssh = argv(&["ssh", "-x", "-a", "-o", "BatchMode=yes", "b2", "nix-store"]);
+        assert_eq!(ssh_host(&ssh).as_deref(), Some("b2"));
+    }
+
+    #[test]
+    fn ssh_host_attached_values() {
+        let ssh = argv(&["ssh", "-ikey", "-oPort=22", "b1"]);
+        assert_eq!(ssh_host(&ssh).as_deref(), Some("b1"));
+        // grouped flags, the last one taking a value
+        let ssh = argv(&["ssh", "-vi", "key", "root@b2"]);
+        assert_eq!(ssh_host(&ssh).as_deref(), Some("b2"));
+    }
+
+    #[test]
+    fn ssh_host_missing() {
+        assert_eq!(ssh_host(&argv(&["ssh"])), None);
+        assert_eq!(ssh_host(&argv(&["ssh", "-i", "key"])), None);
+    }
+}
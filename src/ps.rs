@@ -1,45 +1,278 @@
-use chrono::{DateTime, Utc};
-use serde::Deserialize;
-use tokio::process::Command;
+use crate::{
+    cgroup,
+    source::{DataSource, Sources},
+    store::DrvName,
+};
+use anyhow::Context;
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
 
-#[derive(Debug, Deserialize)]
+// unknown fields are ignored (serde's default), so newer nix versions adding stuff is fine
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BuildProcess {
+    #[serde(default, deserialize_with = "nullable")]
     pub argv: Vec<String>,
-    pub parent_pid: usize,
+    #[serde(default)]
+    pub parent_pid: Option<usize>,
     pub pid: usize,
-    pub stime: f64,
-    pub utime: f64,
+    #[serde(default)]
+    pub stime: Option<f64>,
+    #[serde(default)]
+    pub utime: Option<f64>,
     // !!! other stuff might be null !!!
-    // actually I don't know, even these might be null as well...
-    // BUT I checked, at least on Linux and macOS, these seem to not be null
+    // checked on Linux and macOS these aren't, but nix doesn't promise anything, so only pid is
+    // required. a process without one gets dropped instead of failing the whole build
+    /// Resident memory in bytes, filled in by us (not part of `nix ps` output).
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub rss: Option<u64>,
+    /// Bytes read and written so far, also filled in by us.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub io: Option<Io>,
 }
 
-#[derive(Debug, Deserialize)]
+impl BuildProcess {
+    /// User and system CPU time in seconds, if nix told us both.
+    pub fn cpu_seconds(&self) -> Option<f64> {
+        Some(self.utime? + self.stime?)
+    }
+}
+
+/// Storage I/O of a process, from `/proc/<pid>/io`. Doesn't count reads served from the page
+/// cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Io {
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+impl std::ops::Add for Io {
+    type Output = Io;
+
+    fn add(self, other: Io) -> Io {
+        Io {
+            read_bytes: self.read_bytes + other.read_bytes,
+            write_bytes: self.write_bytes + other.write_bytes,
+        }
+    }
+}
+
+/// Network traffic of a build that has a network namespace to itself, loopback left out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Net {
+    pub received_bytes: u64,
+    pub sent_bytes: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Build {
     pub derivation: String,
     pub main_pid: usize,
     pub nix_pid: usize,
+    #[serde(default, deserialize_with = "skip_invalid")]
     pub processes: Vec<BuildProcess>,
-    pub start_time: f64,
+    #[serde(default)]
+    pub start_time: Option<f64>,
     // same warning as above
     // only add stuff that we need !!!
+    /// Machine the build runs on, `None` for this one. Filled in by us.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Totals from the build's cgroup, if Nix put it in one. Filled in by us.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<cgroup::Stats>,
+    /// Only for sandboxed builds, the others share the network with everything else. Filled in
+    /// by us.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub net: Option<Net>,
+    /// Handed off to a remote builder by the local Nix, so it's not in anyone's `nix ps`. Only
+    /// the local side of it (the build hook and its `ssh`) is in `processes`, if anything.
+    #[serde(skip_deserializing, skip_serializing_if = "std::ops::Not::not")]
+    pub delegated: bool,
 }
 
 impl Build {
-    pub fn started(&self) -> DateTime<Utc> {
-        DateTime::from_timestamp_secs(self.start_time as i64)
-            .expect("failed to convert millis to datetime??")
+    /// When the build started, if nix knows.
+    pub fn started(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_secs(self.start_time? as i64)
+    }
+
+    /// How long the build has been running, if we know when it started.
+    pub fn elapsed(&self, now: DateTime<Utc>) -> Option<TimeDelta> {
+        Some(now - self.started()?)
+    }
+
+    /// Splits the derivation into package name and version.
+    pub fn name(&self) -> (&str, &str) {
+        let name = self.drv_name();
+        (name.pname, name.version)
+    }
+
+    /// Everything in the derivation's name: hash, package name and version.
+    pub fn drv_name(&self) -> DrvName<'_> {
+        DrvName::parse(&self.derivation)
+    }
+
+    /// PIDs are only unique per machine, so builds are told apart by both.
+    pub fn key(&self) -> (Option<&str>, usize) {
+        (self.host.as_deref(), self.nix_pid)
+    }
+
+    pub fn process(&self, pid: usize) -> Option<&BuildProcess> {
+        self.processes.iter().find(|p| p.pid == pid)
+    }
+
+    /// Total CPU time (user and system) of the build. Without a cgroup, this only counts
+    /// processes that are still around, the ones that already exited are lost.
+    pub fn cpu_time(&self) -> TimeDelta {
+        let seconds = match self.cgroup {
+            Some(cgroup) => cgroup.cpu_seconds,
+            None => self
+                .processes
+                .iter()
+                .filter_map(BuildProcess::cpu_seconds)
+                .sum(),
+        };
+        TimeDelta::milliseconds((seconds * 1000.0) as i64)
+    }
+
+    /// Bytes read and written by the processes that are still around.
+    pub fn io(&self) -> Option<Io> {
+        self.processes
+            .iter()
+            .filter_map(|p| p.io)
+            .reduce(|a, b| a + b)
+    }
+
+    /// Memory used by the build: from the cgroup if there is one, otherwise the resident memory
+    /// of all processes added up.
+    pub fn rss(&self) -> Option<u64> {
+        if let Some(memory) = self.cgroup.and_then(|c| c.memory) {
+            return Some(memory);
+        }
+        self.processes
+            .iter()
+            .filter_map(|p| p.rss)
+            .reduce(|a, b| a + b)
     }
 }
 
 pub type Output = Vec<Build>;
 
-// meant to use like ps::get() instead of use ps::get and then get()
-pub async fn get() -> anyhow::Result<Output> {
-    let cmd = Command::new("nix").arg("ps").arg("--json").output().await?;
-    let mut data: Output = serde_json::from_slice(&cmd.stdout)?;
-    data.sort_by(|a, b| a.derivation.cmp(&b.derivation));
-    Ok(data)
+/// Parses `nix ps --json` output. Builds that don't make sense are skipped, so one odd build
+/// doesn't hide all the others.
+pub fn parse(json: &[u8]) -> serde_json::Result<Output> {
+    let builds: Vec<serde_json::Value> = serde_json::from_slice(json)?;
+    Ok(from_values(builds))
+}
+
+/// Same as [`parse`], for output that was already parsed into JSON values.
+pub fn from_values<T: DeserializeOwned>(values: Vec<serde_json::Value>) -> Vec<T> {
+    values
+        .into_iter()
+        .filter_map(|value| {
+            serde_json::from_value(value)
+                .inspect_err(|err| tracing::warn!(%err, "skipping an entry that doesn't parse"))
+                .ok()
+        })
+        .collect()
+}
+
+/// Treats `null` the same as a missing field.
+fn nullable<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Drops entries that don't deserialize instead of failing the whole list.
+fn skip_invalid<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let values: Vec<serde_json::Value> = nullable(deserializer)?;
+    Ok(from_values(values))
+}
+
+/// Gets builds from all sources at once. Results are in the same order as the sources.
+pub async fn get(sources: &[Box<dyn DataSource>]) -> Vec<anyhow::Result<Output>> {
+    let results = join_all(sources.iter().map(|source| async move {
+        let host = source.host();
+        let started = Instant::now();
+        let result = source.fetch().await;
+        match &result {
+            Ok(data) => {
+                tracing::debug!(host, builds = data.len(), elapsed = ?started.elapsed(), "fetched");
+            }
+            Err(err) => {
+                tracing::warn!(host, elapsed = ?started.elapsed(), "fetching failed: {err:#}")
+            }
+        }
+        let mut data = match host {
+            Some(host) => result.with_context(|| format!("on {host}"))?,
+            None => result?,
+        };
+
+        for build in &mut data {
+            build.host = host.map(str::to_string);
+        }
+        Ok(data)
+    }));
+    results.await
+}
+
+/// Like [`get`], but fails if any of the sources does.
+pub async fn get_merged(sources: &[Box<dyn DataSource>]) -> anyhow::Result<Output> {
+    let mut builds = vec![];
+    for result in get(sources).await {
+        builds.extend(result?);
+    }
+    Ok(builds)
+}
+
+/// [`get_merged`] for servers, where clients decide how often it runs. The last fetch is reused
+/// until it's `max_age` old, and requests coming in during a fetch wait for it instead of
+/// starting their own.
+#[derive(Debug)]
+pub struct Cached {
+    sources: Sources,
+    max_age: Duration,
+    /// When the last fetch finished, and what it got. Errors as text, to hand out more than once.
+    last: Mutex<Option<(Instant, Result<Output, String>)>>,
+}
+
+impl Cached {
+    pub fn new(sources: Sources, max_age: Duration) -> Self {
+        Self {
+            sources,
+            max_age,
+            last: Mutex::default(),
+        }
+    }
+
+    /// The builds, and when they were fetched.
+    pub async fn get(&self) -> anyhow::Result<(Instant, Output)> {
+        let mut last = self.last.lock().await;
+        if let Some((at, result)) = &*last
+            && at.elapsed() < self.max_age
+        {
+            return Ok((*at, result.clone().map_err(anyhow::Error::msg)?));
+        }
+
+        let result = get_merged(&self.sources)
+            .await
+            .map_err(|err| format!("{err:#}"));
+        let at = Instant::now();
+        *last = Some((at, result.clone()));
+        Ok((at, result.map_err(anyhow::Error::msg)?))
+    }
 }
@@ -0,0 +1,132 @@
+use crate::ps;
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Term,
+    Kill,
+    Int,
+    Hup,
+    Quit,
+    Stop,
+    Cont,
+    Usr1,
+    Usr2,
+}
+
+impl Signal {
+    /// The ones worth sending to a build, in the order the signal menu shows them.
+    pub const ALL: [Signal; 9] = [
+        Signal::Term,
+        Signal::Kill,
+        Signal::Int,
+        Signal::Hup,
+        Signal::Quit,
+        Signal::Stop,
+        Signal::Cont,
+        Signal::Usr1,
+        Signal::Usr2,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Term => "SIGTERM",
+            Signal::Kill => "SIGKILL",
+            Signal::Int => "SIGINT",
+            Signal::Hup => "SIGHUP",
+            Signal::Quit => "SIGQUIT",
+            Signal::Stop => "SIGSTOP",
+            Signal::Cont => "SIGCONT",
+            Signal::Usr1 => "SIGUSR1",
+            Signal::Usr2 => "SIGUSR2",
+        }
+    }
+
+    /// What it usually does, for the signal menu.
+    pub fn description(self) -> &'static str {
+        match self {
+            Signal::Term => "ask to stop",
+            Signal::Kill => "stop right away",
+            Signal::Int => "like Ctrl-C",
+            Signal::Hup => "hang up",
+            Signal::Quit => "stop and dump core",
+            Signal::Stop => "pause",
+            Signal::Cont => "resume",
+            Signal::Usr1 => "up to the program",
+            Signal::Usr2 => "up to the program",
+        }
+    }
+
+    /// Parses a name like `TERM` or `SIGTERM`, in any case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_uppercase();
+        let name = name.strip_prefix("SIG").unwrap_or(&name);
+        Self::ALL
+            .into_iter()
+            .find(|signal| &signal.name()[3..] == name)
+    }
+
+    fn number(self) -> libc::c_int {
+        match self {
+            Signal::Term => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+            Signal::Int => libc::SIGINT,
+            Signal::Hup => libc::SIGHUP,
+            Signal::Quit => libc::SIGQUIT,
+            Signal::Stop => libc::SIGSTOP,
+            Signal::Cont => libc::SIGCONT,
+            Signal::Usr1 => libc::SIGUSR1,
+            Signal::Usr2 => libc::SIGUSR2,
+        }
+    }
+}
+
+/// Sends a signal to a single process.
+pub fn send(pid: usize, signal: Signal) -> io::Result<()> {
+    // pid 0 and negative pids mean process groups, never want those
+    let pid = libc::pid_t::try_from(pid)
+        .ok()
+        .filter(|pid| *pid > 0)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid pid"))?;
+
+    // SAFETY: kill(2) doesn't touch our memory
+    if unsafe { libc::kill(pid, signal.number()) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Sends a signal to every process in a build. Keeps going on failure (processes come and go),
+/// but returns the first error.
+pub fn send_all(build: &ps::Build, signal: Signal) -> io::Result<()> {
+    let mut result = Ok(());
+    for process in &build.processes {
+        if let Err(err) = send(process.pid, signal) {
+            result = result.and(Err(err));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_name() {
+        assert_eq!(Signal::from_name("TERM"), Some(Signal::Term));
+        assert_eq!(Signal::from_name("SIGKILL"), Some(Signal::Kill));
+        assert_eq!(Signal::from_name("sigusr1"), Some(Signal::Usr1));
+        assert_eq!(Signal::from_name("Hup"), Some(Signal::Hup));
+    }
+
+    #[test]
+    fn from_name_unknown() {
+        assert_eq!(Signal::from_name(""), None);
+        assert_eq!(Signal::from_name("SIG"), None);
+        assert_eq!(Signal::from_name("SIGFOO"), None);
+        // not one of the ones we send
+        assert_eq!(Signal::from_name("SIGSEGV"), None);
+    }
+}
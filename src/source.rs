@@ -0,0 +1,268 @@
+//! Where builds come from. Each backend implements [`DataSource`], the rest of the app only sees
+//! `nix ps`-shaped [`Output`].
+
+use crate::{
+    enrich, proc,
+    ps::{self, Output},
+};
+use anyhow::Context;
+use futures::{FutureExt, future::BoxFuture};
+use regex::Regex;
+use std::{
+    fmt::Debug,
+    fs, io,
+    path::Path,
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Instant,
+};
+use tokio::process::Command;
+
+/// A provider of active builds.
+pub trait DataSource: Debug + Send + Sync {
+    /// Gets the builds that are running right now.
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<Output>>;
+
+    /// Machine the builds are on, `None` for this one. Builds get tagged with it.
+    fn host(&self) -> Option<&str> {
+        None
+    }
+
+    /// A command that runs `program` on the machine of the builds, for things like following
+    /// logs. `None` if there's no such machine.
+    fn command(&self, program: &str) -> Option<Command> {
+        Some(Command::new(program))
+    }
+}
+
+/// All the sources the app gets builds from, shared with the refresh tasks.
+pub type Sources = Arc<[Box<dyn DataSource>]>;
+
+/// Parses a `--host` argument, `local` being this machine. `store` is passed to Nix as
+/// `--store`, and only derivations matching `filter` are kept.
+pub fn from_host(host: &str, store: Option<&str>, filter: Option<&Regex>) -> Box<dyn DataSource> {
+    let store = store.map(str::to_string);
+    let filter = filter.cloned();
+    match host {
+        "local" | "localhost" => Box::new(Local { store, filter }),
+        host => Box::new(Ssh {
+            host: host.to_string(),
+            store,
+            filter,
+        }),
+    }
+}
+
+/// Drops the builds whose derivation doesn't match, before anything is spent on them.
+fn keep_matching(mut output: Output, filter: Option<&Regex>) -> Output {
+    if let Some(filter) = filter {
+        output.retain(|build| filter.is_match(&build.derivation));
+    }
+    output
+}
+
+/// Adds `--store` to a nix command, if there's a store to use.
+fn with_store(mut command: Command, program: &str, store: Option<&str>) -> Command {
+    if let Some(store) = store
+        && program == "nix"
+    {
+        command.arg("--store").arg(store);
+    }
+    command
+}
+
+/// Whether the builds of a store run on this machine. Remote stores are `ssh://` and
+/// `ssh-ng://`, everything else (paths, `daemon`, `local?root=...`) is here.
+fn is_here(store: &str) -> bool {
+    !store.starts_with("ssh://") && !store.starts_with("ssh-ng://")
+}
+
+/// Runs `nix ps --json` with the given command and parses its output, keeping the builds that
+/// match `filter`.
+async fn nix_ps(mut command: Command, filter: Option<&Regex>) -> anyhow::Result<Output> {
+    command.arg("ps").arg("--json").stdin(Stdio::null());
+    tracing::debug!(command = ?command.as_std(), "running nix ps");
+    let started = Instant::now();
+    let cmd = command.output().await.context("failed to run nix")?;
+    tracing::debug!(
+        status = %cmd.status,
+        elapsed = ?started.elapsed(),
+        bytes = cmd.stdout.len(),
+        "nix ps done"
+    );
+
+    if !cmd.status.success() {
+        let stderr = String::from_utf8_lossy(&cmd.stderr);
+        anyhow::bail!("nix ps exited with {}: {}", cmd.status, stderr.trim());
+    }
+
+    let output = ps::parse(&cmd.stdout)
+        .inspect_err(|err| {
+            let output = String::from_utf8_lossy(&cmd.stdout);
+            tracing::warn!(%err, %output, "nix ps output doesn't parse");
+        })
+        .context("failed to parse nix ps output")?;
+    Ok(keep_matching(output, filter))
+}
+
+/// Whether `nix ps` failed for not being there: no Nix in PATH, or one that's too old for it or
+/// doesn't have `nix-command` turned on. Anything else is a real error worth showing.
+fn no_nix_ps(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(err) = cause.downcast_ref::<io::Error>() {
+            return err.kind() == io::ErrorKind::NotFound;
+        }
+        let message = cause.to_string();
+        message.contains("'ps' is not a recognised command")
+            || message.contains("experimental Nix feature 'nix-command' is disabled")
+    })
+}
+
+/// `nix ps` on this machine, falling back to [`Proc`] when there's no `nix ps` to run.
+#[derive(Debug, Default)]
+pub struct Local {
+    /// Store to ask instead of the default one. One on another machine makes its builds remote.
+    pub store: Option<String>,
+    /// Only derivations matching this are watched.
+    pub filter: Option<Regex>,
+}
+
+impl DataSource for Local {
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<Output>> {
+        async {
+            let nix = self.command("nix").expect("local always has a command");
+            let filter = self.filter.as_ref();
+            if self.host().is_some() {
+                // none of the processes are here to look at
+                return nix_ps(nix, filter).await;
+            }
+            match nix_ps(nix, filter).await {
+                Ok(mut data) => {
+                    let enricher = enrich::local();
+                    for build in &mut data {
+                        enricher.enrich(build);
+                    }
+                    Ok(data)
+                }
+                // older Nix without `nix ps`, or no Nix in PATH at all. the processes are still
+                // around though, at least on Linux. /proc can't tell which store a build is for,
+                // so not with one given
+                Err(err) if self.store.is_none() && no_nix_ps(&err) => {
+                    tracing::debug!("nix ps failed, looking in /proc instead: {err:#}");
+                    Proc.fetch()
+                        .await
+                        .map(|output| keep_matching(output, filter))
+                        .map_err(|_| err)
+                }
+                Err(err) => Err(err),
+            }
+        }
+        .boxed()
+    }
+
+    fn host(&self) -> Option<&str> {
+        self.store.as_deref().filter(|store| !is_here(store))
+    }
+
+    fn command(&self, program: &str) -> Option<Command> {
+        Some(with_store(
+            Command::new(program),
+            program,
+            self.store.as_deref(),
+        ))
+    }
+}
+
+/// `nix ps` on another machine, over `ssh`.
+#[derive(Debug)]
+pub struct Ssh {
+    pub host: String,
+    /// Store to ask on that machine instead of its default one.
+    pub store: Option<String>,
+    /// Only derivations matching this are watched.
+    pub filter: Option<Regex>,
+}
+
+impl DataSource for Ssh {
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<Output>> {
+        let command = self.command("nix").expect("ssh always has a command");
+        nix_ps(command, self.filter.as_ref()).boxed()
+    }
+
+    fn host(&self) -> Option<&str> {
+        Some(&self.host)
+    }
+
+    fn command(&self, program: &str) -> Option<Command> {
+        let mut cmd = Command::new("ssh");
+        // never prompt for passwords, the terminal belongs to the UI
+        cmd.arg("-o")
+            .arg("BatchMode=yes")
+            .arg(&self.host)
+            .arg("--")
+            .arg(program);
+        Some(with_store(cmd, program, self.store.as_deref()))
+    }
+}
+
+/// Build user processes from `/proc`, see [`proc::builds`].
+#[derive(Debug)]
+pub struct Proc;
+
+impl DataSource for Proc {
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<Output>> {
+        async { proc::builds().context("failed to scan /proc") }.boxed()
+    }
+}
+
+/// Plays back `nix ps --json` outputs saved to a file, one per fetch, then keeps showing the last
+/// one. Good for demos and poking at the UI without building anything.
+#[derive(Debug)]
+pub struct Replay {
+    snapshots: Vec<Output>,
+    next: AtomicUsize,
+    filter: Option<Regex>,
+}
+
+impl Replay {
+    pub fn open(path: &Path, filter: Option<Regex>) -> anyhow::Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let snapshots = serde_json::Deserializer::from_str(&text)
+            .into_iter::<Vec<serde_json::Value>>()
+            .map(|snapshot| snapshot.map(ps::from_values))
+            .collect::<Result<Vec<Output>, _>>()
+            .with_context(|| format!("parsing {}", path.display()))?;
+
+        if snapshots.is_empty() {
+            anyhow::bail!("{} has no snapshots in it", path.display());
+        }
+
+        Ok(Self {
+            snapshots,
+            next: AtomicUsize::new(0),
+            filter,
+        })
+    }
+}
+
+impl DataSource for Replay {
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<Output>> {
+        let i = self.next.fetch_add(1, Ordering::Relaxed);
+        let snapshot = self.snapshots[i.min(self.snapshots.len() - 1)].clone();
+        let snapshot = keep_matching(snapshot, self.filter.as_ref());
+        async { Ok(snapshot) }.boxed()
+    }
+
+    /// Tagged so that nobody sends signals to whatever has these PIDs now.
+    fn host(&self) -> Option<&str> {
+        Some("replay")
+    }
+
+    fn command(&self, _program: &str) -> Option<Command> {
+        None
+    }
+}
@@ -0,0 +1,421 @@
+use crate::{glyphs, ps};
+use chrono::TimeDelta;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Instant,
+};
+
+/// A process on some host, `None` being this one.
+type Key = (Option<String>, usize);
+
+/// CPU usage computed from `utime`/`stime` deltas between two consecutive snapshots.
+#[derive(Debug, Default)]
+pub struct CpuUsage {
+    /// Total CPU seconds per process in the previous snapshot.
+    previous: HashMap<Key, f64>,
+    previous_at: Option<Instant>,
+    /// CPU usage per process in percent, 100% being one full core.
+    percent: HashMap<Key, f64>,
+    /// Same as above but for whole builds with a cgroup, by Nix PID.
+    previous_cgroups: HashMap<Key, f64>,
+    cgroup_percent: HashMap<Key, f64>,
+}
+
+impl CpuUsage {
+    /// Diffs a new snapshot against the previous one.
+    pub fn update(&mut self, builds: &[ps::Build]) {
+        let now = Instant::now();
+        let elapsed = self
+            .previous_at
+            .map(|at| now.duration_since(at).as_secs_f64())
+            .filter(|secs| *secs > 0.0);
+
+        let current: HashMap<Key, f64> = builds
+            .iter()
+            .flat_map(|b| b.processes.iter().map(|p| ((b.host.clone(), p.pid), p)))
+            .filter_map(|(key, p)| Some((key, p.cpu_seconds()?)))
+            .collect();
+        let cgroups: HashMap<Key, f64> = builds
+            .iter()
+            .filter_map(|b| Some(((b.host.clone(), b.nix_pid), b.cgroup?.cpu_seconds)))
+            .collect();
+
+        self.percent.clear();
+        self.cgroup_percent.clear();
+        if let Some(elapsed) = elapsed {
+            diff(&self.previous, &current, elapsed, &mut self.percent);
+            diff(
+                &self.previous_cgroups,
+                &cgroups,
+                elapsed,
+                &mut self.cgroup_percent,
+            );
+        }
+
+        self.previous = current;
+        self.previous_cgroups = cgroups;
+        self.previous_at = Some(now);
+    }
+
+    /// CPU usage of one process of the build.
+    pub fn process(&self, build: &ps::Build, pid: usize) -> Option<f64> {
+        self.percent.get(&(build.host.clone(), pid)).copied()
+    }
+
+    /// CPU usage of the whole build, from its cgroup if it has one, otherwise the sum of all its
+    /// processes.
+    pub fn build(&self, build: &ps::Build) -> Option<f64> {
+        if let Some(percent) = self
+            .cgroup_percent
+            .get(&(build.host.clone(), build.nix_pid))
+        {
+            return Some(*percent);
+        }
+        build
+            .processes
+            .iter()
+            .filter_map(|p| self.process(build, p.pid))
+            .reduce(|a, b| a + b)
+    }
+}
+
+/// Turns CPU seconds into percentages. New keys don't have a previous value, so no usage for
+/// them yet.
+fn diff(
+    previous: &HashMap<Key, f64>,
+    current: &HashMap<Key, f64>,
+    elapsed: f64,
+    percent: &mut HashMap<Key, f64>,
+) {
+    for (key, total) in current {
+        if let Some(previous) = previous.get(key) {
+            let used = (total - previous).max(0.0);
+            percent.insert(key.clone(), used / elapsed * 100.0);
+        }
+    }
+}
+
+/// Read and write rates in bytes per second, computed the same way as [`CpuUsage`].
+#[derive(Debug, Default)]
+pub struct IoUsage {
+    previous: HashMap<Key, ps::Io>,
+    previous_at: Option<Instant>,
+    rates: HashMap<Key, (f64, f64)>,
+}
+
+impl IoUsage {
+    pub fn update(&mut self, builds: &[ps::Build]) {
+        let now = Instant::now();
+        let elapsed = self
+            .previous_at
+            .map(|at| now.duration_since(at).as_secs_f64())
+            .filter(|secs| *secs > 0.0);
+
+        let current: HashMap<Key, ps::Io> = builds
+            .iter()
+            .flat_map(|b| b.processes.iter().map(|p| ((b.host.clone(), p.pid), p)))
+            .filter_map(|(key, p)| Some((key, p.io?)))
+            .collect();
+
+        self.rates.clear();
+        if let Some(elapsed) = elapsed {
+            for (key, io) in &current {
+                if let Some(previous) = self.previous.get(key) {
+                    let read = io.read_bytes.saturating_sub(previous.read_bytes) as f64;
+                    let written = io.write_bytes.saturating_sub(previous.write_bytes) as f64;
+                    self.rates
+                        .insert(key.clone(), (read / elapsed, written / elapsed));
+                }
+            }
+        }
+
+        self.previous = current;
+        self.previous_at = Some(now);
+    }
+
+    /// Read and write rates of one process of the build.
+    pub fn process(&self, build: &ps::Build, pid: usize) -> Option<(f64, f64)> {
+        self.rates.get(&(build.host.clone(), pid)).copied()
+    }
+
+    /// Read and write rates of all processes in the build added up.
+    pub fn build(&self, build: &ps::Build) -> Option<(f64, f64)> {
+        build
+            .processes
+            .iter()
+            .filter_map(|p| self.process(build, p.pid))
+            .reduce(|a, b| (a.0 + b.0, a.1 + b.1))
+    }
+}
+
+/// Download and upload rates of builds in bytes per second, from [`ps::Build::net`].
+#[derive(Debug, Default)]
+pub struct NetUsage {
+    previous: HashMap<Key, ps::Net>,
+    previous_at: Option<Instant>,
+    rates: HashMap<Key, (f64, f64)>,
+}
+
+impl NetUsage {
+    pub fn update(&mut self, builds: &[ps::Build]) {
+        let now = Instant::now();
+        let elapsed = self
+            .previous_at
+            .map(|at| now.duration_since(at).as_secs_f64())
+            .filter(|secs| *secs > 0.0);
+
+        let current: HashMap<Key, ps::Net> = builds
+            .iter()
+            .filter_map(|b| Some(((b.host.clone(), b.nix_pid), b.net?)))
+            .collect();
+
+        self.rates.clear();
+        if let Some(elapsed) = elapsed {
+            for (key, net) in &current {
+                if let Some(previous) = self.previous.get(key) {
+                    let received = net.received_bytes.saturating_sub(previous.received_bytes);
+                    let sent = net.sent_bytes.saturating_sub(previous.sent_bytes);
+                    self.rates.insert(
+                        key.clone(),
+                        (received as f64 / elapsed, sent as f64 / elapsed),
+                    );
+                }
+            }
+        }
+
+        self.previous = current;
+        self.previous_at = Some(now);
+    }
+
+    /// Download and upload rates of the build.
+    pub fn build(&self, build: &ps::Build) -> Option<(f64, f64)> {
+        self.rates
+            .get(&(build.host.clone(), build.nix_pid))
+            .copied()
+    }
+}
+
+/// How many CPU samples to keep per build, a few minutes at the default refresh interval.
+const MAX_SAMPLES: usize = 90;
+
+/// Recent CPU usage of each build, for the sparklines.
+#[derive(Debug, Default)]
+pub struct CpuHistory {
+    /// Oldest first, by host and Nix PID.
+    samples: HashMap<Key, VecDeque<f64>>,
+}
+
+impl CpuHistory {
+    /// Adds a sample for every build that has one, and forgets the builds that are gone.
+    pub fn update(&mut self, builds: &[ps::Build], cpu: &CpuUsage) {
+        self.samples
+            .retain(|(host, pid), _| builds.iter().any(|b| b.key() == (host.as_deref(), *pid)));
+
+        for build in builds {
+            let Some(percent) = cpu.build(build) else {
+                continue;
+            };
+            let samples = self
+                .samples
+                .entry((build.host.clone(), build.nix_pid))
+                .or_default();
+            samples.push_back(percent);
+            if samples.len() > MAX_SAMPLES {
+                samples.pop_front();
+            }
+        }
+    }
+
+    pub fn build(&self, build: &ps::Build) -> Option<&VecDeque<f64>> {
+        self.samples.get(&(build.host.clone(), build.nix_pid))
+    }
+}
+
+/// Draws the last `width` samples with block characters. The scale goes up to the highest sample
+/// but at least one full core, so that an idle build doesn't look busy.
+pub fn sparkline(samples: &VecDeque<f64>, width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    const ASCII_BARS: [char; 8] = [' ', '.', ':', '-', '=', '+', '*', '#'];
+    let bars = if glyphs::ascii() { ASCII_BARS } else { BARS };
+    let shown = samples.iter().skip(samples.len().saturating_sub(width));
+    let max = shown.clone().fold(100.0, |a: f64, b| a.max(*b));
+    shown
+        .map(|percent| {
+            let level = (percent / max * (bars.len() - 1) as f64).round() as usize;
+            bars[level.min(bars.len() - 1)]
+        })
+        .collect()
+}
+
+/// A bar of `width` characters filled in as far as `done` is of `expected`.
+pub fn progress_bar(done: u64, expected: u64, width: usize) -> String {
+    let filled = if expected == 0 {
+        0
+    } else {
+        ((done.min(expected) as f64 / expected as f64) * width as f64).round() as usize
+    };
+    format!(
+        "{}{}",
+        glyphs::pick("█", "#").repeat(filled),
+        glyphs::pick("░", "-").repeat(width - filled)
+    )
+}
+
+/// Formats a CPU percentage for display, `-` when unknown.
+pub fn show_cpu(percent: Option<f64>) -> String {
+    match percent {
+        Some(percent) => format!("{percent:.0}%"),
+        None => "-".to_string(),
+    }
+}
+
+/// Formats bytes per second for display, `-` when unknown.
+pub fn show_rate(rate: Option<f64>) -> String {
+    match rate {
+        Some(rate) => format!("{}/s", show_bytes(Some(rate as u64))),
+        None => "-".to_string(),
+    }
+}
+
+/// Formats a byte count for display, `-` when unknown.
+pub fn show_bytes(bytes: Option<u64>) -> String {
+    let Some(bytes) = bytes else {
+        return "-".to_string();
+    };
+
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 || value >= 10.0 {
+        format!("{value:.0}{}", UNITS[unit])
+    } else {
+        format!("{value:.1}{}", UNITS[unit])
+    }
+}
+
+/// Like [`show_duration`], `-` when unknown.
+pub fn show_elapsed(duration: Option<TimeDelta>) -> String {
+    duration
+        .map(show_duration)
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Formats a duration as its two most significant components, like `1h 23m`.
+pub fn show_duration(duration: TimeDelta) -> String {
+    let mut duration = duration;
+    let mut components = vec![];
+
+    if duration.num_days() > 0 {
+        components.push(format!("{}d", duration.num_days()));
+        duration = duration - TimeDelta::days(duration.num_days());
+    }
+
+    if duration.num_hours() > 0 {
+        components.push(format!("{}h", duration.num_hours()));
+        duration = duration - TimeDelta::hours(duration.num_hours());
+    }
+
+    if duration.num_minutes() > 0 && components.len() < 2 {
+        components.push(format!("{}m", duration.num_minutes()));
+        duration = duration - TimeDelta::minutes(duration.num_minutes());
+    }
+
+    if (duration.num_seconds() > 0 && components.len() < 2) || components.is_empty() {
+        components.push(format!("{}s", duration.num_seconds().max(0)));
+    }
+
+    components.join(" ")
+}
+
+/// Reads a duration the way [`show_duration`] writes them, like `1h 30m`, `90s` or `2d`. The
+/// spaces are optional and a plain number is seconds.
+pub fn parse_duration(text: &str) -> Result<TimeDelta, String> {
+    let text = text.trim();
+    let too_long = || format!("{text:?} is too long");
+    if let Ok(seconds) = text.parse::<u64>() {
+        return i64::try_from(seconds)
+            .ok()
+            .and_then(TimeDelta::try_seconds)
+            .ok_or_else(too_long);
+    }
+
+    let mut total = TimeDelta::zero();
+    let mut number = String::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        // only digits get here, so this fails on an empty or huge number, never a negative one
+        let n: i64 = number
+            .parse()
+            .map_err(|_| format!("expected a number before {c:?} in {text:?}"))?;
+        let part = match c {
+            'd' => TimeDelta::try_days(n),
+            'h' => TimeDelta::try_hours(n),
+            'm' => TimeDelta::try_minutes(n),
+            's' => TimeDelta::try_seconds(n),
+            _ => return Err(format!("unknown unit {c:?} in {text:?}, use d, h, m or s")),
+        };
+        total = part
+            .and_then(|part| total.checked_add(&part))
+            .ok_or_else(too_long)?;
+        number.clear();
+    }
+    if !number.is_empty() || text.is_empty() {
+        return Err(format!("{text:?} needs a unit at the end, like 30m"));
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_valid_durations() {
+        assert_eq!(parse_duration("90"), Ok(TimeDelta::seconds(90)));
+        assert_eq!(parse_duration("30m"), Ok(TimeDelta::minutes(30)));
+        assert_eq!(parse_duration("1h 30m"), Ok(TimeDelta::minutes(90)),);
+        assert_eq!(
+            parse_duration(" 2d1h5s "),
+            Ok(TimeDelta::days(2) + TimeDelta::hours(1) + TimeDelta::seconds(5)),
+        );
+        assert_eq!(parse_duration("0s"), Ok(TimeDelta::zero()));
+    }
+
+    #[test]
+    fn parse_round_trips_show_duration() {
+        let duration = TimeDelta::hours(3) + TimeDelta::minutes(12);
+        assert_eq!(parse_duration(&show_duration(duration)), Ok(duration));
+    }
+
+    #[test]
+    fn parse_rejects_negative() {
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("-5").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_overflow() {
+        assert!(parse_duration("99999999999999d").is_err());
+        assert!(parse_duration("99999999999999999999").is_err());
+        assert!(parse_duration("9223372036854775807s").is_err());
+        // each part fits, not all of them together
+        assert!(parse_duration("106751991167d 106751991167d").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_empty_and_missing_units() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("   ").is_err());
+        assert!(parse_duration("1h 30").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5w").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Store paths: where the store is, and what's in a derivation's name.
+
+use std::{env, sync::OnceLock};
+
+/// Characters of Nix's base-32, which store path hashes are written in.
+const HASH_CHARS: &str = "0123456789abcdfghijklmnpqrsvwxyz";
+const HASH_LEN: usize = 32;
+
+/// A directory from the environment, if it's set to something.
+fn env_dir(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| dir.trim_end_matches('/').to_string())
+}
+
+/// The store directory, `$NIX_STORE_DIR` like Nix itself, or `/nix/store`.
+pub fn store_dir() -> &'static str {
+    static DIR: OnceLock<String> = OnceLock::new();
+    DIR.get_or_init(|| env_dir("NIX_STORE_DIR").unwrap_or_else(|| "/nix/store".to_string()))
+}
+
+/// Where the daemon puts build logs, as `<first 2 chars of hash>/<rest>.drv.bz2`. In
+/// `$NIX_LOG_DIR` like Nix itself, or next to `$NIX_STATE_DIR` (`/nix/var/nix` has its logs in
+/// `/nix/var/log/nix`).
+pub fn drv_log_dir() -> &'static str {
+    static DIR: OnceLock<String> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let log_dir = env_dir("NIX_LOG_DIR")
+            .or_else(|| {
+                let state_dir = env_dir("NIX_STATE_DIR")?;
+                let (var, _) = state_dir.rsplit_once('/')?;
+                Some(format!("{var}/log/nix"))
+            })
+            .unwrap_or_else(|| "/nix/var/log/nix".to_string());
+        format!("{log_dir}/drvs")
+    })
+}
+
+/// Full path of a derivation, which `nix ps` gives without the store directory.
+pub fn drv_path(derivation: &str) -> String {
+    if derivation.starts_with('/') {
+        derivation.to_string()
+    } else {
+        format!("{}/{derivation}", store_dir())
+    }
+}
+
+/// The part of a path after the store directory, if it's in the store.
+pub fn strip_store_dir(path: &str) -> Option<&str> {
+    path.strip_prefix(store_dir())?.strip_prefix('/')
+}
+
+/// The parts of a derivation's (or any store path's) name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrvName<'a> {
+    /// Whatever came before the name, if it was a full path.
+    pub store_dir: Option<&'a str>,
+    pub hash: Option<&'a str>,
+    /// Package name and version together, without the hash and `.drv`.
+    pub name: &'a str,
+    pub pname: &'a str,
+    /// Empty when there isn't one.
+    pub version: &'a str,
+}
+
+impl<'a> DrvName<'a> {
+    /// Takes apart a path like `/nix/store/<hash>-hello-2.12.1.drv`. The store directory, hash and
+    /// `.drv` are all optional.
+    pub fn parse(derivation: &'a str) -> Self {
+        let (store_dir, base) = match derivation.rsplit_once('/') {
+            Some((dir, base)) => (Some(dir), base),
+            None => (None, derivation),
+        };
+        let base = base.strip_suffix(".drv").unwrap_or(base);
+        let (hash, name) = match base.split_once('-') {
+            Some((hash, name)) if is_hash(hash) => (Some(hash), name),
+            _ => (None, base),
+        };
+
+        // like `builtins.parseDrvName`, the version starts after the first dash that isn't
+        // followed by a letter
+        let dash = name
+            .char_indices()
+            .zip(name.chars().skip(1))
+            .find(|((_, c), next)| *c == '-' && !next.is_alphabetic())
+            .map(|((i, _), _)| i);
+        let (pname, version) = match dash {
+            Some(i) => (&name[..i], &name[i + 1..]),
+            None => (name, ""),
+        };
+
+        Self {
+            store_dir,
+            hash,
+            name,
+            pname,
+            version,
+        }
+    }
+}
+
+fn is_hash(text: &str) -> bool {
+    text.len() == HASH_LEN && text.chars().all(|c| HASH_CHARS.contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "0c7c1ljqnvjkpkqdkyvb6zzba6l1pc98";
+
+    #[test]
+    fn full_path() {
+        let path = format!("/nix/store/{HASH}-hello-2.12.1.drv");
+        assert_eq!(
+            DrvName::parse(&path),
+            DrvName {
+                store_dir: Some("/nix/store"),
+                hash: Some(HASH),
+                name: "hello-2.12.1",
+                pname: "hello",
+                version: "2.12.1",
+            }
+        );
+    }
+
+    #[test]
+    fn custom_store_dir() {
+        let path = format!("/home/alice/nix/store/{HASH}-hello-2.12.1.drv");
+        let name = DrvName::parse(&path);
+        assert_eq!(name.store_dir, Some("/home/alice/nix/store"));
+        assert_eq!(name.hash, Some(HASH));
+        assert_eq!((name.pname, name.version), ("hello", "2.12.1"));
+    }
+
+    #[test]
+    fn without_store_dir_or_drv() {
+        let path = format!("{HASH}-hello-2.12.1");
+        let name = DrvName::parse(&path);
+        assert_eq!(name.store_dir, None);
+        assert_eq!(name.hash, Some(HASH));
+        assert_eq!(name.name, "hello-2.12.1");
+        assert_eq!((name.pname, name.version), ("hello", "2.12.1"));
+    }
+
+    #[test]
+    fn without_hash() {
+        let name = DrvName::parse("hello-2.12.1.drv");
+        assert_eq!(name.hash, None);
+        assert_eq!((name.pname, name.version), ("hello", "2.12.1"));
+
+        // too short to be one
+        let name = DrvName::parse("abc-hello-2.12.1.drv");
+        assert_eq!(name.hash, None);
+        assert_eq!((name.pname, name.version), ("abc-hello", "2.12.1"));
+    }
+
+    #[test]
+    fn version_with_suffix() {
+        let path = format!("{HASH}-foo-1.2-dev.drv");
+        let name = DrvName::parse(&path);
+        assert_eq!((name.pname, name.version), ("foo", "1.2-dev"));
+    }
+
+    #[test]
+    fn dashes_in_pname() {
+        let path = format!("{HASH}-python3.12-requests-2.31.0.drv");
+        let name = DrvName::parse(&path);
+        assert_eq!(
+            (name.pname, name.version),
+            ("python3.12-requests", "2.31.0")
+        );
+    }
+
+    #[test]
+    fn no_version() {
+        let path = format!("{HASH}-source.drv");
+        let name = DrvName::parse(&path);
+        assert_eq!((name.pname, name.version), ("source", ""));
+
+        let path = format!("{HASH}-nixos-system.drv");
+        let name = DrvName::parse(&path);
+        assert_eq!((name.pname, name.version), ("nixos-system", ""));
+    }
+
+    #[test]
+    fn drv_path_adds_store_dir() {
+        let derivation = format!("{HASH}-hello-2.12.1.drv");
+        assert_eq!(
+            drv_path(&derivation),
+            format!("{}/{derivation}", store_dir())
+        );
+        let full = format!("/somewhere/else/{derivation}");
+        assert_eq!(drv_path(&full), full);
+    }
+}
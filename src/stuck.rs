@@ -0,0 +1,81 @@
+//! Spotting builds that stopped doing anything, like fetches waiting on a dead connection.
+
+use crate::ps;
+use std::collections::HashMap;
+
+/// By host and Nix PID.
+type Key = (Option<String>, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Idle for a while, might just be waiting on something.
+    Idle,
+    /// Idle for twice as long, probably hung.
+    Stuck,
+}
+
+#[derive(Debug, Default)]
+pub struct StuckBuilds {
+    /// How many refreshes in a row a build has to be idle to be flagged, 0 to never flag.
+    after: u32,
+    /// CPU time and I/O totals of each build in the previous refresh.
+    previous: HashMap<Key, (i64, Option<ps::Io>)>,
+    /// Refreshes in a row each build used no CPU and did no I/O.
+    idle: HashMap<Key, u32>,
+}
+
+impl StuckBuilds {
+    pub fn new(after: u32) -> Self {
+        Self {
+            after,
+            ..Self::default()
+        }
+    }
+
+    /// Compares the builds with the previous refresh. Returns the ones that just became stuck.
+    pub fn update<'a>(&mut self, builds: &'a [ps::Build]) -> Vec<&'a ps::Build> {
+        let mut newly_stuck = vec![];
+        let mut current = HashMap::new();
+        let mut idle = HashMap::new();
+
+        // the local side of a delegated build is just ssh waiting, which says nothing
+        for build in builds.iter().filter(|b| !b.delegated) {
+            let key = (build.host.clone(), build.nix_pid);
+            let totals = (build.cpu_time().num_milliseconds(), build.io());
+            let count = match self.previous.get(&key) {
+                Some(previous) if *previous == totals => {
+                    self.idle.get(&key).copied().unwrap_or(0) + 1
+                }
+                _ => 0,
+            };
+
+            if self.after > 0 && count == self.after * 2 {
+                newly_stuck.push(build);
+            }
+            current.insert(key.clone(), totals);
+            idle.insert(key, count);
+        }
+
+        self.previous = current;
+        self.idle = idle;
+        newly_stuck
+    }
+
+    /// How many refreshes in a row the build has been idle.
+    pub fn idle_for(&self, build: &ps::Build) -> u32 {
+        self.idle
+            .get(&(build.host.clone(), build.nix_pid))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn level(&self, build: &ps::Build) -> Option<Level> {
+        let idle = self.idle_for(build);
+        match self.after {
+            0 => None,
+            after if idle >= after * 2 => Some(Level::Stuck),
+            after if idle >= after => Some(Level::Idle),
+            _ => None,
+        }
+    }
+}
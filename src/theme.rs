@@ -0,0 +1,165 @@
+use crate::{category::Category, glyphs};
+use ratatui::{style::Color, symbols::border};
+use serde::{Deserialize, Deserializer, de::Error};
+
+/// Defines the theme struct along with a same-shaped struct of optional overrides for the config.
+macro_rules! theme {
+    ($($(#[doc = $doc:literal])* $name:ident,)*) => {
+        /// Every color used in the UI.
+        #[derive(Debug, Clone)]
+        pub struct Theme {
+            $($(#[doc = $doc])* pub $name: Color,)*
+        }
+
+        /// Colors overridden in the config file, on top of a built-in theme.
+        #[derive(Debug, Default, Deserialize)]
+        #[serde(default, deny_unknown_fields)]
+        pub struct Colors {
+            $(#[serde(deserialize_with = "color")] $name: Option<Color>,)*
+        }
+
+        impl Theme {
+            fn with(mut self, colors: &Colors) -> Self {
+                $(if let Some(color) = colors.$name {
+                    self.$name = color;
+                })*
+                self
+            }
+        }
+    };
+}
+
+theme! {
+    /// Pane borders.
+    border,
+    /// Border of the focused pane.
+    focus,
+    /// Pane titles.
+    title,
+    /// Keys in hints.
+    key,
+    /// Text in hints.
+    hint,
+    /// Background of the selected row.
+    highlight,
+    pname,
+    version,
+    derivation,
+    /// Times, filter text, and other things that should stand out a bit.
+    accent,
+    cpu,
+    memory,
+    /// Builds that look idle, and other things that might need a look.
+    warning,
+    /// Dangerous stuff, like the signal in the kill confirmation.
+    danger,
+    error_fg,
+    error_bg,
+    /// Kinds of processes in the process tree.
+    compiler,
+    linker,
+    test,
+    fetch,
+    shell,
+}
+
+impl Theme {
+    /// Rounded borders, or ones made of `+`, `-` and `|` when sticking to ASCII.
+    pub fn border_set(&self) -> border::Set<'static> {
+        if glyphs::ascii() {
+            border::Set {
+                top_left: "+",
+                top_right: "+",
+                bottom_left: "+",
+                bottom_right: "+",
+                vertical_left: "|",
+                vertical_right: "|",
+                horizontal_top: "-",
+                horizontal_bottom: "-",
+            }
+        } else {
+            border::ROUNDED
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            border: Color::Black,
+            focus: Color::Cyan,
+            title: Color::Cyan,
+            key: Color::Red,
+            hint: Color::White,
+            highlight: Color::Rgb(19, 57, 117),
+            pname: Color::LightGreen,
+            version: Color::LightCyan,
+            derivation: Color::Magenta,
+            accent: Color::Yellow,
+            cpu: Color::Cyan,
+            memory: Color::Magenta,
+            warning: Color::LightYellow,
+            danger: Color::Red,
+            error_fg: Color::White,
+            error_bg: Color::Red,
+            compiler: Color::LightBlue,
+            linker: Color::LightMagenta,
+            test: Color::Green,
+            fetch: Color::LightRed,
+            shell: Color::DarkGray,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            border: Color::Gray,
+            focus: Color::Blue,
+            title: Color::Blue,
+            key: Color::Red,
+            hint: Color::Black,
+            highlight: Color::Rgb(198, 219, 252),
+            pname: Color::Green,
+            version: Color::Blue,
+            derivation: Color::Magenta,
+            accent: Color::Rgb(175, 95, 0),
+            cpu: Color::Blue,
+            memory: Color::Magenta,
+            warning: Color::Rgb(215, 135, 0),
+            danger: Color::Red,
+            error_fg: Color::White,
+            error_bg: Color::Red,
+            compiler: Color::Blue,
+            linker: Color::Magenta,
+            test: Color::Green,
+            fetch: Color::Rgb(175, 95, 0),
+            shell: Color::DarkGray,
+        }
+    }
+
+    pub fn category(&self, category: Category) -> Color {
+        match category {
+            Category::Compiler => self.compiler,
+            Category::Linker => self.linker,
+            Category::Test => self.test,
+            Category::Fetch => self.fetch,
+            Category::Shell => self.shell,
+        }
+    }
+
+    /// Looks up a built-in theme by name and applies the overrides on top.
+    pub fn new(name: &str, colors: &Colors) -> anyhow::Result<Self> {
+        let base = match name {
+            "dark" => Self::dark(),
+            "light" => Self::light(),
+            _ => anyhow::bail!("unknown theme {name:?}, expected \"dark\" or \"light\""),
+        };
+        Ok(base.with(colors))
+    }
+}
+
+/// Parses colors the same way ratatui does: names, `#rrggbb`, or indexes.
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| D::Error::custom(format!("invalid color {value:?}")))
+}
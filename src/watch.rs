@@ -0,0 +1,85 @@
+//! Noticing builds start and finish right away instead of at the next refresh, by watching the
+//! directory Nix writes build logs to. Needs inotify, so only on Linux.
+
+use std::io;
+
+/// Calls `changed` every time a build log is created (a build started) or finished being written
+/// (a build stopped). Blocks until `changed` returns false, so run it on its own thread.
+#[cfg(target_os = "linux")]
+pub fn watch_logs(mut changed: impl FnMut() -> bool) -> io::Result<()> {
+    use crate::store;
+    use std::{
+        collections::HashSet,
+        ffi::CString,
+        fs::{self, File},
+        io::Read,
+        os::fd::{AsRawFd, FromRawFd},
+    };
+
+    // SAFETY: no pointers involved, and the fd is owned by the file right away
+    let mut inotify = unsafe {
+        let fd = libc::inotify_init1(libc::IN_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        File::from_raw_fd(fd)
+    };
+    let add_watch = |inotify: &File, path: &str, mask: u32| -> io::Result<i32> {
+        let path = CString::new(path)?;
+        // SAFETY: the path is a valid C string that outlives the call
+        let wd = unsafe { libc::inotify_add_watch(inotify.as_raw_fd(), path.as_ptr(), mask) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(wd)
+    };
+
+    // the top directory only gets new subdirectories, the logs are in those
+    let log_dir = store::drv_log_dir();
+    let root = add_watch(&inotify, log_dir, libc::IN_CREATE | libc::IN_ONLYDIR)?;
+    let log_mask = libc::IN_CREATE | libc::IN_CLOSE_WRITE;
+    let mut subdirs = HashSet::new();
+    for entry in fs::read_dir(log_dir)?.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            let path = entry.path();
+            subdirs.extend(add_watch(&inotify, &path.to_string_lossy(), log_mask).ok());
+        }
+    }
+
+    let mut buffer = [0u8; 4096];
+    loop {
+        let read = inotify.read(&mut buffer)?;
+        let mut offset = 0;
+        let mut any = false;
+        // events are a header followed by a NUL padded name of `len` bytes
+        while offset + size_of::<libc::inotify_event>() <= read {
+            // SAFETY: the kernel only writes whole events, and read_unaligned doesn't care about
+            // alignment of the buffer
+            let event: libc::inotify_event =
+                unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr().cast()) };
+            let name_start = offset + size_of::<libc::inotify_event>();
+            let name = &buffer[name_start..name_start + event.len as usize];
+            offset = name_start + event.len as usize;
+
+            if event.wd == root && event.mask & libc::IN_ISDIR != 0 {
+                let name = String::from_utf8_lossy(name.split(|b| *b == 0).next().unwrap_or(name));
+                let path = format!("{log_dir}/{name}");
+                subdirs.extend(add_watch(&inotify, &path, log_mask).ok());
+            } else if subdirs.contains(&event.wd) {
+                any = true;
+            }
+        }
+
+        if any && !changed() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn watch_logs(_changed: impl FnMut() -> bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "watching build logs needs inotify",
+    ))
+}
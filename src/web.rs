@@ -0,0 +1,55 @@
+//! A small web dashboard of the active builds, for glancing at a headless builder from a browser.
+
+use crate::{
+    export::{self, Format},
+    http::{self, Request, Response},
+    metrics, ps,
+    stats::CpuUsage,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::net::TcpListener;
+
+/// The whole page, which gets the builds from `/api/builds` every few seconds.
+const INDEX: &str = include_str!("web.html");
+
+struct State {
+    builds: Arc<ps::Cached>,
+    /// Shared by everyone asking, and only updated when there's a new fetch to compare with.
+    cpu: Mutex<(Option<Instant>, CpuUsage)>,
+}
+
+async fn handle(request: Request, state: Arc<State>) -> Response {
+    match request.path.as_str() {
+        "/" => Response::ok("text/html; charset=utf-8", INDEX.to_string()),
+        "/api/builds" => match state.builds.get().await {
+            Ok((fetched, builds)) => {
+                let mut cpu = state.cpu.lock().unwrap_or_else(|e| e.into_inner());
+                let (updated, cpu) = &mut *cpu;
+                if *updated != Some(fetched) {
+                    cpu.update(&builds);
+                    *updated = Some(fetched);
+                }
+                match export::serialize(&builds, cpu, Format::Json) {
+                    Ok(json) => Response::ok("application/json", json),
+                    Err(err) => Response::error(err),
+                }
+            }
+            Err(err) => Response::error(err),
+        },
+        // `/metrics` too, so one port is enough
+        _ => metrics::handle(request, state.builds.clone()).await,
+    }
+}
+
+/// Serves the dashboard forever. Like metrics, requests share the last `nix ps` while it's
+/// recent.
+pub async fn serve(listener: TcpListener, builds: ps::Cached) -> std::io::Result<()> {
+    let state = Arc::new(State {
+        builds: Arc::new(builds),
+        cpu: Mutex::default(),
+    });
+    http::serve(listener, move |request| handle(request, state.clone())).await
+}
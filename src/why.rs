@@ -0,0 +1,51 @@
+//! Finding out why something is being built, i.e. what pulls it in, with `nix why-depends`.
+
+use crate::store::{self, DrvName};
+use anyhow::Context;
+use std::{path::Path, process::Stdio};
+use tokio::process::Command;
+
+/// The chain of derivations from `target` down to `derivation`, as names without the hash.
+/// Relative flake references are resolved from `cwd`.
+pub async fn why_depends(
+    mut nix: Command,
+    target: &str,
+    cwd: Option<&Path>,
+    derivation: &str,
+) -> anyhow::Result<Vec<String>> {
+    nix.arg("why-depends")
+        .arg("--derivation")
+        .arg(target)
+        .arg(store::drv_path(derivation))
+        .stdin(Stdio::null());
+    if let Some(cwd) = cwd {
+        nix.current_dir(cwd);
+    }
+
+    let output = nix.output().await.context("failed to run nix")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // the last line is the actual error, the rest is evaluation noise
+        let error = stderr
+            .lines()
+            .rfind(|l| !l.trim().is_empty())
+            .unwrap_or_default();
+        anyhow::bail!(
+            "nix why-depends exited with {}: {}",
+            output.status,
+            error.trim()
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let chain: Vec<String> = stdout.lines().filter_map(name).collect();
+    anyhow::ensure!(!chain.is_empty(), "{target} doesn't depend on it");
+    Ok(chain)
+}
+
+/// Name of the store path on a line of the tree that `nix why-depends` draws.
+fn name(line: &str) -> Option<String> {
+    let start = line.find(store::store_dir())?;
+    let path = line[start..].split_whitespace().next()?;
+    Some(DrvName::parse(path).name.to_string())
+}